@@ -6,18 +6,44 @@
  */
 
 use pyo3::prelude::*;
-use pyo3::types::PyDict;
+use pyo3::types::{PyDict, PyList};
 use rayon::prelude::*;
-use std::collections::HashMap;
+use chrono::{Datelike, Timelike};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+/// 日期 -> 股票代码 -> 数值 的截面面板数据
+type Panel = HashMap<String, HashMap<String, f64>>;
+
+/// 止损/止盈触发记录 (timestamp, 触发类型, 成交价)
+type ExitTrigger = (i64, &'static str, f64);
 
 /// 交易类型
+#[pyclass(eq, eq_int)]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TradeType {
     Buy,
     Sell,
 }
 
+impl TradeType {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "buy" => Some(TradeType::Buy),
+            "sell" => Some(TradeType::Sell),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            TradeType::Buy => "buy",
+            TradeType::Sell => "sell",
+        }
+    }
+}
+
 /// 订单状态
+#[pyclass(eq, eq_int)]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum OrderStatus {
     Pending,
@@ -27,35 +53,128 @@ pub enum OrderStatus {
 }
 
 /// 订单结构
+#[pyclass]
 #[derive(Debug, Clone)]
 pub struct Order {
+    #[pyo3(get)]
     pub id: String,
+    #[pyo3(get)]
     pub symbol: String,
+    #[pyo3(get)]
     pub trade_type: TradeType,
+    #[pyo3(get)]
     pub price: f64,
+    #[pyo3(get)]
     pub quantity: f64,
+    #[pyo3(get)]
     pub timestamp: i64,
+    #[pyo3(get)]
     pub status: OrderStatus,
 }
 
 /// 持仓结构
+#[pyclass]
 #[derive(Debug, Clone)]
 pub struct Position {
+    #[pyo3(get)]
     pub symbol: String,
+    /// 持仓数量，空头为负
+    #[pyo3(get)]
     pub quantity: f64,
+    /// 开仓均价 (空头为卖出开仓均价)
+    #[pyo3(get)]
     pub avg_price: f64,
+    /// 最近一次盯市的浮动盈亏，`(价格 - 开仓均价) * 持仓数量`，空头价格下跌时为正
+    #[pyo3(get)]
     pub unrealized_pnl: f64,
+    /// 最近一次成交或盯市的价格
+    #[pyo3(get)]
+    pub last_price: f64,
 }
 
 /// 交易记录
+#[pyclass]
 #[derive(Debug, Clone)]
 pub struct Trade {
+    #[pyo3(get)]
     pub symbol: String,
+    #[pyo3(get)]
     pub trade_type: TradeType,
+    #[pyo3(get)]
     pub price: f64,
+    #[pyo3(get)]
     pub quantity: f64,
+    #[pyo3(get)]
     pub timestamp: i64,
+    #[pyo3(get)]
+    pub commission: f64,
+    /// 滑点成本 (成交价相对下单价的偏离 × 数量)
+    #[pyo3(get)]
+    pub slippage: f64,
+    /// 印花税 (仅卖出)
+    #[pyo3(get)]
+    pub tax: f64,
+}
+
+/// 未平仓的开仓批次 (多头为买入，空头为卖出)，平仓时按先进先出 (FIFO) 匹配
+#[derive(Debug, Clone)]
+pub struct Lot {
+    pub price: f64,
+    /// 剩余数量 (恒为正，方向由持仓符号决定)
+    pub quantity: f64,
+    /// 剩余数量对应的开仓手续费 (空头开仓含印花税)
     pub commission: f64,
+    /// 开仓时间戳
+    pub opened_at: i64,
+}
+
+/// 已平仓批次 (一个开仓批次被一次平仓匹配的部分)
+#[derive(Debug, Clone, Copy)]
+pub struct ClosedLot {
+    /// 已实现盈亏 (扣除开平仓手续费与印花税)
+    pub pnl: f64,
+    /// 开仓成本 (开仓价 × 匹配数量)
+    pub entry_cost: f64,
+    pub opened_at: i64,
+    pub closed_at: i64,
+}
+
+impl ClosedLot {
+    /// R 倍数：未设定单笔风险时以开仓成本为 1R，即批次收益率
+    pub fn r_multiple(&self) -> f64 {
+        if self.entry_cost > 0.0 { self.pnl / self.entry_cost } else { 0.0 }
+    }
+}
+
+/// 成本归因：毛收益 = 净收益 + 手续费 + 滑点 + 印花税
+#[derive(Debug, Clone, Default)]
+pub struct CostAttribution {
+    pub gross_return: f64,
+    pub commission_drag: f64,
+    pub slippage_drag: f64,
+    pub tax_drag: f64,
+    pub net_return: f64,
+}
+
+/// 权益曲线上的一个点 (按收盘价逐日盯市)
+#[derive(Debug, Clone, Copy)]
+pub struct EquityPoint {
+    pub timestamp: i64,
+    pub equity: f64,
+}
+
+/// 某一时点的持仓敞口，均为占权益的比例
+#[derive(Debug, Clone, Copy)]
+pub struct Exposure {
+    pub timestamp: i64,
+    /// 多头市值 / 权益
+    pub long: f64,
+    /// 空头市值绝对值 / 权益
+    pub short: f64,
+    /// 多头 - 空头
+    pub net: f64,
+    /// 多头 + 空头
+    pub gross: f64,
 }
 
 /// 回测结果
@@ -69,15 +188,77 @@ pub struct BacktestResult {
     pub sharpe_ratio: f64,
     pub win_rate: f64,
     pub final_capital: f64,
+    pub ulcer_index: f64,
+    pub martin_ratio: Option<f64>,
+    /// 痛苦指数：回撤深度的平均值 (%)
+    pub pain_index: f64,
+    /// 痛苦比率：年化超额收益 / 痛苦指数，痛苦指数为0时为 None
+    pub pain_ratio: Option<f64>,
+    pub sterling_ratio: Option<f64>,
+    /// 逐K线权益收益的 Sortino 比率 (未年化)，没有负收益时为 None
+    pub sortino_ratio: Option<f64>,
+    /// 年化收益率 / 最大回撤，无回撤时为 None
+    pub calmar_ratio: Option<f64>,
+    pub time_in_drawdown_pct: f64,
+    pub cost_attribution: CostAttribution,
+    /// 盈利因子：盈利批次总盈利 / 亏损批次总亏损绝对值，无亏损时为 None
+    pub profit_factor: Option<f64>,
+    /// 每个已平仓批次的平均盈亏，无平仓批次时为 None
+    pub expectancy: Option<f64>,
+    /// 盈利批次的平均盈利，无盈利批次时为 None
+    pub avg_win: Option<f64>,
+    /// 亏损 (含持平) 批次的平均盈亏 (非正数)，无亏损批次时为 None
+    pub avg_loss: Option<f64>,
+    /// 系统质量数 SQN = sqrt(n) * mean(R) / std(R)，批次不足2个或 R 无波动时为 None
+    pub sqn: Option<f64>,
+    /// 每持仓K线的期望 R：各批次 R 倍数之和 / 各批次持仓K线数之和，无平仓批次时为 None
+    pub expectancy_per_bar: Option<f64>,
+    /// 每个已平仓批次的已实现盈亏 (扣除买卖双方手续费与印花税)
+    pub realized_pnls: Vec<f64>,
+    /// 按平仓时刻的本地小时 (0-23) 汇总的已实现盈亏
+    pub pnl_by_hour: BTreeMap<u32, f64>,
+    /// 按平仓日期的本地星期 (0 为周一，6 为周日) 汇总的已实现盈亏
+    pub pnl_by_weekday: BTreeMap<u32, f64>,
 }
 
 /// 回测引擎
+#[pyclass]
 pub struct BacktestEngine {
     capital: f64,
     positions: HashMap<String, Position>,
     trades: Vec<Trade>,
     current_capital: f64,
     commission_rate: f64,
+    /// 单笔最低手续费
+    commission_min: f64,
+    /// 单笔固定手续费
+    commission_fixed: f64,
+    slippage_rate: f64,
+    tax_rate: f64,
+    settlement_days: i64,
+    /// 每年周期数 (年化夏普比率用)
+    periods_per_year: f64,
+    /// 每周期无风险收益率
+    risk_free_rate: f64,
+    /// 是否允许卖空 (无持仓或持仓不足时卖出开空)
+    allow_short: bool,
+    /// 未交收的卖出款 (可用时间戳, 金额)，已计入 current_capital
+    unsettled: Vec<(i64, f64)>,
+    /// 各股票的未平仓批次 (多头为买入、空头为卖出，按开仓先后排列)
+    lots: HashMap<String, VecDeque<Lot>>,
+    /// 已平仓批次，按平仓顺序排列
+    closed_lots: Vec<ClosedLot>,
+    equity_curve: Vec<EquityPoint>,
+    /// 与权益曲线逐点对应的持仓敞口
+    exposures: Vec<Exposure>,
+    /// 按时段归因盈亏所用时区相对 UTC 的偏移秒数
+    tz_offset_seconds: i64,
+    /// 挂单中的限价单 (按提交先后排列)
+    resting_orders: Vec<Order>,
+    /// 已离开挂单簿的限价单 (状态为 Filled / Rejected / Cancelled)
+    order_history: Vec<Order>,
+    /// 已提交限价单数量 (用于生成订单编号)
+    limit_order_seq: usize,
 }
 
 impl BacktestEngine {
@@ -89,7 +270,135 @@ impl BacktestEngine {
             positions: HashMap::new(),
             trades: Vec::new(),
             commission_rate,
+            commission_min: 0.0,
+            commission_fixed: 0.0,
+            slippage_rate: 0.0,
+            tax_rate: 0.0,
+            settlement_days: 0,
+            periods_per_year: TRADING_DAYS_PER_YEAR,
+            risk_free_rate: 0.0,
+            allow_short: false,
+            unsettled: Vec::new(),
+            lots: HashMap::new(),
+            closed_lots: Vec::new(),
+            equity_curve: Vec::new(),
+            exposures: Vec::new(),
+            tz_offset_seconds: 0,
+            resting_orders: Vec::new(),
+            order_history: Vec::new(),
+            limit_order_seq: 0,
+        }
+    }
+
+    /// 设置卖出资金交收天数 (T+N)
+    ///
+    /// 卖出款计入现金，但需到卖出时间戳之后 `settlement_days` 天才能用于买入
+    pub fn with_settlement(mut self, settlement_days: i64) -> Self {
+        self.settlement_days = settlement_days;
+        self
+    }
+
+    /// 设置夏普比率的年化周期数与每周期无风险收益率 (默认 252 与 0，对应日线)
+    pub fn with_annualization(mut self, periods_per_year: f64, risk_free_rate: f64) -> Self {
+        self.periods_per_year = periods_per_year;
+        self.risk_free_rate = risk_free_rate;
+        self
+    }
+
+    /// 设置按平仓时段归因盈亏所用的时区 (相对 UTC 的偏移秒数，如北京时间 28800)
+    pub fn with_timezone(mut self, tz_offset_seconds: i64) -> Self {
+        self.tz_offset_seconds = tz_offset_seconds;
+        self
+    }
+
+    /// 允许卖空
+    ///
+    /// 卖出数量超过多头持仓时开空 (卖出款扣除费用后计入现金)，之后的买入先平空头
+    pub fn with_short_selling(mut self, allow_short: bool) -> Self {
+        self.allow_short = allow_short;
+        self
+    }
+
+    /// 截至 `timestamp` 仍未交收的卖出款
+    pub fn unsettled_cash(&self, timestamp: i64) -> f64 {
+        self.unsettled.iter()
+            .filter(|(available_at, _)| *available_at > timestamp)
+            .map(|(_, amount)| amount)
+            .sum()
+    }
+
+    /// 截至 `timestamp` 已交收、可用于买入的现金
+    pub fn settled_cash(&self, timestamp: i64) -> f64 {
+        self.current_capital - self.unsettled_cash(timestamp)
+    }
+
+    /// 设置单笔最低手续费与固定手续费
+    ///
+    /// 单笔手续费为 `max(commission_min, commission_fixed + 成交金额 * commission_rate)`
+    pub fn with_commission_schedule(mut self, commission_min: f64, commission_fixed: f64) -> Self {
+        self.commission_min = commission_min;
+        self.commission_fixed = commission_fixed;
+        self
+    }
+
+    /// 按成交金额计算单笔手续费
+    fn commission(&self, notional: f64) -> f64 {
+        self.commission_min.max(self.commission_fixed + notional * self.commission_rate)
+    }
+
+    /// 设置滑点率与卖出印花税率
+    ///
+    /// 买入按 `price * (1 + slippage_rate)` 成交，卖出按 `price * (1 - slippage_rate)` 成交
+    pub fn with_costs(mut self, slippage_rate: f64, tax_rate: f64) -> Self {
+        self.slippage_rate = slippage_rate;
+        self.tax_rate = tax_rate;
+        self
+    }
+
+    /// 已平仓批次的已实现盈亏，按平仓顺序排列
+    pub fn realized_pnls(&self) -> Vec<f64> {
+        self.closed_lots.iter().map(|lot| lot.pnl).collect()
+    }
+
+    /// 盯市记录的持仓敞口序列
+    pub fn exposures(&self) -> &[Exposure] {
+        &self.exposures
+    }
+
+    /// 按最新价格盯市，记录一个权益曲线点及持仓敞口
+    ///
+    /// 缺少价格的持仓按最近一次成交或盯市价格计价；空头持仓市值为负。权益不为正时敞口记为0
+    pub fn mark_to_market(&mut self, timestamp: i64, prices: &HashMap<String, f64>) {
+        let (mut long_value, mut short_value) = (0.0, 0.0);
+        for position in self.positions.values_mut() {
+            if let Some(&price) = prices.get(&position.symbol) {
+                position.last_price = price;
+            }
+            let price = position.last_price;
+            position.unrealized_pnl = (price - position.avg_price) * position.quantity;
+            let value = position.quantity * price;
+            if value >= 0.0 {
+                long_value += value;
+            } else {
+                short_value -= value;
+            }
         }
+
+        let equity = self.current_capital + long_value - short_value;
+        self.equity_curve.push(EquityPoint { timestamp, equity });
+
+        let (long, short) = if equity > 0.0 {
+            (long_value / equity, short_value / equity)
+        } else {
+            (0.0, 0.0)
+        };
+        self.exposures.push(Exposure {
+            timestamp,
+            long,
+            short,
+            net: long - short,
+            gross: long + short,
+        });
     }
 
     /// 处理订单
@@ -98,179 +407,563 @@ impl BacktestEngine {
             return None;
         }
 
-        let commission = order.price * order.quantity * self.commission_rate;
+        let fill_price = match order.trade_type {
+            TradeType::Buy => order.price * (1.0 + self.slippage_rate),
+            TradeType::Sell => order.price * (1.0 - self.slippage_rate),
+        };
+        self.execute_order(order, fill_price)
+    }
+
+    /// 按给定成交价执行订单 (不再叠加滑点)，资金或持仓不足时返回 None
+    ///
+    /// 滑点成本记为成交价相对下单价的偏离
+    fn execute_order(&mut self, order: Order, fill_price: f64) -> Option<Trade> {
+        let slippage = (fill_price - order.price).abs() * order.quantity;
+        let commission = self.commission(fill_price * order.quantity);
+        let tax = match order.trade_type {
+            TradeType::Buy => 0.0,
+            TradeType::Sell => fill_price * order.quantity * self.tax_rate,
+        };
 
+        let held = self.positions.get(&order.symbol).map_or(0.0, |p| p.quantity);
         match order.trade_type {
             TradeType::Buy => {
-                let cost = order.price * order.quantity + commission;
-                if cost > self.current_capital {
-                    return None; // 资金不足
+                let cost = fill_price * order.quantity + commission;
+                self.unsettled.retain(|(available_at, _)| *available_at > order.timestamp);
+                if cost > self.settled_cash(order.timestamp) {
+                    return None; // 资金不足 (含未交收卖出款)
                 }
                 self.current_capital -= cost;
-
-                // 更新或创建持仓
-                let position = self.positions.entry(order.symbol.clone()).or_insert(Position {
-                    symbol: order.symbol.clone(),
-                    quantity: 0.0,
-                    avg_price: 0.0,
-                    unrealized_pnl: 0.0,
-                });
-
-                // 重新计算平均价格
-                let total_cost = position.avg_price * position.quantity + order.price * order.quantity;
-                position.quantity += order.quantity;
-                position.avg_price = total_cost / position.quantity;
             }
             TradeType::Sell => {
-                if let Some(position) = self.positions.get_mut(&order.symbol) {
-                    if position.quantity < order.quantity {
-                        return None; // 持仓不足
-                    }
-
-                    let revenue = order.price * order.quantity - commission;
-                    self.current_capital += revenue;
-
-                    // 更新持仓
-                    position.quantity -= order.quantity;
+                if held < order.quantity && !self.allow_short {
+                    return None; // 持仓不足
+                }
 
-                    // 如果持仓为0，移除
-                    if position.quantity <= 0.0 {
-                        self.positions.remove(&order.symbol);
-                    }
-                } else {
-                    return None; // 无持仓
+                let revenue = fill_price * order.quantity - commission - tax;
+                self.current_capital += revenue;
+                if self.settlement_days > 0 {
+                    self.unsettled.push((order.timestamp + self.settlement_days * MS_PER_DAY, revenue));
                 }
             }
         }
 
+        // 先平掉反向持仓 (卖出平多、买入平空)，剩余数量按同方向开仓
+        let (direction, closing) = match order.trade_type {
+            TradeType::Buy => (1.0, order.quantity.min((-held).max(0.0))),
+            TradeType::Sell => (-1.0, order.quantity.min(held.max(0.0))),
+        };
+        let opening = order.quantity - closing;
+        let costs = commission + tax;
+        if closing > 0.0 {
+            self.close_lots(
+                &order.symbol, closing, fill_price, costs * closing / order.quantity, -direction, order.timestamp,
+            );
+        }
+        if opening > 0.0 {
+            self.lots.entry(order.symbol.clone()).or_default().push_back(Lot {
+                price: fill_price,
+                quantity: opening,
+                commission: costs * opening / order.quantity,
+                opened_at: order.timestamp,
+            });
+        }
+
+        // 更新或创建持仓，空头持仓数量为负
+        let position = self.positions.entry(order.symbol.clone()).or_insert(Position {
+            symbol: order.symbol.clone(),
+            quantity: 0.0,
+            avg_price: 0.0,
+            unrealized_pnl: 0.0,
+            last_price: fill_price,
+        });
+        let remaining = held + direction * closing;
+        let quantity = remaining + direction * opening;
+        if opening > 0.0 {
+            // 同方向加仓或反手开仓，重新计算开仓均价
+            position.avg_price = (position.avg_price * remaining.abs() + fill_price * opening)
+                / quantity.abs();
+        }
+        position.quantity = quantity;
+        position.last_price = fill_price;
+
+        // 如果持仓为0，移除
+        if quantity.abs() <= 1e-9 {
+            self.positions.remove(&order.symbol);
+            self.lots.remove(&order.symbol);
+        }
+
         let trade = Trade {
             symbol: order.symbol,
             trade_type: order.trade_type,
-            price: order.price,
+            price: fill_price,
             quantity: order.quantity,
             timestamp: order.timestamp,
             commission,
+            slippage,
+            tax,
         };
 
         self.trades.push(trade.clone());
         Some(trade)
     }
 
-    /// 计算回测结果
-    pub fn calculate_result(&self, final_prices: &HashMap<String, f64>) -> BacktestResult {
-        let total_trades = self.trades.len();
-        let winning_trades = 0; // 需要计算
-        let losing_trades = 0;  // 需要计算
+    /// 按先进先出匹配平仓数量与未平仓批次，逐批次记录已实现盈亏
+    ///
+    /// `side` 为被平批次的方向 (1.0 多头，-1.0 空头)；
+    /// 平仓手续费与印花税 (`closing_costs`) 按匹配数量分摊到各批次
+    fn close_lots(
+        &mut self,
+        symbol: &str,
+        quantity: f64,
+        fill_price: f64,
+        closing_costs: f64,
+        side: f64,
+        timestamp: i64,
+    ) {
+        let lots = match self.lots.get_mut(symbol) {
+            Some(lots) => lots,
+            None => return,
+        };
 
-        let total_return = (self.current_capital / self.capital - 1.0) * 100.0;
-        let max_drawdown = self.calculate_max_drawdown();
+        let mut remaining = quantity;
+        while remaining > 1e-9 {
+            let lot = match lots.front_mut() {
+                Some(lot) => lot,
+                None => break,
+            };
 
-        // 计算胜率（简化版本）
-        let mut win_count = 0;
-        for trade in &self.trades {
-            if trade.trade_type == TradeType::Sell {
-                // 查找对应的买入交易
-                for buy_trade in &self.trades {
-                    if buy_trade.trade_type == TradeType::Buy
-                        && buy_trade.symbol == trade.symbol
-                        && buy_trade.timestamp < trade.timestamp
-                    {
-                        let pnl = (trade.price - buy_trade.price) * trade.quantity
-                            - trade.commission - buy_trade.commission;
-                        if pnl > 0.0 {
-                            win_count += 1;
-                        }
-                        break;
-                    }
-                }
+            let matched = remaining.min(lot.quantity);
+            let opening_costs = lot.commission * matched / lot.quantity;
+            self.closed_lots.push(ClosedLot {
+                pnl: side * (fill_price - lot.price) * matched - opening_costs - closing_costs * matched / quantity,
+                entry_cost: lot.price * matched,
+                opened_at: lot.opened_at,
+                closed_at: timestamp,
+            });
+
+            lot.quantity -= matched;
+            lot.commission -= opening_costs;
+            remaining -= matched;
+            if lot.quantity <= 1e-9 {
+                lots.pop_front();
             }
         }
+    }
 
-        let sell_count = self.trades.iter().filter(|t| t.trade_type == TradeType::Sell).count();
-        let win_rate = if sell_count > 0 {
-            (win_count as f64 / sell_count as f64) * 100.0
-        } else {
-            0.0
-        };
+    /// 挂出限价单，返回订单编号
+    ///
+    /// 订单以 `Pending` 状态挂单，由后续K线的 `fill_resting_orders` 撮合
+    pub fn submit_limit(
+        &mut self,
+        symbol: String,
+        trade_type: TradeType,
+        limit_price: f64,
+        quantity: f64,
+        timestamp: i64,
+    ) -> String {
+        self.limit_order_seq += 1;
+        let id = format!("limit_{}", self.limit_order_seq);
+        self.resting_orders.push(Order {
+            id: id.clone(),
+            symbol,
+            trade_type,
+            price: limit_price,
+            quantity,
+            timestamp,
+            status: OrderStatus::Pending,
+        });
+        id
+    }
 
-        // 计算夏普比率（简化版本，无风险利率设为0）
-        let returns: Vec<f64> = self.trades.chunks(2).filter_map(|pair| {
-            if pair.len() == 2 {
-                let buy = &pair[0];
-                let sell = &pair[1];
-                if buy.trade_type == TradeType::Sell {
-                    // 交换
-                    Some(None)
-                } else if pair[1].trade_type == TradeType::Sell {
-                    let pnl = (sell.price - buy.price) * sell.quantity
-                        - sell.commission - buy.commission;
-                    Some(Some(pnl / self.capital))
-                } else {
-                    Some(None)
-                }
-            } else {
-                Some(None)
-            }
-        }).filter_map(|x| x).collect();
-
-        let sharpe_ratio = if returns.len() > 1 {
-            let avg_return = returns.iter().sum::<f64>() / returns.len() as f64;
-            let variance = returns.iter()
-                .map(|&r| {
-                    let diff = r - avg_return;
-                    diff * diff
-                })
-                .sum::<f64>() / returns.len() as f64;
-            if variance > 0.0 {
-                avg_return / variance.sqrt()
-            } else {
-                0.0
+    /// 撤销挂单，订单不存在时返回 false
+    pub fn cancel_limit(&mut self, id: &str) -> bool {
+        match self.resting_orders.iter().position(|order| order.id == id) {
+            Some(index) => {
+                let order = self.resting_orders.remove(index);
+                self.order_history.push(Order { status: OrderStatus::Cancelled, ..order });
+                true
             }
-        } else {
+            None => false,
+        }
+    }
+
+    /// 当前挂单
+    pub fn resting_orders(&self) -> &[Order] {
+        &self.resting_orders
+    }
+
+    /// 已成交、被拒绝或已撤销的限价单，按离开挂单的先后排列
+    pub fn order_history(&self) -> &[Order] {
+        &self.order_history
+    }
+
+    /// 用一根K线撮合该股票在此之前提交的限价单
+    ///
+    /// 买入限价单在 `low <= 限价` 时成交，卖出限价单在 `high >= 限价` 时成交；
+    /// 开盘即越过限价时按更优的开盘价成交，成交价不叠加滑点。
+    /// 资金或持仓不足的订单被拒绝并移出挂单，离开挂单的订单以最终状态记入订单历史。
+    pub fn fill_resting_orders(
+        &mut self,
+        symbol: &str,
+        timestamp: i64,
+        open: f64,
+        high: f64,
+        low: f64,
+    ) -> Vec<Trade> {
+        let (due, resting): (Vec<Order>, Vec<Order>) = std::mem::take(&mut self.resting_orders)
+            .into_iter()
+            .partition(|order| {
+                order.symbol == symbol
+                    && order.timestamp < timestamp
+                    && match order.trade_type {
+                        TradeType::Buy => low <= order.price,
+                        TradeType::Sell => high >= order.price,
+                    }
+            });
+        self.resting_orders = resting;
+
+        due.into_iter()
+            .filter_map(|order| {
+                let price = match order.trade_type {
+                    TradeType::Buy => order.price.min(open),
+                    TradeType::Sell => order.price.max(open),
+                };
+                let trade = self.execute_order(Order { price, timestamp, ..order.clone() }, price);
+                let status = if trade.is_some() { OrderStatus::Filled } else { OrderStatus::Rejected };
+                self.order_history.push(Order { status, ..order });
+                trade
+            })
+            .collect()
+    }
+
+    /// 计算回测结果
+    ///
+    /// 期末资金为现金加未平仓持仓市值 (空头为负)，持仓按 `final_prices` 计价，
+    /// 缺少价格时按最近一次成交或盯市价格计价
+    pub fn calculate_result(&self, final_prices: &HashMap<String, f64>) -> BacktestResult {
+        let total_trades = self.trades.len();
+        let positions_value: f64 = self.positions.values()
+            .map(|position| {
+                let price = final_prices.get(&position.symbol).copied().unwrap_or(position.last_price);
+                position.quantity * price
+            })
+            .sum();
+        let final_capital = self.current_capital + positions_value;
+        let total_return = (final_capital / self.capital - 1.0) * 100.0;
+        let max_drawdown = self.calculate_max_drawdown();
+
+        // 胜负按已平仓批次的已实现盈亏统计
+        let realized_pnls = self.realized_pnls();
+        let winning_trades = realized_pnls.iter().filter(|&&pnl| pnl > 0.0).count();
+        let losing_trades = realized_pnls.len() - winning_trades;
+        let win_rate = if realized_pnls.is_empty() {
             0.0
+        } else {
+            (winning_trades as f64 / realized_pnls.len() as f64) * 100.0
         };
 
+        let r_multiples: Vec<f64> = self.closed_lots.iter().map(ClosedLot::r_multiple).collect();
+        let (wins, losses): (Vec<f64>, Vec<f64>) = realized_pnls.iter().partition(|&&pnl| pnl > 0.0);
+        let gross_profit: f64 = wins.iter().sum();
+        let gross_loss: f64 = -losses.iter().sum::<f64>();
+        let (pnl_by_hour, pnl_by_weekday) = pnl_by_exit_time(&self.closed_lots, self.tz_offset_seconds);
+
         BacktestResult {
             total_trades,
-            winning_trades: win_count,
-            losing_trades: sell_count - win_count,
+            winning_trades,
+            losing_trades,
             total_return,
             max_drawdown,
-            sharpe_ratio,
+            sharpe_ratio: self.calculate_sharpe_ratio(),
             win_rate,
-            final_capital: self.current_capital,
+            final_capital,
+            ulcer_index: ulcer_index(&self.equity_curve),
+            martin_ratio: martin_ratio(&self.equity_curve),
+            pain_index: pain_index(&self.equity_curve),
+            pain_ratio: pain_ratio(&self.equity_curve, self.risk_free_rate, self.periods_per_year),
+            sterling_ratio: sterling_ratio(&self.equity_curve),
+            sortino_ratio: sortino_ratio(&self.equity_curve),
+            calmar_ratio: if max_drawdown > 0.0 {
+                Some(annualized_return(&self.equity_curve) / max_drawdown)
+            } else {
+                None
+            },
+            time_in_drawdown_pct: time_in_drawdown_pct(&self.equity_curve),
+            cost_attribution: self.calculate_cost_attribution(),
+            profit_factor: if gross_loss > 0.0 { Some(gross_profit / gross_loss) } else { None },
+            expectancy: average(&realized_pnls),
+            avg_win: average(&wins),
+            avg_loss: average(&losses),
+            sqn: system_quality_number(&r_multiples),
+            expectancy_per_bar: self.expectancy_per_bar(),
+            realized_pnls,
+            pnl_by_hour,
+            pnl_by_weekday,
+        }
+    }
+
+    /// 计算成本归因 (金额)
+    ///
+    /// 期末权益取权益曲线最后一点，未盯市时取现金
+    fn calculate_cost_attribution(&self) -> CostAttribution {
+        let final_equity = self.equity_curve.last()
+            .map(|point| point.equity)
+            .unwrap_or(self.current_capital);
+        let net_return = final_equity - self.capital;
+
+        let commission_drag: f64 = self.trades.iter().map(|t| t.commission).sum();
+        let slippage_drag: f64 = self.trades.iter().map(|t| t.slippage).sum();
+        let tax_drag: f64 = self.trades.iter().map(|t| t.tax).sum();
+
+        CostAttribution {
+            gross_return: net_return + commission_drag + slippage_drag + tax_drag,
+            commission_drag,
+            slippage_drag,
+            tax_drag,
+            net_return,
+        }
+    }
+
+    /// 年化夏普比率
+    ///
+    /// 以权益曲线逐K线收益计算 `sqrt(periods_per_year) * mean(r - rf) / std(r - rf)`，
+    /// std 为总体标准差；收益少于2个或无波动时为0
+    fn calculate_sharpe_ratio(&self) -> f64 {
+        let equity: Vec<f64> = self.equity_curve.iter().map(|p| p.equity).collect();
+        let excess: Vec<f64> = simple_returns(&equity)
+            .into_iter()
+            .map(|r| r - self.risk_free_rate)
+            .collect();
+        if excess.len() < 2 {
+            return 0.0;
+        }
+
+        let std = std_dev(&excess);
+        if std > 1e-12 {
+            self.periods_per_year.sqrt() * mean(&excess) / std
+        } else {
+            0.0
+        }
+    }
+
+    /// 每持仓K线的期望 R
+    ///
+    /// 批次持仓K线数为开仓 (含) 到平仓 (不含) 之间盯市的K线数，至少记1根
+    fn expectancy_per_bar(&self) -> Option<f64> {
+        if self.closed_lots.is_empty() {
+            return None;
         }
+
+        let bars_held: usize = self.closed_lots.iter()
+            .map(|lot| {
+                let start = self.equity_curve.partition_point(|p| p.timestamp < lot.opened_at);
+                let end = self.equity_curve.partition_point(|p| p.timestamp < lot.closed_at);
+                end.saturating_sub(start).max(1)
+            })
+            .sum();
+        let total_r: f64 = self.closed_lots.iter().map(ClosedLot::r_multiple).sum();
+        Some(total_r / bars_held as f64)
     }
 
-    /// 计算最大回撤
+    /// 计算最大回撤 (%)
+    ///
+    /// 基于 `mark_to_market` 记录的盯市权益曲线 (现金 + 持仓市值)，以初始资金为起始峰值；
+    /// 未记录权益曲线时为0
     fn calculate_max_drawdown(&self) -> f64 {
-        let mut max_capital = self.capital;
-        let mut max_drawdown = 0.0;
+        if self.equity_curve.is_empty() {
+            return 0.0;
+        }
 
-        // 简化版本：基于交易序列计算
-        let mut capital = self.capital;
+        let mut curve = Vec::with_capacity(self.equity_curve.len() + 1);
+        curve.push(EquityPoint { timestamp: self.equity_curve[0].timestamp, equity: self.capital });
+        curve.extend_from_slice(&self.equity_curve);
 
-        for trade in &self.trades {
-            match trade.trade_type {
-                TradeType::Buy => {
-                    capital -= trade.price * trade.quantity + trade.commission;
-                }
-                TradeType::Sell => {
-                    capital += trade.price * trade.quantity - trade.commission;
-                }
-            }
+        drawdown_series(&curve).into_iter().fold(0.0, f64::max)
+    }
+}
 
-            if capital > max_capital {
-                max_capital = capital;
-            }
+/// Python 接口：在自定义循环中逐笔下单驱动引擎
+#[pymethods]
+impl BacktestEngine {
+    /// 创建回测引擎
+    ///
+    /// # 参数
+    /// * `initial_capital` - 初始资金
+    /// * `commission_rate` - 手续费率
+    /// * `slippage` - 滑点率 (默认0)
+    /// * `tax_rate` - 卖出印花税率 (默认0)
+    /// * `settlement_days` - 卖出资金交收天数 (默认0)
+    /// * `allow_short` - 是否允许卖空 (默认false)
+    /// * `periods_per_year` - 夏普比率年化周期数 (默认252，日线)
+    /// * `risk_free_rate` - 每周期无风险收益率 (默认0)
+    /// * `commission_min` - 单笔最低手续费 (默认0)
+    /// * `commission_fixed` - 单笔固定手续费 (默认0)
+    /// * `tz_offset_seconds` - 按时段归因盈亏的时区偏移秒数 (默认0，即 UTC)
+    #[new]
+    #[pyo3(signature = (initial_capital, commission_rate, slippage=0.0, tax_rate=0.0, settlement_days=0, allow_short=false, periods_per_year=252.0, risk_free_rate=0.0, commission_min=0.0, commission_fixed=0.0, tz_offset_seconds=0))]
+    #[allow(clippy::too_many_arguments)]
+    fn py_new(
+        initial_capital: f64,
+        commission_rate: f64,
+        slippage: f64,
+        tax_rate: f64,
+        settlement_days: i64,
+        allow_short: bool,
+        periods_per_year: f64,
+        risk_free_rate: f64,
+        commission_min: f64,
+        commission_fixed: f64,
+        tz_offset_seconds: i64,
+    ) -> Self {
+        BacktestEngine::new(initial_capital, commission_rate)
+            .with_commission_schedule(commission_min, commission_fixed)
+            .with_costs(slippage, tax_rate)
+            .with_settlement(settlement_days)
+            .with_short_selling(allow_short)
+            .with_annualization(periods_per_year, risk_free_rate)
+            .with_timezone(tz_offset_seconds)
+    }
 
-            let drawdown = (max_capital - capital) / max_capital * 100.0;
-            if drawdown > max_drawdown {
-                max_drawdown = drawdown;
-            }
+    /// 提交市价单并立即按 `price` 成交
+    ///
+    /// # 参数
+    /// * `symbol` - 股票代码
+    /// * `side` - 方向 ("buy" / "sell")
+    /// * `price` - 下单价格
+    /// * `quantity` - 数量
+    /// * `timestamp` - 毫秒时间戳
+    ///
+    /// # 返回
+    /// 成交记录，资金或持仓不足被拒绝时为 None
+    fn submit_order(
+        &mut self,
+        symbol: String,
+        side: &str,
+        price: f64,
+        quantity: f64,
+        timestamp: i64,
+    ) -> PyResult<Option<Trade>> {
+        let trade_type = TradeType::parse(side).ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                format!("Invalid side: {}", side)
+            )
+        })?;
+        if price <= 0.0 || quantity <= 0.0 {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "price and quantity must be positive"
+            ));
+        }
+
+        Ok(self.process_order(Order {
+            id: format!("{}_{}", side, self.trades.len()),
+            symbol,
+            trade_type,
+            price,
+            quantity,
+            timestamp,
+            status: OrderStatus::Pending,
+        }))
+    }
+
+    /// 挂出限价单，等待后续K线触及限价时成交
+    ///
+    /// # 参数
+    /// * `symbol` - 股票代码
+    /// * `side` - 方向 ("buy" / "sell")
+    /// * `limit_price` - 限价
+    /// * `quantity` - 数量
+    /// * `timestamp` - 毫秒时间戳，只有晚于该时间戳的K线才会撮合
+    ///
+    /// # 返回
+    /// 订单编号
+    fn submit_limit_order(
+        &mut self,
+        symbol: String,
+        side: &str,
+        limit_price: f64,
+        quantity: f64,
+        timestamp: i64,
+    ) -> PyResult<String> {
+        let trade_type = TradeType::parse(side).ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                format!("Invalid side: {}", side)
+            )
+        })?;
+        if limit_price <= 0.0 || quantity <= 0.0 {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "limit_price and quantity must be positive"
+            ));
         }
 
-        max_drawdown
+        Ok(self.submit_limit(symbol, trade_type, limit_price, quantity, timestamp))
+    }
+
+    /// 撤销挂单，订单不存在时返回 False
+    fn cancel_order(&mut self, order_id: &str) -> bool {
+        self.cancel_limit(order_id)
+    }
+
+    /// 当前挂单列表
+    fn open_orders(&self) -> Vec<Order> {
+        self.resting_orders.clone()
+    }
+
+    /// 已成交、被拒绝或已撤销的限价单 (status 为最终状态)
+    #[pyo3(name = "order_history")]
+    fn py_order_history(&self) -> Vec<Order> {
+        self.order_history.clone()
+    }
+
+    /// 推进一根K线：撮合该股票的挂单
+    ///
+    /// # 参数
+    /// * `symbol` - 股票代码
+    /// * `timestamp` - K线毫秒时间戳
+    /// * `open` / `high` / `low` - K线开盘价、最高价、最低价
+    ///
+    /// # 返回
+    /// 本根K线成交的记录
+    fn advance_bar(
+        &mut self,
+        symbol: &str,
+        timestamp: i64,
+        open: f64,
+        high: f64,
+        low: f64,
+    ) -> Vec<Trade> {
+        self.fill_resting_orders(symbol, timestamp, open, high, low)
+    }
+
+    /// 按最新价格盯市，记录权益曲线点
+    #[pyo3(name = "mark_to_market")]
+    fn py_mark_to_market(&mut self, timestamp: i64, prices: HashMap<String, f64>) {
+        self.mark_to_market(timestamp, &prices);
+    }
+
+    /// 当前持仓 (股票代码 -> 持仓)
+    #[pyo3(name = "positions")]
+    fn py_positions(&self) -> HashMap<String, Position> {
+        self.positions.clone()
+    }
+
+    /// 全部成交记录
+    #[pyo3(name = "trades")]
+    fn py_trades(&self) -> Vec<Trade> {
+        self.trades.clone()
+    }
+
+    /// 当前现金
+    #[getter]
+    fn cash(&self) -> f64 {
+        self.current_capital
+    }
+
+    /// 计算回测结果字典 (与 `simple_backtest` 相同的绩效字段)
+    #[pyo3(name = "result")]
+    fn py_result(&self, py: Python<'_>, final_prices: HashMap<String, f64>) -> PyResult<PyObject> {
+        let result = self.calculate_result(&final_prices);
+        Ok(result_dict(py, self, &result)?.into())
     }
 }
 
@@ -281,48 +974,199 @@ impl BacktestEngine {
 /// * `initial_capital` - 初始资金
 /// * `commission_rate` - 手续费率
 /// * `strategy` - 策略类型 ("sma_cross", "momentum", "mean_reversion")
-/// * `params` - 策略参数 (JSON字符串)
+/// * `params` - 策略参数 (JSON字符串)，通用参数 `slippage` (滑点率) 与 `tax_rate` (卖出印花税率) 默认为0，
+///   `scale_out` 为分批止盈档位 `[[target_pct, fraction], ...]` (默认不启用)，
+///   `settlement_days` 为卖出资金交收天数 (T+N，默认0即时可用)，
+///   `stop_loss` / `take_profit` 为相对持仓均价的止损/止盈幅度 (如 0.05，默认0不启用)；
+///   sma_cross 在止损/止盈或分批止盈清仓后，需等到出现死叉才会再次金叉买入
+/// * `benchmark` - 与 K 线逐根对齐的基准收盘价 (可选)
+/// * `risk_free_rate` - 每周期无风险收益率 (默认0)，用于夏普比率与累计 Alpha
+/// * `ir_window` - 滚动信息比率的窗口 (周期数，默认20)
+/// * `slippage_bps` - 滑点 (基点，默认0)，买入按 `price * (1 + bps/10000)`、卖出按 `price * (1 - bps/10000)` 成交；
+///   与 `params` 中的 `slippage` 只能二选一
+/// * `periods_per_year` - 每年周期数 (默认252，日线；周线52、月线12)，用于年化夏普比率与滚动信息比率
+/// * `autocorr_window` / `autocorr_lag` - 权益收益滚动自相关的窗口与滞后阶数 (默认20与1)
+/// * `commission_min` / `commission_fixed` - 单笔最低手续费与固定手续费 (默认0)，
+///   单笔手续费为 `max(commission_min, commission_fixed + 成交金额 * commission_rate)`
+/// * `tz_offset_seconds` - 按平仓时段归因盈亏的时区偏移秒数 (默认0即 UTC，北京时间为 28800)
 ///
 /// # 返回
-/// 回测结果字典
+/// 回测结果字典，`cost_attribution` 为成本归因 (金额及占初始资金百分比)；
+/// 提供基准时 `cumulative_alpha_curve` 为逐周期累计 Jensen Alpha，
+/// `rolling_information_ratio` 为逐周期滚动年化信息比率，否则均为 None；
+/// `exit_triggers` 为止损/止盈触发记录 (timestamp, trigger, price)；
+/// `rolling_return_autocorr` 为权益收益的滚动自相关 (预热期及零方差窗口为 None)；
+/// `exposure_summary` 为逐K线的多头、空头、净、总敞口 (long/short/net/gross，占权益比例)；
+/// `trades` 为逐笔成交记录 (symbol, side, price, quantity, timestamp, commission)；
+/// `markers` 为图表买卖点标记，`bar_index` / `price` / `side` 为与成交逐笔对应的平行列表；
+/// `pnl_by_hour` / `pnl_by_weekday` 为按平仓本地小时 (0-23) 与星期 (0 为周一) 汇总的已实现盈亏
 #[pyfunction]
+#[pyo3(signature = (klines, initial_capital, commission_rate, strategy, params, benchmark=None, risk_free_rate=0.0, ir_window=20, slippage_bps=0.0, periods_per_year=252.0, autocorr_window=20, autocorr_lag=1, commission_min=0.0, commission_fixed=0.0, tz_offset_seconds=0))]
+#[allow(clippy::too_many_arguments)]
 fn simple_backtest(
     klines: Vec<(i64, f64, f64, f64, f64, f64)>,
     initial_capital: f64,
     commission_rate: f64,
     strategy: &str,
     params: &str,
+    benchmark: Option<Vec<f64>>,
+    risk_free_rate: f64,
+    ir_window: usize,
+    slippage_bps: f64,
+    periods_per_year: f64,
+    autocorr_window: usize,
+    autocorr_lag: usize,
+    commission_min: f64,
+    commission_fixed: f64,
+    tz_offset_seconds: i64,
 ) -> PyResult<PyObject> {
-    let mut engine = BacktestEngine::new(initial_capital, commission_rate);
+    if let Some(benchmark) = &benchmark {
+        if benchmark.len() != klines.len() {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "Benchmark must have the same length as klines"
+            ));
+        }
+    }
+
+    let (engine, exit_triggers) = run_strategy(
+        &klines,
+        initial_capital,
+        commission_rate,
+        strategy,
+        params,
+        risk_free_rate,
+        slippage_bps,
+        periods_per_year,
+        commission_min,
+        commission_fixed,
+        0,
+    )?;
+    let engine = engine.with_timezone(tz_offset_seconds);
 
+    let result = engine.calculate_result(&HashMap::new());
+    let strategy_equity: Vec<f64> = engine.equity_curve.iter().map(|p| p.equity).collect();
+    let strategy_returns = simple_returns(&strategy_equity);
+    let market_returns = benchmark.as_deref().map(simple_returns);
+    let alpha_curve = market_returns.as_ref().and_then(|market| {
+        cumulative_alpha_curve(&strategy_returns, market, risk_free_rate)
+    });
+    let information_ratio = market_returns.as_ref().map(|market| {
+        rolling_information_ratio(&strategy_returns, market, ir_window, periods_per_year)
+    });
+
+    Python::with_gil(|py| {
+        let dict = result_dict(py, &engine, &result)?;
+        dict.set_item("cumulative_alpha_curve", alpha_curve)?;
+        dict.set_item("rolling_information_ratio", information_ratio)?;
+        dict.set_item(
+            "rolling_return_autocorr",
+            rolling_autocorrelation(&strategy_returns, autocorr_window, autocorr_lag),
+        )?;
+
+        let triggers = PyList::empty(py);
+        for (timestamp, trigger, price) in exit_triggers {
+            let item = PyDict::new(py);
+            item.set_item("timestamp", timestamp)?;
+            item.set_item("trigger", trigger)?;
+            item.set_item("price", price)?;
+            triggers.append(item)?;
+        }
+        dict.set_item("exit_triggers", triggers)?;
+
+        let (bar_index, prices, sides) = trade_markers(&klines, &engine.trades);
+        let markers = PyDict::new(py);
+        markers.set_item("bar_index", bar_index)?;
+        markers.set_item("price", prices)?;
+        markers.set_item("side", sides)?;
+        dict.set_item("markers", markers)?;
+        Ok(dict.into())
+    })
+}
+
+/// 运行单策略回测，返回回测后的引擎与止损/止盈触发记录 (不持有 GIL，可并行调用)
+///
+/// 参数含义同 `simple_backtest`；前 `warmup_bars` 根K线仅用于指标预热，不交易也不盯市
+#[allow(clippy::too_many_arguments)]
+fn run_strategy(
+    klines: &[(i64, f64, f64, f64, f64, f64)],
+    initial_capital: f64,
+    commission_rate: f64,
+    strategy: &str,
+    params: &str,
+    risk_free_rate: f64,
+    slippage_bps: f64,
+    periods_per_year: f64,
+    commission_min: f64,
+    commission_fixed: f64,
+    warmup_bars: usize,
+) -> PyResult<(BacktestEngine, Vec<ExitTrigger>)> {
     // 解析参数
-    let params_map: HashMap<String, f64> = serde_json::from_str(params)
-        .unwrap_or_else(|_| HashMap::new());
+    let params_map = parse_params(params)?;
+
+    let slippage_rate = match params_map.get("slippage") {
+        Some(_) if slippage_bps != 0.0 => {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "Specify either slippage_bps or params slippage, not both"
+            ));
+        }
+        Some(&rate) => rate,
+        None => slippage_bps / 10_000.0,
+    };
+
+    let mut engine = BacktestEngine::new(initial_capital, commission_rate)
+        .with_commission_schedule(commission_min, commission_fixed)
+        .with_costs(slippage_rate, *params_map.get("tax_rate").unwrap_or(&0.0))
+        .with_settlement(*params_map.get("settlement_days").unwrap_or(&0.0) as i64)
+        .with_annualization(periods_per_year, risk_free_rate);
+    let mut scale_out = ScaleOutPlan::new(parse_scale_out(params)?);
+    let protective_exits = ProtectiveExits::new(&params_map)?;
+    // 止损/止盈触发记录 (timestamp, 触发类型, 成交价)
+    let mut exit_triggers: Vec<ExitTrigger> = Vec::new();
 
     match strategy {
         "sma_cross" => {
-            let short_period = *params_map.get("short_period").unwrap_or(&5.0) as usize;
-            let long_period = *params_map.get("long_period").unwrap_or(&20.0) as usize;
+            let short_period = period_param(&params_map, "short_period", 5)?;
+            let long_period = period_param(&params_map, "long_period", 20)?;
 
             // 计算移动平均线
-            let short_sma = calculate_sma(&klines, short_period);
-            let long_sma = calculate_sma(&klines, long_period);
+            let short_sma = calculate_sma(klines, short_period);
+            let long_sma = calculate_sma(klines, long_period);
 
             // 生成交易信号
             let mut in_position = false;
+            // 止损/止盈或分批止盈清仓后，需等到死叉出现才允许再次买入
+            let mut reentry_blocked = false;
+            let mut mark_prices = HashMap::new();
 
-            for (i, kline) in klines.iter().enumerate() {
+            for (i, kline) in klines.iter().enumerate().skip(warmup_bars) {
+                mark_prices.insert("TEST".to_string(), kline.4);
                 if i < long_period {
+                    engine.mark_to_market(kline.0, &mark_prices);
                     continue;
                 }
 
+                // 先检查止损/止盈，触发的K线上不再评估策略信号
+                if in_position {
+                    if let Some((trigger, price)) = protective_exits.apply(&mut engine, "TEST", kline) {
+                        exit_triggers.push((kline.0, trigger, price));
+                        in_position = false;
+                        reentry_blocked = true;
+                        engine.mark_to_market(kline.0, &mark_prices);
+                        continue;
+                    }
+                }
+
                 let short_avg = short_sma[i];
                 let long_avg = long_sma[i];
 
                 if let (Some(short), Some(long)) = (short_avg, long_avg) {
-                    if short > long && !in_position {
+                    if short < long {
+                        reentry_blocked = false;
+                    }
+
+                    if short > long && !in_position && !reentry_blocked {
                         // 金叉买入
-                        engine.process_order(Order {
+                        if let Some(trade) = engine.process_order(Order {
                             id: format!("buy_{}", i),
                             symbol: "TEST".to_string(),
                             trade_type: TradeType::Buy,
@@ -330,7 +1174,9 @@ fn simple_backtest(
                             quantity: (initial_capital * 0.95) / kline.4,
                             timestamp: kline.0,
                             status: OrderStatus::Pending,
-                        });
+                        }) {
+                            scale_out.start(trade.price, trade.quantity);
+                        }
                         in_position = true;
                     } else if short < long && in_position {
                         // 死叉卖出
@@ -348,21 +1194,43 @@ fn simple_backtest(
                         in_position = false;
                     }
                 }
+
+                if in_position && scale_out.apply(&mut engine, "TEST", kline.4, kline.0) {
+                    in_position = false;
+                    reentry_blocked = true;
+                }
+
+                engine.mark_to_market(kline.0, &mark_prices);
             }
         }
         "momentum" => {
-            let period = *params_map.get("period").unwrap_or(&10.0) as usize;
+            let period = period_param(&params_map, "period", 10)?;
             let threshold = *params_map.get("threshold").unwrap_or(&0.02);
 
             // 动量策略
-            for i in period..klines.len() {
+            let mut mark_prices = HashMap::new();
+            for kline in klines.iter().take(period).skip(warmup_bars) {
+                mark_prices.insert("TEST".to_string(), kline.4);
+                engine.mark_to_market(kline.0, &mark_prices);
+            }
+
+            for i in period.max(warmup_bars)..klines.len() {
                 let prev_close = klines[i - period].4;
                 let curr_close = klines[i].4;
                 let momentum = (curr_close - prev_close) / prev_close;
+                mark_prices.insert("TEST".to_string(), curr_close);
+
+                // 先检查止损/止盈，触发的K线上不再评估策略信号
+                if let Some((trigger, price)) = protective_exits.apply(&mut engine, "TEST", &klines[i]) {
+                    exit_triggers.push((klines[i].0, trigger, price));
+                    engine.mark_to_market(klines[i].0, &mark_prices);
+                    continue;
+                }
 
                 if momentum > threshold {
                     // 正动量买入
-                    engine.process_order(Order {
+                    let was_flat = !engine.positions.contains_key("TEST");
+                    if let Some(trade) = engine.process_order(Order {
                         id: format!("buy_{}", i),
                         symbol: "TEST".to_string(),
                         trade_type: TradeType::Buy,
@@ -370,7 +1238,11 @@ fn simple_backtest(
                         quantity: (initial_capital * 0.95) / curr_close,
                         timestamp: klines[i].0,
                         status: OrderStatus::Pending,
-                    });
+                    }) {
+                        if was_flat {
+                            scale_out.start(trade.price, trade.quantity);
+                        }
+                    }
                 } else if momentum < -threshold {
                     // 负动量卖出
                     if let Some(pos) = engine.positions.get("TEST") {
@@ -385,6 +1257,12 @@ fn simple_backtest(
                         });
                     }
                 }
+
+                if engine.positions.contains_key("TEST") {
+                    scale_out.apply(&mut engine, "TEST", curr_close, klines[i].0);
+                }
+
+                engine.mark_to_market(klines[i].0, &mark_prices);
             }
         }
         _ => {
@@ -394,47 +1272,3735 @@ fn simple_backtest(
         }
     }
 
-    let result = engine.calculate_result(&HashMap::new());
-
-    Python::with_gil(|py| {
-        let dict = PyDict::new(py);
-        dict.set_item("total_trades", result.total_trades)?;
-        dict.set_item("winning_trades", result.winning_trades)?;
-        dict.set_item("losing_trades", result.losing_trades)?;
-        dict.set_item("total_return", result.total_return)?;
-        dict.set_item("max_drawdown", result.max_drawdown)?;
-        dict.set_item("sharpe_ratio", result.sharpe_ratio)?;
-        dict.set_item("win_rate", result.win_rate)?;
-        dict.set_item("final_capital", result.final_capital)?;
-        Ok(dict.into())
-    })
+    Ok((engine, exit_triggers))
 }
 
-/// 计算简单移动平均线
-fn calculate_sma(
+/// 将成交映射回K线下标，生成图表买卖点标记 (K线下标, 成交价, 方向) 的平行列表
+///
+/// 时间戳重复时取第一根匹配的K线，找不到对应K线的成交被跳过
+fn trade_markers(
     klines: &[(i64, f64, f64, f64, f64, f64)],
-    period: usize,
-) -> Vec<Option<f64>> {
-    let mut result = Vec::with_capacity(klines.len());
+    trades: &[Trade],
+) -> (Vec<usize>, Vec<f64>, Vec<&'static str>) {
+    let mut index_by_timestamp: HashMap<i64, usize> = HashMap::new();
+    for (i, kline) in klines.iter().enumerate() {
+        index_by_timestamp.entry(kline.0).or_insert(i);
+    }
 
-    for i in 0..klines.len() {
-        if i < period - 1 {
-            result.push(None);
-        } else {
-            let sum: f64 = klines[i - period + 1..=i]
-                .iter()
-                .map(|k| k.4) // close price
-                .sum();
-            result.push(Some(sum / period as f64));
+    let mut bar_index = Vec::with_capacity(trades.len());
+    let mut prices = Vec::with_capacity(trades.len());
+    let mut sides = Vec::with_capacity(trades.len());
+    for trade in trades {
+        if let Some(&i) = index_by_timestamp.get(&trade.timestamp) {
+            bar_index.push(i);
+            prices.push(trade.price);
+            sides.push(trade.trade_type.as_str());
         }
     }
-
-    result
+    (bar_index, prices, sides)
 }
 
-/// Python模块定义
-#[pymodule]
-fn tacn_backtest(m: &Bound<'_, PyModule>) -> PyResult<()> {
-    m.add_function(wrap_pyfunction!(simple_backtest, m)?)?;
-    Ok(())
+/// 滚动窗口 (walk-forward) 回测
+///
+/// 以 `train_bars` 根K线为样本内窗口、其后 `test_bars` 根为样本外测试段，窗口每次前移
+/// `test_bars` 根，测试段首尾相接覆盖样本内窗口之后的全部K线 (不足 `test_bars` 的尾段丢弃)。
+/// 每段独立回测，资金与持仓在段首重置；样本内窗口的K线只用于指标预热，
+/// 只在测试段内交易与计算绩效，段末未平仓持仓按最后收盘价计价。
+///
+/// # 参数
+/// * `klines` - K线数据 (timestamp, open, high, low, close, volume)
+/// * `initial_capital` - 每段初始资金
+/// * `commission_rate` - 手续费率
+/// * `strategy` - 策略类型 (同 `simple_backtest`)
+/// * `params` - 策略参数 (JSON字符串，同 `simple_backtest`)
+/// * `train_bars` - 样本内窗口K线数
+/// * `test_bars` - 样本外测试段K线数
+///
+/// # 返回
+/// 字典：`segments` 为各测试段的回测结果字典 (字段同 `BacktestEngine.result`，附 train_start / test_start / test_end K线下标，
+/// test_end 不含)；`aggregate` 汇总段数、复利累计收益 `compounded_return`、平均收益
+/// `mean_return`、平均夏普 `mean_sharpe_ratio`、最差回撤 `worst_max_drawdown`、
+/// 盈利段占比 `positive_segment_pct` (%) 与总成交笔数 `total_trades`
+#[pyfunction]
+fn walk_forward_backtest(
+    klines: Vec<(i64, f64, f64, f64, f64, f64)>,
+    initial_capital: f64,
+    commission_rate: f64,
+    strategy: &str,
+    params: &str,
+    train_bars: usize,
+    test_bars: usize,
+) -> PyResult<PyObject> {
+    if test_bars == 0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "test_bars must be positive"
+        ));
+    }
+    if train_bars + test_bars > klines.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Not enough klines for one train/test window"
+        ));
+    }
+
+    Python::with_gil(|py| {
+        let segments = PyList::empty(py);
+        let mut returns = Vec::new();
+        let mut sharpes = Vec::new();
+        let mut worst_drawdown = 0.0_f64;
+        let mut total_trades = 0usize;
+
+        let mut test_start = train_bars;
+        while test_start + test_bars <= klines.len() {
+            let test_end = test_start + test_bars;
+            let (engine, _) = run_strategy(
+                &klines[test_start - train_bars..test_end],
+                initial_capital,
+                commission_rate,
+                strategy,
+                params,
+                0.0,
+                0.0,
+                TRADING_DAYS_PER_YEAR,
+                0.0,
+                0.0,
+                train_bars,
+            )?;
+            let final_prices = HashMap::from([("TEST".to_string(), klines[test_end - 1].4)]);
+            let result = engine.calculate_result(&final_prices);
+            returns.push(result.total_return);
+            sharpes.push(result.sharpe_ratio);
+            worst_drawdown = worst_drawdown.max(result.max_drawdown);
+            total_trades += result.total_trades;
+
+            let segment = result_dict(py, &engine, &result)?;
+            segment.set_item("train_start", test_start - train_bars)?;
+            segment.set_item("test_start", test_start)?;
+            segment.set_item("test_end", test_end)?;
+            segments.append(segment)?;
+            test_start = test_end;
+        }
+
+        let compounded = returns.iter().fold(1.0, |acc, r| acc * (1.0 + r / 100.0));
+        let positive = returns.iter().filter(|&&r| r > 0.0).count();
+        let aggregate = PyDict::new(py);
+        aggregate.set_item("segments", returns.len())?;
+        aggregate.set_item("compounded_return", (compounded - 1.0) * 100.0)?;
+        aggregate.set_item("mean_return", mean(&returns))?;
+        aggregate.set_item("mean_sharpe_ratio", mean(&sharpes))?;
+        aggregate.set_item("worst_max_drawdown", worst_drawdown)?;
+        aggregate.set_item("positive_segment_pct", positive as f64 / returns.len() as f64 * 100.0)?;
+        aggregate.set_item("total_trades", total_trades)?;
+
+        let dict = PyDict::new(py);
+        dict.set_item("segments", segments)?;
+        dict.set_item("aggregate", aggregate)?;
+        Ok(dict.into())
+    })
+}
+
+/// 网格搜索最多评估的参数组合数
+const MAX_GRID_COMBINATIONS: usize = 10_000;
+
+/// 网格搜索可用的优化目标，`max_drawdown` 越小越好，其余越大越好
+const GRID_OBJECTIVES: [&str; 7] = [
+    "total_return", "sharpe_ratio", "sortino_ratio", "calmar_ratio",
+    "win_rate", "profit_factor", "max_drawdown",
+];
+
+/// 网格搜索参数优化
+///
+/// 对 `param_grid` 中各参数取值的笛卡尔积逐一回测 (rayon 并行，不持有 GIL)，
+/// 按 `objective` 排序，期末未平仓持仓按最后一根K线收盘价计价。
+/// 组合数超过 `MAX_GRID_COMBINATIONS` (10000) 时报错；
+/// 目标值为 None (如无回撤时的 calmar_ratio) 的组合排在最后。
+///
+/// # 参数
+/// * `klines` - K线数据 (timestamp, open, high, low, close, volume)
+/// * `initial_capital` - 初始资金
+/// * `commission_rate` - 手续费率
+/// * `strategy` - 策略类型 (同 `simple_backtest`)
+/// * `param_grid` - JSON 对象，参数名 -> 候选数值列表，如 `{"short_period": [3, 5], "long_period": [20, 30]}`
+/// * `objective` - 优化目标 (默认 "total_return")，可选 sharpe_ratio / sortino_ratio / calmar_ratio /
+///   win_rate / profit_factor / max_drawdown
+/// * `top_n` - 返回的最优组合数 (默认10)
+///
+/// # 返回
+/// 字典：`table` 为按目标排序的全部组合，`top` 为前 `top_n` 个，每行含 `params`、`score`
+/// 与 total_return / sharpe_ratio / max_drawdown / win_rate / total_trades / final_capital；
+/// `combinations` 为组合总数
+#[pyfunction]
+#[pyo3(signature = (klines, initial_capital, commission_rate, strategy, param_grid, objective="total_return", top_n=10))]
+#[allow(clippy::too_many_arguments)]
+fn optimize_backtest(
+    py: Python<'_>,
+    klines: Vec<(i64, f64, f64, f64, f64, f64)>,
+    initial_capital: f64,
+    commission_rate: f64,
+    strategy: &str,
+    param_grid: &str,
+    objective: &str,
+    top_n: usize,
+) -> PyResult<PyObject> {
+    if !GRID_OBJECTIVES.contains(&objective) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            format!("Unknown objective: {}", objective)
+        ));
+    }
+
+    let mut axes: Vec<(String, Vec<f64>)> = Vec::new();
+    for (name, values) in parse_params_object(param_grid)? {
+        let values: Option<Vec<f64>> = values.as_array()
+            .filter(|values| !values.is_empty())
+            .and_then(|values| values.iter().map(serde_json::Value::as_f64).collect());
+        let values = values.ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                format!("Invalid param_grid: {} must be a non-empty list of numbers", name)
+            )
+        })?;
+        axes.push((name, values));
+    }
+    let combinations = axes.iter()
+        .try_fold(1usize, |acc, (_, values)| acc.checked_mul(values.len()))
+        .filter(|&n| n <= MAX_GRID_COMBINATIONS)
+        .ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                format!("param_grid exceeds {} combinations", MAX_GRID_COMBINATIONS)
+            )
+        })?;
+
+    // 笛卡尔积展开，末位参数变化最快
+    let mut grid: Vec<Vec<(String, f64)>> = vec![Vec::new()];
+    for (name, values) in &axes {
+        grid = grid.into_iter()
+            .flat_map(|combo| values.iter().map(move |&value| {
+                let mut combo = combo.clone();
+                combo.push((name.clone(), value));
+                combo
+            }))
+            .collect();
+    }
+    debug_assert_eq!(grid.len(), combinations);
+
+    let final_prices: HashMap<String, f64> = klines.last()
+        .map(|kline| HashMap::from([("TEST".to_string(), kline.4)]))
+        .unwrap_or_default();
+    let results: Vec<BacktestResult> = py.allow_threads(|| {
+        grid.par_iter()
+            .map(|combo| {
+                let params = serde_json::Value::Object(
+                    combo.iter().map(|(name, value)| (name.clone(), serde_json::json!(value))).collect(),
+                ).to_string();
+                let (engine, _) = run_strategy(
+                    &klines, initial_capital, commission_rate, strategy, &params,
+                    0.0, 0.0, TRADING_DAYS_PER_YEAR, 0.0, 0.0, 0,
+                )?;
+                Ok(engine.calculate_result(&final_prices))
+            })
+            .collect::<PyResult<Vec<_>>>()
+    })?;
+
+    let score = |result: &BacktestResult| -> Option<f64> {
+        match objective {
+            "total_return" => Some(result.total_return),
+            "sharpe_ratio" => Some(result.sharpe_ratio),
+            "sortino_ratio" => result.sortino_ratio,
+            "calmar_ratio" => result.calmar_ratio,
+            "win_rate" => Some(result.win_rate),
+            "profit_factor" => result.profit_factor,
+            _ => Some(result.max_drawdown),
+        }
+    };
+    let lower_is_better = objective == "max_drawdown";
+    let mut order: Vec<usize> = (0..results.len()).collect();
+    order.sort_by(|&a, &b| match (score(&results[a]), score(&results[b])) {
+        (Some(x), Some(y)) if lower_is_better => x.partial_cmp(&y).unwrap_or(std::cmp::Ordering::Equal),
+        (Some(x), Some(y)) => y.partial_cmp(&x).unwrap_or(std::cmp::Ordering::Equal),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+
+    let table = PyList::empty(py);
+    for &i in &order {
+        let result = &results[i];
+        let params = PyDict::new(py);
+        for (name, value) in &grid[i] {
+            params.set_item(name, value)?;
+        }
+        let row = PyDict::new(py);
+        row.set_item("params", params)?;
+        row.set_item("score", score(result))?;
+        row.set_item("total_return", result.total_return)?;
+        row.set_item("sharpe_ratio", result.sharpe_ratio)?;
+        row.set_item("max_drawdown", result.max_drawdown)?;
+        row.set_item("win_rate", result.win_rate)?;
+        row.set_item("total_trades", result.total_trades)?;
+        row.set_item("final_capital", result.final_capital)?;
+        table.append(row)?;
+    }
+
+    let dict = PyDict::new(py);
+    dict.set_item("top", table.get_slice(0, top_n.min(table.len())))?;
+    dict.set_item("table", table)?;
+    dict.set_item("combinations", combinations)?;
+    Ok(dict.into())
+}
+
+/// 引擎通用的回测结果字典：绩效指标、成本归因、敞口与已实现盈亏
+fn result_dict<'py>(
+    py: Python<'py>,
+    engine: &BacktestEngine,
+    result: &BacktestResult,
+) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("total_trades", result.total_trades)?;
+    dict.set_item("winning_trades", result.winning_trades)?;
+    dict.set_item("losing_trades", result.losing_trades)?;
+    dict.set_item("total_return", result.total_return)?;
+    dict.set_item("max_drawdown", result.max_drawdown)?;
+    dict.set_item("sharpe_ratio", result.sharpe_ratio)?;
+    dict.set_item("win_rate", result.win_rate)?;
+    dict.set_item("final_capital", result.final_capital)?;
+    dict.set_item("ulcer_index", result.ulcer_index)?;
+    dict.set_item("martin_ratio", result.martin_ratio)?;
+    dict.set_item("pain_index", result.pain_index)?;
+    dict.set_item("pain_ratio", result.pain_ratio)?;
+    dict.set_item("sterling_ratio", result.sterling_ratio)?;
+    dict.set_item("sortino_ratio", result.sortino_ratio)?;
+    dict.set_item("calmar_ratio", result.calmar_ratio)?;
+    dict.set_item("time_in_drawdown_pct", result.time_in_drawdown_pct)?;
+
+    let costs = &result.cost_attribution;
+    let cost_dict = PyDict::new(py);
+    for (key, value) in [
+        ("gross_return", costs.gross_return),
+        ("commission_drag", costs.commission_drag),
+        ("slippage_drag", costs.slippage_drag),
+        ("tax_drag", costs.tax_drag),
+        ("net_return", costs.net_return),
+    ] {
+        cost_dict.set_item(key, value)?;
+        cost_dict.set_item(format!("{}_pct", key), value / engine.capital * 100.0)?;
+    }
+    dict.set_item("cost_attribution", cost_dict)?;
+
+    let exposure_dict = PyDict::new(py);
+    let exposures = engine.exposures();
+    exposure_dict.set_item("long", exposures.iter().map(|e| e.long).collect::<Vec<_>>())?;
+    exposure_dict.set_item("short", exposures.iter().map(|e| e.short).collect::<Vec<_>>())?;
+    exposure_dict.set_item("net", exposures.iter().map(|e| e.net).collect::<Vec<_>>())?;
+    exposure_dict.set_item("gross", exposures.iter().map(|e| e.gross).collect::<Vec<_>>())?;
+    dict.set_item("exposure_summary", exposure_dict)?;
+    dict.set_item("profit_factor", result.profit_factor)?;
+    dict.set_item("expectancy", result.expectancy)?;
+    dict.set_item("avg_win", result.avg_win)?;
+    dict.set_item("avg_loss", result.avg_loss)?;
+    dict.set_item("sqn", result.sqn)?;
+    dict.set_item("expectancy_per_bar", result.expectancy_per_bar)?;
+    dict.set_item("realized_pnls", &result.realized_pnls)?;
+    dict.set_item("pnl_by_hour", &result.pnl_by_hour)?;
+    dict.set_item("pnl_by_weekday", &result.pnl_by_weekday)?;
+
+    let trades = PyList::empty(py);
+    for trade in &engine.trades {
+        let item = PyDict::new(py);
+        item.set_item("symbol", &trade.symbol)?;
+        item.set_item("side", trade.trade_type.as_str())?;
+        item.set_item("price", trade.price)?;
+        item.set_item("quantity", trade.quantity)?;
+        item.set_item("timestamp", trade.timestamp)?;
+        item.set_item("commission", trade.commission)?;
+        trades.append(item)?;
+    }
+    dict.set_item("trades", trades)?;
+    Ok(dict)
+}
+
+/// 逐周期简单收益率，前值不为正时记为0
+fn simple_returns(values: &[f64]) -> Vec<f64> {
+    values.windows(2)
+        .map(|w| if w[0] > 0.0 { w[1] / w[0] - 1.0 } else { 0.0 })
+        .collect()
+}
+
+/// 累计 Jensen Alpha 曲线
+///
+/// Beta 以全样本超额收益 OLS 回归 `(r - rf) ~ (m - rf)` 估计，
+/// 每期 `alpha_t = r_t - (rf + beta * (m_t - rf))`，按期简单累加。
+/// 序列长度不一致或 Beta 无法估计时为 None
+fn cumulative_alpha_curve(
+    strategy_returns: &[f64],
+    market_returns: &[f64],
+    risk_free_rate: f64,
+) -> Option<Vec<f64>> {
+    if strategy_returns.len() != market_returns.len() {
+        return None;
+    }
+
+    let excess_strategy: Vec<f64> = strategy_returns.iter().map(|r| r - risk_free_rate).collect();
+    let excess_market: Vec<f64> = market_returns.iter().map(|m| m - risk_free_rate).collect();
+    let (_, beta) = ols_alpha_beta(&excess_strategy, &excess_market)?;
+
+    let mut cumulative = 0.0;
+    Some(strategy_returns.iter()
+        .zip(market_returns.iter())
+        .map(|(&r, &m)| {
+            cumulative += r - (risk_free_rate + beta * (m - risk_free_rate));
+            cumulative
+        })
+        .collect())
+}
+
+/// 滚动信息比率
+///
+/// 主动收益 `a_t = r_t - m_t`，窗口内 `mean(a) / std(a) * sqrt(periods_per_year)`，
+/// 跟踪误差为窗口内主动收益的总体标准差。前 `window - 1` 个值及跟踪误差为0时为 None
+fn rolling_information_ratio(
+    strategy_returns: &[f64],
+    market_returns: &[f64],
+    window: usize,
+    periods_per_year: f64,
+) -> Vec<Option<f64>> {
+    let active: Vec<f64> = strategy_returns.iter()
+        .zip(market_returns.iter())
+        .map(|(r, m)| r - m)
+        .collect();
+
+    (0..active.len())
+        .map(|i| {
+            if window == 0 || i + 1 < window {
+                return None;
+            }
+            let slice = &active[i + 1 - window..=i];
+            let tracking_error = std_dev(slice);
+            if tracking_error > 1e-12 {
+                Some(mean(slice) / tracking_error * periods_per_year.sqrt())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// 每年交易日数 (日线年化)
+const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+
+/// 每天毫秒数
+const MS_PER_DAY: i64 = 24 * 3600 * 1000;
+
+/// 每年毫秒数 (K线时间戳为毫秒)
+const MS_PER_YEAR: f64 = 365.25 * 24.0 * 3600.0 * 1000.0;
+
+/// 回撤序列 (%)，每个点相对此前峰值的回撤深度，非负
+fn drawdown_series(equity_curve: &[EquityPoint]) -> Vec<f64> {
+    let mut peak = f64::NEG_INFINITY;
+    equity_curve.iter()
+        .map(|point| {
+            peak = peak.max(point.equity);
+            if peak > 0.0 {
+                (peak - point.equity) / peak * 100.0
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+/// 年化收益率 (%)，按权益曲线首尾时间跨度复利折算
+fn annualized_return(equity_curve: &[EquityPoint]) -> f64 {
+    let (first, last) = match (equity_curve.first(), equity_curve.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return 0.0,
+    };
+
+    let years = (last.timestamp - first.timestamp) as f64 / MS_PER_YEAR;
+    if years <= 0.0 || first.equity <= 0.0 || last.equity <= 0.0 {
+        return 0.0;
+    }
+
+    ((last.equity / first.equity).powf(1.0 / years) - 1.0) * 100.0
+}
+
+/// 溃疡指数 (Ulcer Index)：回撤百分比的均方根
+fn ulcer_index(equity_curve: &[EquityPoint]) -> f64 {
+    if equity_curve.is_empty() {
+        return 0.0;
+    }
+
+    let drawdowns = drawdown_series(equity_curve);
+    let mean_sq = drawdowns.iter().map(|d| d * d).sum::<f64>() / drawdowns.len() as f64;
+    mean_sq.sqrt()
+}
+
+/// Martin 比率：年化收益率 / 溃疡指数，溃疡指数为0时为None
+fn martin_ratio(equity_curve: &[EquityPoint]) -> Option<f64> {
+    let ulcer = ulcer_index(equity_curve);
+    if ulcer > 0.0 {
+        Some(annualized_return(equity_curve) / ulcer)
+    } else {
+        None
+    }
+}
+
+/// 按平仓时刻的本地小时与星期汇总已实现盈亏
+///
+/// 返回 (小时 -> 盈亏, 星期 -> 盈亏)，星期 0 为周一；只包含有平仓的时段
+fn pnl_by_exit_time(
+    closed_lots: &[ClosedLot],
+    tz_offset_seconds: i64,
+) -> (BTreeMap<u32, f64>, BTreeMap<u32, f64>) {
+    let mut by_hour = BTreeMap::new();
+    let mut by_weekday = BTreeMap::new();
+    for lot in closed_lots {
+        let local = match chrono::DateTime::from_timestamp_millis(lot.closed_at + tz_offset_seconds * 1000) {
+            Some(local) => local,
+            None => continue,
+        };
+        *by_hour.entry(local.hour()).or_insert(0.0) += lot.pnl;
+        *by_weekday.entry(local.weekday().num_days_from_monday()).or_insert(0.0) += lot.pnl;
+    }
+    (by_hour, by_weekday)
+}
+
+/// 痛苦指数 (Pain Index)：回撤百分比的算术平均
+fn pain_index(equity_curve: &[EquityPoint]) -> f64 {
+    mean(&drawdown_series(equity_curve))
+}
+
+/// 痛苦比率：(年化收益率 - 年化无风险收益率) / 痛苦指数，痛苦指数为0时为None
+///
+/// 年化无风险收益率由每周期无风险收益率按 `periods_per_year` 复利折算
+fn pain_ratio(equity_curve: &[EquityPoint], risk_free_rate: f64, periods_per_year: f64) -> Option<f64> {
+    let pain = pain_index(equity_curve);
+    if pain > 0.0 {
+        let annual_rf = ((1.0 + risk_free_rate).powf(periods_per_year) - 1.0) * 100.0;
+        Some((annualized_return(equity_curve) - annual_rf) / pain)
+    } else {
+        None
+    }
+}
+
+/// 系统质量数 (Van Tharp SQN)：`sqrt(n) * mean(R) / std(R)`，std 为样本标准差
+///
+/// 少于2个 R 或标准差为0时为None
+fn system_quality_number(r_multiples: &[f64]) -> Option<f64> {
+    let n = r_multiples.len();
+    if n < 2 {
+        return None;
+    }
+    let m = mean(r_multiples);
+    let std = (r_multiples.iter().map(|r| (r - m).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt();
+    if std > 1e-12 {
+        Some((n as f64).sqrt() * m / std)
+    } else {
+        None
+    }
+}
+
+/// Sortino 比率：逐点权益收益均值 / 下行偏差 (目标收益为0，未年化)
+///
+/// 没有负收益 (下行偏差为0) 时为None
+fn sortino_ratio(equity_curve: &[EquityPoint]) -> Option<f64> {
+    let equity: Vec<f64> = equity_curve.iter().map(|p| p.equity).collect();
+    let returns = simple_returns(&equity);
+    let downside = downside_deviation(&returns);
+    if downside > 0.0 {
+        Some(mean(&returns) / downside)
+    } else {
+        None
+    }
+}
+
+/// Sterling 比率：年化收益率 / (各自然年最大回撤均值 + 10%)
+///
+/// 每年的回撤以当年内的峰值计算；权益曲线不足两个自然年时退化为整体最大回撤。
+/// 权益点少于2个时为None
+fn sterling_ratio(equity_curve: &[EquityPoint]) -> Option<f64> {
+    if equity_curve.len() < 2 {
+        return None;
+    }
+
+    let mut years: BTreeMap<i32, Vec<EquityPoint>> = BTreeMap::new();
+    for point in equity_curve {
+        let year = chrono::DateTime::from_timestamp_millis(point.timestamp)
+            .map(|dt| dt.year())
+            .unwrap_or(0);
+        years.entry(year).or_default().push(*point);
+    }
+
+    let max_drawdown = |points: &[EquityPoint]| drawdown_series(points).into_iter().fold(0.0, f64::max);
+    let avg_drawdown = if years.len() < 2 {
+        max_drawdown(equity_curve)
+    } else {
+        years.values().map(|points| max_drawdown(points)).sum::<f64>() / years.len() as f64
+    };
+
+    Some(annualized_return(equity_curve) / (avg_drawdown + 10.0))
+}
+
+/// 回撤时间占比 (%)：权益低于此前峰值的K线数 / 总K线数
+fn time_in_drawdown_pct(equity_curve: &[EquityPoint]) -> f64 {
+    if equity_curve.is_empty() {
+        return 0.0;
+    }
+
+    let underwater = drawdown_series(equity_curve).iter().filter(|&&d| d > 0.0).count();
+    underwater as f64 / equity_curve.len() as f64 * 100.0
+}
+
+/// 非数值的结构化参数，由各自的解析函数处理
+const STRUCTURED_PARAMS: [&str; 1] = ["scale_out"];
+
+/// 解析策略参数 JSON 中的数值参数，空字符串视为使用默认参数
+fn parse_params(params: &str) -> PyResult<HashMap<String, f64>> {
+    let mut result = HashMap::new();
+    for (key, value) in parse_params_object(params)? {
+        if STRUCTURED_PARAMS.contains(&key.as_str()) {
+            continue;
+        }
+        let number = value.as_f64().ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                format!("Invalid params JSON: {} must be a number", key)
+            )
+        })?;
+        result.insert(key, number);
+    }
+    Ok(result)
+}
+
+/// 读取周期类参数，未设置时使用默认值；取整后小于 1 的周期视为无效参数
+fn period_param(params_map: &HashMap<String, f64>, key: &str, default: usize) -> PyResult<usize> {
+    match params_map.get(key) {
+        None => Ok(default),
+        Some(&value) if value >= 1.0 => Ok(value as usize),
+        Some(_) => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            format!("Invalid params JSON: {} must be a positive integer", key)
+        )),
+    }
+}
+
+/// 解析策略参数 JSON 为键值对象
+fn parse_params_object(params: &str) -> PyResult<serde_json::Map<String, serde_json::Value>> {
+    if params.trim().is_empty() {
+        return Ok(serde_json::Map::new());
+    }
+
+    serde_json::from_str(params).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            format!("Invalid params JSON: {}", e)
+        )
+    })
+}
+
+/// 解析分批止盈参数 `scale_out`: `[[target_pct, fraction], ...]`
+///
+/// `target_pct` 为相对开仓价的涨幅 (%)，`fraction` 为卖出开仓数量的比例 (0..1]。
+/// 返回按目标涨幅升序排列的档位，未设置时为空
+fn parse_scale_out(params: &str) -> PyResult<Vec<(f64, f64)>> {
+    let invalid = || {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Invalid params JSON: scale_out must be a list of [target_pct, fraction] pairs with target_pct > 0 and 0 < fraction <= 1"
+        )
+    };
+
+    let object = parse_params_object(params)?;
+    let tiers = match object.get("scale_out") {
+        Some(serde_json::Value::Array(tiers)) => tiers,
+        Some(serde_json::Value::Null) | None => return Ok(Vec::new()),
+        Some(_) => return Err(invalid()),
+    };
+
+    let mut result = Vec::with_capacity(tiers.len());
+    for tier in tiers {
+        let pair = tier.as_array().filter(|pair| pair.len() == 2).ok_or_else(invalid)?;
+        let target = pair[0].as_f64().ok_or_else(invalid)?;
+        let fraction = pair[1].as_f64().ok_or_else(invalid)?;
+        if target <= 0.0 || fraction <= 0.0 || fraction > 1.0 {
+            return Err(invalid());
+        }
+        result.push((target, fraction));
+    }
+    result.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    Ok(result)
+}
+
+/// 止损/止盈保护
+///
+/// 以持仓均价为基准，`stop_loss` / `take_profit` 为相对涨跌幅 (如 0.05)，0 表示不启用
+struct ProtectiveExits {
+    stop_loss: f64,
+    take_profit: f64,
+}
+
+impl ProtectiveExits {
+    fn new(params: &HashMap<String, f64>) -> PyResult<Self> {
+        let stop_loss = *params.get("stop_loss").unwrap_or(&0.0);
+        let take_profit = *params.get("take_profit").unwrap_or(&0.0);
+        if !(0.0..1.0).contains(&stop_loss) || take_profit < 0.0 {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "Invalid params JSON: stop_loss must be in [0, 1) and take_profit must be non-negative"
+            ));
+        }
+        Ok(ProtectiveExits { stop_loss, take_profit })
+    }
+
+    /// 用K线最低/最高价检查多头持仓，触发时全部平仓
+    ///
+    /// 成交价为触发价，开盘即跳空越过触发价时为开盘价；同一根K线同时触及两者时按止损处理。
+    /// 返回触发类型 ("stop_loss" / "take_profit") 与成交价
+    fn apply(
+        &self,
+        engine: &mut BacktestEngine,
+        symbol: &str,
+        kline: &(i64, f64, f64, f64, f64, f64),
+    ) -> Option<(&'static str, f64)> {
+        let &(timestamp, open, high, low, _, _) = kline;
+        let (quantity, entry_price) = match engine.positions.get(symbol) {
+            Some(pos) if pos.quantity > 0.0 => (pos.quantity, pos.avg_price),
+            _ => return None,
+        };
+
+        let stop_price = entry_price * (1.0 - self.stop_loss);
+        let target_price = entry_price * (1.0 + self.take_profit);
+        let (trigger, price) = if self.stop_loss > 0.0 && low <= stop_price {
+            ("stop_loss", open.min(stop_price))
+        } else if self.take_profit > 0.0 && high >= target_price {
+            ("take_profit", open.max(target_price))
+        } else {
+            return None;
+        };
+
+        engine.process_order(Order {
+            id: format!("{}_{}", trigger, timestamp),
+            symbol: symbol.to_string(),
+            trade_type: TradeType::Sell,
+            price,
+            quantity,
+            timestamp,
+            status: OrderStatus::Pending,
+        })?;
+        Some((trigger, price))
+    }
+}
+
+/// 分批止盈计划
+///
+/// 价格达到开仓价上方各档目标时，按开仓数量的对应比例卖出；
+/// 每次开仓对应单一买入批次，剩余持仓即按先进先出匹配后的未平仓数量
+struct ScaleOutPlan {
+    tiers: Vec<(f64, f64)>,
+    filled: Vec<bool>,
+    entry_price: f64,
+    entry_quantity: f64,
+}
+
+impl ScaleOutPlan {
+    fn new(tiers: Vec<(f64, f64)>) -> Self {
+        let filled = vec![true; tiers.len()];
+        ScaleOutPlan { tiers, filled, entry_price: 0.0, entry_quantity: 0.0 }
+    }
+
+    /// 开仓后重置各档状态
+    fn start(&mut self, entry_price: f64, entry_quantity: f64) {
+        self.entry_price = entry_price;
+        self.entry_quantity = entry_quantity;
+        self.filled.iter_mut().for_each(|f| *f = false);
+    }
+
+    /// 按当前价格执行已触发的档位，返回持仓是否已全部卖出
+    fn apply(&mut self, engine: &mut BacktestEngine, symbol: &str, price: f64, timestamp: i64) -> bool {
+        for (tier, &(target_pct, fraction)) in self.tiers.iter().enumerate() {
+            // 比较收益率而非目标价，容忍浮点误差 (如 100 * 1.1 略大于 110)
+            let gain_pct = (price / self.entry_price - 1.0) * 100.0;
+            if self.filled[tier] || gain_pct < target_pct - 1e-9 {
+                continue;
+            }
+
+            let remaining = match engine.positions.get(symbol) {
+                Some(pos) => pos.quantity,
+                None => break,
+            };
+            let mut quantity = (self.entry_quantity * fraction).min(remaining);
+            // 避免浮点误差留下极小的零头持仓
+            if remaining - quantity <= self.entry_quantity * 1e-9 {
+                quantity = remaining;
+            }
+
+            engine.process_order(Order {
+                id: format!("scale_out_{}_{}", tier, timestamp),
+                symbol: symbol.to_string(),
+                trade_type: TradeType::Sell,
+                price,
+                quantity,
+                timestamp,
+                status: OrderStatus::Pending,
+            });
+            self.filled[tier] = true;
+        }
+
+        !engine.positions.contains_key(symbol)
+    }
+}
+
+/// 计算简单移动平均线
+fn calculate_sma(
+    klines: &[(i64, f64, f64, f64, f64, f64)],
+    period: usize,
+) -> Vec<Option<f64>> {
+    let mut result = Vec::with_capacity(klines.len());
+
+    for i in 0..klines.len() {
+        if i < period - 1 {
+            result.push(None);
+        } else {
+            // 先加1再减，避免首个完整窗口 (i = period - 1) 时 `i - period` 下溢
+            let sum: f64 = klines[i + 1 - period..=i]
+                .iter()
+                .map(|k| k.4) // close price
+                .sum();
+            result.push(Some(sum / period as f64));
+        }
+    }
+
+    result
+}
+
+/// 最大不利偏移 (MAE) 分布
+///
+/// 将盈利交易与亏损交易的 MAE 分别统计为直方图，用于确定止损位置
+///
+/// # 参数
+/// * `trade_maes` - 每笔交易的最大不利偏移 (如 0.03 表示持仓期间最多浮亏3%)
+/// * `is_winner` - 每笔交易是否盈利
+/// * `bins` - 直方图分组数
+///
+/// # 返回
+/// 字典: `bin_edges` (bins+1 个分组边界), `winners` / `losers` (每组交易数)
+#[pyfunction]
+fn mae_distribution(
+    trade_maes: Vec<f64>,
+    is_winner: Vec<bool>,
+    bins: usize,
+) -> PyResult<HashMap<String, Vec<f64>>> {
+    if trade_maes.len() != is_winner.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Input arrays must have the same length"
+        ));
+    }
+    if bins == 0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "bins must be greater than 0"
+        ));
+    }
+
+    let mut winners = vec![0.0; bins];
+    let mut losers = vec![0.0; bins];
+
+    let min_mae = trade_maes.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max_mae = trade_maes.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let (min_mae, max_mae) = if trade_maes.is_empty() { (0.0, 0.0) } else { (min_mae, max_mae) };
+    let width = (max_mae - min_mae) / bins as f64;
+
+    let bin_edges: Vec<f64> = (0..=bins).map(|i| min_mae + width * i as f64).collect();
+
+    for (&mae, &winner) in trade_maes.iter().zip(is_winner.iter()) {
+        // 最大值归入最后一组
+        let idx = if width > 0.0 {
+            (((mae - min_mae) / width) as usize).min(bins - 1)
+        } else {
+            0
+        };
+        if winner {
+            winners[idx] += 1.0;
+        } else {
+            losers[idx] += 1.0;
+        }
+    }
+
+    let mut result = HashMap::new();
+    result.insert("bin_edges".to_string(), bin_edges);
+    result.insert("winners".to_string(), winners);
+    result.insert("losers".to_string(), losers);
+    Ok(result)
+}
+
+/// 按日均成交量估算组合容量 (最大管理规模)
+///
+/// 假设组合在一个交易日内完成调仓，股票 i 的日交易额为 `AUM × |w_i|`，
+/// 需不超过 `max_participation × ADV_i × price_i`。容量为各股票上限
+/// `max_participation × ADV_i × price_i / |w_i|` 的最小值，由流动性最差的股票决定。
+///
+/// # 参数
+/// * `avg_daily_volumes` - 每只股票的日均成交量 (股)
+/// * `prices` - 每只股票的价格
+/// * `weights` - 每只股票的目标权重 (权重为0的股票不参与计算)
+/// * `max_participation` - 单只股票日交易量占日均成交量的上限 (0, 1]
+///
+/// # 返回
+/// 组合容量 (与价格同币种的金额)
+#[pyfunction]
+fn capacity_estimate(
+    avg_daily_volumes: HashMap<String, f64>,
+    prices: HashMap<String, f64>,
+    weights: HashMap<String, f64>,
+    max_participation: f64,
+) -> PyResult<f64> {
+    if !(max_participation > 0.0 && max_participation <= 1.0) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "max_participation must be in (0, 1]"
+        ));
+    }
+
+    let mut capacity = f64::INFINITY;
+    for (symbol, &weight) in &weights {
+        if weight == 0.0 {
+            continue;
+        }
+        let (volume, price) = match (avg_daily_volumes.get(symbol), prices.get(symbol)) {
+            (Some(&volume), Some(&price)) => (volume, price),
+            _ => {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                    format!("Missing volume or price for {}", symbol)
+                ));
+            }
+        };
+        capacity = capacity.min(max_participation * volume * price / weight.abs());
+    }
+
+    if capacity.is_infinite() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "At least one non-zero weight is required"
+        ));
+    }
+    Ok(capacity)
+}
+
+/// 各股票对组合方差的风险贡献
+///
+/// 贡献度 = 权重 × 边际贡献 `w_i * (Σw)_i`，各股票贡献之和等于组合方差 `w'Σw`
+/// (Σ 为样本协方差矩阵)。`weights` 中缺失的股票按权重0处理。
+///
+/// # 参数
+/// * `returns_by_symbol` - 每只股票的收益率序列 (长度必须相同)
+/// * `weights` - 每只股票的权重
+///
+/// # 返回
+/// 每只股票的方差贡献
+#[pyfunction]
+fn risk_contribution(
+    returns_by_symbol: HashMap<String, Vec<f64>>,
+    weights: HashMap<String, f64>,
+) -> PyResult<HashMap<String, f64>> {
+    let (symbols, series) = aligned_return_series(&returns_by_symbol)?;
+    let cov = covariance_matrix(&series);
+    let w: Vec<f64> = symbols.iter().map(|s| weights.get(s).copied().unwrap_or(0.0)).collect();
+
+    Ok(symbols
+        .into_iter()
+        .enumerate()
+        .map(|(i, symbol)| {
+            let marginal: f64 = (0..w.len()).map(|j| cov[i][j] * w[j]).sum();
+            (symbol, w[i] * marginal)
+        })
+        .collect())
+}
+
+/// 风险平价或最小方差组合权重 (权重和为1)
+///
+/// * `inverse_vol`: 朴素风险平价，权重与样本波动率成反比
+/// * `min_variance`: 全局最小方差组合 `w = Σ⁻¹1 / (1'Σ⁻¹1)`，允许负权重
+///
+/// # 参数
+/// * `returns_by_symbol` - 每只股票的收益率序列 (长度必须相同)
+/// * `method` - "inverse_vol" 或 "min_variance"
+///
+/// # 返回
+/// 每只股票的权重
+#[pyfunction]
+#[pyo3(signature = (returns_by_symbol, method="inverse_vol"))]
+fn risk_parity_weights(
+    returns_by_symbol: HashMap<String, Vec<f64>>,
+    method: &str,
+) -> PyResult<HashMap<String, f64>> {
+    let (symbols, series) = aligned_return_series(&returns_by_symbol)?;
+    if symbols.is_empty() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "At least one return series is required"
+        ));
+    }
+    let cov = covariance_matrix(&series);
+
+    let raw: Vec<f64> = match method {
+        "inverse_vol" => {
+            if cov.iter().enumerate().any(|(i, row)| row[i] <= 0.0) {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                    "Return series must have non-zero volatility"
+                ));
+            }
+            (0..symbols.len()).map(|i| 1.0 / cov[i][i].sqrt()).collect()
+        }
+        "min_variance" => solve_linear_system(cov, vec![1.0; symbols.len()]).ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>("Covariance matrix is singular")
+        })?,
+        _ => {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                format!("Unknown method: {}", method)
+            ));
+        }
+    };
+
+    let total: f64 = raw.iter().sum();
+    if total.abs() < 1e-15 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Weights cannot be normalized"
+        ));
+    }
+    Ok(symbols.into_iter().zip(raw).map(|(symbol, w)| (symbol, w / total)).collect())
+}
+
+/// 按股票代码排序并校验收益率序列等长 (至少2个观测值)
+fn aligned_return_series(
+    returns_by_symbol: &HashMap<String, Vec<f64>>,
+) -> PyResult<(Vec<String>, Vec<Vec<f64>>)> {
+    let mut symbols: Vec<String> = returns_by_symbol.keys().cloned().collect();
+    symbols.sort();
+
+    let series: Vec<Vec<f64>> = symbols.iter().map(|s| returns_by_symbol[s].clone()).collect();
+    let len = series.first().map(|r| r.len()).unwrap_or(0);
+    if series.iter().any(|r| r.len() != len) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Return series must have the same length"
+        ));
+    }
+    if !series.is_empty() && len < 2 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Return series must have at least 2 observations"
+        ));
+    }
+
+    Ok((symbols, series))
+}
+
+/// 样本协方差矩阵 (n - 1 自由度)，各序列长度须相同且不少于2
+fn covariance_matrix(series: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let means: Vec<f64> = series.iter()
+        .map(|r| r.iter().sum::<f64>() / r.len() as f64)
+        .collect();
+
+    let k = series.len();
+    let mut cov = vec![vec![0.0; k]; k];
+    for i in 0..k {
+        for j in i..k {
+            let n = series[i].len();
+            let c = (0..n)
+                .map(|t| (series[i][t] - means[i]) * (series[j][t] - means[j]))
+                .sum::<f64>() / (n - 1) as f64;
+            cov[i][j] = c;
+            cov[j][i] = c;
+        }
+    }
+    cov
+}
+
+/// 相关系数矩阵，由样本协方差矩阵归一化得到；零方差序列与其他序列的相关系数记为0
+fn correlation_matrix(series: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let cov = covariance_matrix(series);
+    let k = cov.len();
+    let mut corr = vec![vec![0.0; k]; k];
+    for i in 0..k {
+        for j in 0..k {
+            let denom = (cov[i][i] * cov[j][j]).sqrt();
+            corr[i][j] = if i == j {
+                1.0
+            } else if denom > 0.0 {
+                (cov[i][j] / denom).clamp(-1.0, 1.0)
+            } else {
+                0.0
+            };
+        }
+    }
+    corr
+}
+
+/// 多策略权益曲线收益率的相关系数矩阵 (用于策略组合分散化)
+///
+/// 各曲线按最短长度对齐 (保留末尾部分) 后计算逐期简单收益率，再求相关系数矩阵。
+///
+/// # 参数
+/// * `equity_curves` - 策略名称 -> 权益曲线
+///
+/// # 返回
+/// (按名称排序的策略列表, 相关系数矩阵)
+#[pyfunction]
+fn strategy_correlation(
+    equity_curves: HashMap<String, Vec<f64>>,
+) -> PyResult<(Vec<String>, Vec<Vec<f64>>)> {
+    let min_len = equity_curves.values().map(|c| c.len()).min().unwrap_or(0);
+    if equity_curves.is_empty() || min_len < 3 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Each equity curve must have at least 3 points"
+        ));
+    }
+
+    let returns_by_name: HashMap<String, Vec<f64>> = equity_curves
+        .into_iter()
+        .map(|(name, curve)| {
+            let returns = simple_returns(&curve[curve.len() - min_len..]);
+            (name, returns)
+        })
+        .collect();
+    let (names, series) = aligned_return_series(&returns_by_name)?;
+    Ok((names, correlation_matrix(&series)))
+}
+
+/// Treynor 比率：年化超额收益 / Beta
+///
+/// 年化收益 = 平均周期收益 × `periods_per_year`，Beta 由对市场收益的 OLS 回归估计
+///
+/// # 参数
+/// * `returns` - 策略周期收益率序列
+/// * `market_returns` - 市场周期收益率序列
+/// * `risk_free_rate` - 年化无风险利率 (如 0.02)
+/// * `periods_per_year` - 每年周期数 (日线为 252)
+///
+/// # 返回
+/// Treynor 比率，Beta 为0或无法估计时为 None
+#[pyfunction]
+fn treynor_ratio(
+    returns: Vec<f64>,
+    market_returns: Vec<f64>,
+    risk_free_rate: f64,
+    periods_per_year: f64,
+) -> PyResult<Option<f64>> {
+    if returns.len() != market_returns.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Input arrays must have the same length"
+        ));
+    }
+
+    let beta = match ols_alpha_beta(&returns, &market_returns) {
+        Some((_, beta)) if beta != 0.0 => beta,
+        _ => return Ok(None),
+    };
+
+    let annual_return = mean(&returns) * periods_per_year;
+    Ok(Some((annual_return - risk_free_rate) / beta))
+}
+
+/// 跟踪误差：主动收益 (组合收益 - 目标组合收益) 的年化标准差
+///
+/// 标准差为总体标准差 (与滚动信息比率一致)，乘以 `sqrt(periods_per_year)` 年化
+///
+/// # 参数
+/// * `portfolio_returns` - 组合周期收益率序列
+/// * `target_returns` - 目标权重组合 (或指数) 的周期收益率序列
+/// * `periods_per_year` - 每年周期数 (日线为 252)
+///
+/// # 返回
+/// 年化跟踪误差
+#[pyfunction]
+fn tracking_error(
+    portfolio_returns: Vec<f64>,
+    target_returns: Vec<f64>,
+    periods_per_year: f64,
+) -> PyResult<f64> {
+    if portfolio_returns.len() != target_returns.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Input arrays must have the same length"
+        ));
+    }
+    if periods_per_year <= 0.0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "periods_per_year must be positive"
+        ));
+    }
+
+    let active: Vec<f64> = portfolio_returns.iter()
+        .zip(target_returns.iter())
+        .map(|(r, t)| r - t)
+        .collect();
+    Ok(std_dev(&active) * periods_per_year.sqrt())
+}
+
+/// 评估比率 (Appraisal Ratio)：年化 Alpha / 年化残差标准差
+///
+/// 对市场收益做 OLS 回归 `r = α + β·m + ε`，`α` 乘以 `periods_per_year` 年化，
+/// 残差标准差按 n-2 自由度估计后乘以 `sqrt(periods_per_year)` 年化
+///
+/// # 参数
+/// * `returns` - 策略周期收益率序列
+/// * `market_returns` - 市场周期收益率序列
+/// * `periods_per_year` - 每年周期数 (日线为 252)
+///
+/// # 返回
+/// 评估比率，回归无法估计、样本少于3个或残差标准差为0时为 None
+#[pyfunction]
+fn appraisal_ratio(
+    returns: Vec<f64>,
+    market_returns: Vec<f64>,
+    periods_per_year: f64,
+) -> PyResult<Option<f64>> {
+    if returns.len() != market_returns.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Input arrays must have the same length"
+        ));
+    }
+    if returns.len() < 3 {
+        return Ok(None);
+    }
+
+    let (alpha, beta) = match ols_alpha_beta(&returns, &market_returns) {
+        Some(coefficients) => coefficients,
+        None => return Ok(None),
+    };
+
+    let sse: f64 = returns.iter()
+        .zip(market_returns.iter())
+        .map(|(&r, &m)| {
+            let residual = r - alpha - beta * m;
+            residual * residual
+        })
+        .sum();
+    let residual_std = (sse / (returns.len() - 2) as f64).sqrt();
+    // 完全拟合时残差仅剩舍入误差，相对收益量级可忽略即视为0
+    let scale = returns.iter().fold(0.0f64, |acc, r| acc.max(r.abs()));
+    if residual_std <= scale * 1e-12 {
+        return Ok(None);
+    }
+
+    Ok(Some(alpha * periods_per_year / (residual_std * periods_per_year.sqrt())))
+}
+
+/// 多因子回归 Beta
+///
+/// 对 `r = α + Σ β_i·f_i + ε` 做带截距的 OLS (正规方程，部分主元高斯消元求解)
+///
+/// # 参数
+/// * `returns` - 策略周期收益率序列
+/// * `factors` - 因子名 -> 与收益率等长的因子收益率序列
+///
+/// # 返回
+/// 字典: alpha (截距)、各因子名对应的 Beta、r_squared (收益率无波动时为0)；
+/// 序列不等长、观测值少于因子数 + 1、因子名与输出键冲突或因子共线时报错
+#[pyfunction]
+fn multi_factor_betas(
+    returns: Vec<f64>,
+    factors: HashMap<String, Vec<f64>>,
+) -> PyResult<HashMap<String, f64>> {
+    let mut names: Vec<&String> = factors.keys().collect();
+    names.sort();
+
+    if names.iter().any(|name| name.as_str() == "alpha" || name.as_str() == "r_squared") {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Factor names 'alpha' and 'r_squared' are reserved"
+        ));
+    }
+    if factors.values().any(|f| f.len() != returns.len()) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Factor series must have the same length as returns"
+        ));
+    }
+    let k = names.len() + 1;
+    if returns.len() < k {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            format!("At least {} observations are required for {} factors", k, names.len())
+        ));
+    }
+
+    // 设计矩阵的行: [1, f_1, ..., f_m]
+    let row = |t: usize| -> Vec<f64> {
+        std::iter::once(1.0).chain(names.iter().map(|name| factors[*name][t])).collect()
+    };
+    let mut xtx = vec![vec![0.0; k]; k];
+    let mut xty = vec![0.0; k];
+    for (t, &y) in returns.iter().enumerate() {
+        let x = row(t);
+        for i in 0..k {
+            xty[i] += x[i] * y;
+            for j in 0..k {
+                xtx[i][j] += x[i] * x[j];
+            }
+        }
+    }
+
+    let coefficients = solve_linear_system(xtx, xty).ok_or_else(|| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Factor series are collinear"
+        )
+    })?;
+
+    let m = mean(&returns);
+    let (sse, sst) = returns.iter().enumerate().fold((0.0, 0.0), |(sse, sst), (t, &y)| {
+        let fitted: f64 = row(t).iter().zip(coefficients.iter()).map(|(x, b)| x * b).sum();
+        (sse + (y - fitted).powi(2), sst + (y - m).powi(2))
+    });
+
+    let mut result = HashMap::new();
+    result.insert("alpha".to_string(), coefficients[0]);
+    for (name, beta) in names.iter().zip(coefficients.iter().skip(1)) {
+        result.insert((*name).clone(), *beta);
+    }
+    result.insert("r_squared".to_string(), if sst > 0.0 { 1.0 - sse / sst } else { 0.0 });
+    Ok(result)
+}
+
+/// 部分主元高斯消元求解 `A x = b`，矩阵 (近似) 奇异时为None
+fn solve_linear_system(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    let scale = a.iter().flatten().fold(0.0f64, |acc, v| acc.max(v.abs()));
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() <= scale * 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+
+        for row in col + 1..n {
+            let factor = a[row][col] / a[col][col];
+            let (upper, lower) = a.split_at_mut(row);
+            for (target, source) in lower[0][col..].iter_mut().zip(&upper[col][col..]) {
+                *target -= factor * source;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let tail: f64 = (row + 1..n).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - tail) / a[row][row];
+    }
+    Some(x)
+}
+
+/// 因子多空回测 (扣除换手成本)
+///
+/// 每个调仓日取同时具有因子值和远期收益的股票，按因子值降序排列，做多前 `top_pct`、
+/// 做空后 `top_pct` (等权，各占一倍资金)，价差收益为两组远期收益均值之差。
+/// 换手率为组合权重变化的绝对值之和 `Σ|w_t - w_{t-1}|`，首期从空仓建仓 (多空各满仓时为2)；
+/// 每期成本为 `换手率 × cost_bps / 10000`。调仓日按日期字符串升序处理，少于2只股票的日期跳过。
+///
+/// # 参数
+/// * `factor_by_symbol_by_date` - 日期 -> 股票代码 -> 因子值
+/// * `forward_returns` - 日期 -> 股票代码 -> 下一持有期收益率
+/// * `top_pct` - 多空各组占比 (0, 0.5]
+/// * `cost_bps` - 单边交易成本 (基点)
+///
+/// # 返回
+/// 字典: cumulative_gross_return, cumulative_net_return (复利累计), average_turnover, periods
+#[pyfunction]
+fn factor_backtest(
+    factor_by_symbol_by_date: Panel,
+    forward_returns: Panel,
+    top_pct: f64,
+    cost_bps: f64,
+) -> PyResult<HashMap<String, f64>> {
+    if !(top_pct > 0.0 && top_pct <= 0.5) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "top_pct must be in (0, 0.5]"
+        ));
+    }
+
+    let mut dates: Vec<&String> = factor_by_symbol_by_date.keys().collect();
+    dates.sort();
+
+    let mut prev_weights: HashMap<String, f64> = HashMap::new();
+    let mut gross_growth = 1.0;
+    let mut net_growth = 1.0;
+    let mut turnovers = Vec::new();
+
+    for date in dates {
+        let returns = match forward_returns.get(date) {
+            Some(returns) => returns,
+            None => continue,
+        };
+
+        let mut ranked: Vec<(&String, f64, f64)> = factor_by_symbol_by_date[date].iter()
+            .filter_map(|(symbol, &factor)| returns.get(symbol).map(|&r| (symbol, factor, r)))
+            .collect();
+        if ranked.len() < 2 {
+            continue;
+        }
+        ranked.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then_with(|| a.0.cmp(b.0))
+        });
+
+        let bucket = ((ranked.len() as f64 * top_pct).round() as usize).clamp(1, ranked.len() / 2);
+        let longs = &ranked[..bucket];
+        let shorts = &ranked[ranked.len() - bucket..];
+
+        let spread = longs.iter().map(|x| x.2).sum::<f64>() / bucket as f64
+            - shorts.iter().map(|x| x.2).sum::<f64>() / bucket as f64;
+
+        let mut weights = HashMap::new();
+        for (symbol, _, _) in longs {
+            weights.insert((*symbol).clone(), 1.0 / bucket as f64);
+        }
+        for (symbol, _, _) in shorts {
+            weights.insert((*symbol).clone(), -1.0 / bucket as f64);
+        }
+
+        let turnover: f64 = weights.iter()
+            .map(|(symbol, w)| (w - prev_weights.get(symbol).copied().unwrap_or(0.0)).abs())
+            .sum::<f64>()
+            + prev_weights.iter()
+                .filter(|(symbol, _)| !weights.contains_key(*symbol))
+                .map(|(_, w)| w.abs())
+                .sum::<f64>();
+
+        gross_growth *= 1.0 + spread;
+        net_growth *= 1.0 + spread - turnover * cost_bps / 10_000.0;
+        turnovers.push(turnover);
+        prev_weights = weights;
+    }
+
+    let mut result = HashMap::new();
+    result.insert("cumulative_gross_return".to_string(), gross_growth - 1.0);
+    result.insert("cumulative_net_return".to_string(), net_growth - 1.0);
+    result.insert("average_turnover".to_string(), mean(&turnovers));
+    result.insert("periods".to_string(), turnovers.len() as f64);
+    Ok(result)
+}
+
+/// 风险价值 (VaR)、条件风险价值 (CVaR) 与下行偏差
+///
+/// 符号约定：损失记为正数，VaR = 0.03 表示在给定置信度下单期损失不超过 3%。
+/// * `historical`: 取最差的 `k = floor(n * (1 - confidence))` (至少1) 个收益，
+///   VaR 为其中最好的一个取负，CVaR 为其均值取负
+/// * `gaussian`: 按正态分布 `VaR = z·σ - μ`，`CVaR = σ·φ(z)/(1 - confidence) - μ`
+///
+/// 下行偏差为 `sqrt(mean(min(r, 0)²))`
+///
+/// # 参数
+/// * `returns` - 周期收益率序列
+/// * `confidence` - 置信度 (如 0.95)
+/// * `method` - "historical" 或 "gaussian"
+///
+/// # 返回
+/// 字典: var, cvar, downside_deviation
+#[pyfunction]
+fn var_cvar(returns: Vec<f64>, confidence: f64, method: &str) -> PyResult<HashMap<String, f64>> {
+    if returns.is_empty() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Returns must not be empty"
+        ));
+    }
+    if !(confidence > 0.0 && confidence < 1.0) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Confidence must be between 0 and 1"
+        ));
+    }
+
+    let (var, cvar) = match method {
+        "historical" => {
+            let mut sorted = returns.clone();
+            sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+            let k = ((sorted.len() as f64 * (1.0 - confidence)).floor() as usize).max(1);
+            let tail = &sorted[..k];
+            (-tail[k - 1], -mean(tail))
+        }
+        "gaussian" => {
+            let mu = mean(&returns);
+            let sigma = std_dev(&returns);
+            let z = normal_quantile(confidence);
+            let pdf = (-0.5 * z * z).exp() / (2.0 * std::f64::consts::PI).sqrt();
+            (z * sigma - mu, sigma * pdf / (1.0 - confidence) - mu)
+        }
+        _ => {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                format!("Unknown VaR method: {}", method)
+            ));
+        }
+    };
+
+    let mut result = HashMap::new();
+    result.insert("var".to_string(), var);
+    result.insert("cvar".to_string(), cvar);
+    result.insert("downside_deviation".to_string(), downside_deviation(&returns));
+    Ok(result)
+}
+
+/// VaR 回测：统计实际损失超过预测 VaR 的次数，并做 Kupiec 失败比例 (POF) 检验
+///
+/// 符号约定同 `var_cvar`：VaR 为正数表示损失，`returns[i] < -var_series[i]` 记为一次突破。
+/// Kupiec 似然比 `LR = -2 ln[(1-p)^(n-x) p^x] + 2 ln[(1-x/n)^(n-x) (x/n)^x]`
+/// (p = 1 - confidence)，服从自由度为1的卡方分布。
+///
+/// # 参数
+/// * `returns` - 实际周期收益率序列
+/// * `var_series` - 与收益逐期对齐的预测 VaR 序列
+/// * `confidence` - VaR 置信度 (默认 0.95)，决定期望突破率
+///
+/// # 返回
+/// 字典: observations, breaches, breach_rate, expected_breaches, expected_rate,
+/// kupiec_lr, kupiec_p_value (p 值较小说明突破频率与置信度不符)
+#[pyfunction]
+#[pyo3(signature = (returns, var_series, confidence=0.95))]
+fn var_backtest(returns: Vec<f64>, var_series: Vec<f64>, confidence: f64) -> PyResult<HashMap<String, f64>> {
+    if returns.len() != var_series.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Input arrays must have the same length"
+        ));
+    }
+    if returns.is_empty() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Returns must not be empty"
+        ));
+    }
+    if !(confidence > 0.0 && confidence < 1.0) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Confidence must be between 0 and 1"
+        ));
+    }
+
+    let n = returns.len() as f64;
+    let breaches = returns.iter()
+        .zip(var_series.iter())
+        .filter(|(r, var)| **r < -**var)
+        .count() as f64;
+    let p = 1.0 - confidence;
+    let observed = breaches / n;
+
+    // x ln(q)，约定 0·ln(0) = 0
+    let xlogy = |x: f64, y: f64| if x == 0.0 { 0.0 } else { x * y.ln() };
+    let log_null = xlogy(n - breaches, 1.0 - p) + xlogy(breaches, p);
+    let log_alt = xlogy(n - breaches, 1.0 - observed) + xlogy(breaches, observed);
+    let lr = (2.0 * (log_alt - log_null)).max(0.0);
+    // 自由度为1的卡方分布生存函数 P(χ² > lr) = 2·(1 - Φ(sqrt(lr)))
+    let p_value = 2.0 * (1.0 - normal_cdf(lr.sqrt()));
+
+    let mut result = HashMap::new();
+    result.insert("observations".to_string(), n);
+    result.insert("breaches".to_string(), breaches);
+    result.insert("breach_rate".to_string(), observed);
+    result.insert("expected_breaches".to_string(), n * p);
+    result.insert("expected_rate".to_string(), p);
+    result.insert("kupiec_lr".to_string(), lr);
+    result.insert("kupiec_p_value".to_string(), p_value);
+    Ok(result)
+}
+
+/// 滚动最大回撤 (%)
+///
+/// 每个位置给出末尾 `window` 个权益点内最深的峰谷回撤。区间以 (最高, 最低, 最大回撤)
+/// 聚合，两段相接时跨段回撤由左段最高与右段最低决定，因此可用双栈队列在
+/// 摊还 O(1) 内滑动窗口，总复杂度 O(n)。
+///
+/// # 参数
+/// * `equity` - 权益序列
+/// * `window` - 窗口长度
+///
+/// # 返回
+/// 滚动最大回撤列表，前 `window - 1` 个值为 None
+#[pyfunction]
+fn rolling_max_drawdown(equity: Vec<f64>, window: usize) -> PyResult<Vec<Option<f64>>> {
+    if window == 0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Window must be positive"
+        ));
+    }
+
+    // front 保存出队端各元素到前段末尾的后缀聚合，back_agg 为入队端的整体聚合
+    let mut front: Vec<DrawdownSpan> = Vec::new();
+    let mut back: Vec<f64> = Vec::new();
+    let mut back_agg: Option<DrawdownSpan> = None;
+
+    let mut result = Vec::with_capacity(equity.len());
+    for (i, &value) in equity.iter().enumerate() {
+        let span = DrawdownSpan::single(value);
+        back_agg = Some(match back_agg {
+            Some(agg) => agg.merge(&span),
+            None => span,
+        });
+        back.push(value);
+
+        if i >= window {
+            if front.is_empty() {
+                let mut suffix: Option<DrawdownSpan> = None;
+                for &v in back.iter().rev() {
+                    let span = DrawdownSpan::single(v);
+                    let agg = match suffix {
+                        Some(agg) => span.merge(&agg),
+                        None => span,
+                    };
+                    front.push(agg);
+                    suffix = Some(agg);
+                }
+                back.clear();
+                back_agg = None;
+            }
+            front.pop();
+        }
+
+        if i + 1 < window {
+            result.push(None);
+            continue;
+        }
+
+        let agg = match (front.last(), back_agg) {
+            (Some(f), Some(b)) => f.merge(&b),
+            (Some(f), None) => *f,
+            (None, Some(b)) => b,
+            (None, None) => DrawdownSpan::single(value),
+        };
+        result.push(Some(agg.max_drawdown * 100.0));
+    }
+
+    Ok(result)
+}
+
+/// 蒙特卡洛最大回撤分布 (%)
+///
+/// 模拟 `n_runs` 条几何布朗运动权益路径，每期对数收益为 `(μ - σ²/2) + σ·Z`，
+/// 统计各路径的最大回撤并取分位数 (最近秩法)。第 i 条路径的随机数种子由 `seed` 与 i
+/// 派生，因此结果与 rayon 的线程调度无关，相同种子可复现。
+///
+/// # 参数
+/// * `mean_return` - 每期期望收益率 μ
+/// * `vol` - 每期波动率 σ (非负)
+/// * `n_periods` - 每条路径的期数
+/// * `n_runs` - 模拟路径数
+/// * `seed` - 随机数种子
+///
+/// # 返回
+/// 字典: p50, p95, p99 (最大回撤分位数) 与 mean (平均最大回撤)
+#[pyfunction]
+fn mc_drawdown_distribution(
+    mean_return: f64,
+    vol: f64,
+    n_periods: usize,
+    n_runs: usize,
+    seed: u64,
+) -> PyResult<HashMap<String, f64>> {
+    if n_periods == 0 || n_runs == 0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "n_periods and n_runs must be positive"
+        ));
+    }
+    if vol < 0.0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Volatility must be non-negative"
+        ));
+    }
+
+    let drift = mean_return - 0.5 * vol * vol;
+    let mut drawdowns: Vec<f64> = (0..n_runs as u64)
+        .into_par_iter()
+        .map(|run| {
+            let mut rng = SplitMix64::new(seed ^ run.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+            let (mut log_equity, mut peak, mut max_drawdown) = (0.0_f64, 0.0_f64, 0.0_f64);
+            for _ in 0..n_periods {
+                log_equity += drift + vol * rng.next_gaussian();
+                peak = peak.max(log_equity);
+                max_drawdown = max_drawdown.max(1.0 - (log_equity - peak).exp());
+            }
+            max_drawdown * 100.0
+        })
+        .collect();
+    drawdowns.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    let percentile = |q: f64| drawdowns[((q * n_runs as f64).ceil() as usize).clamp(1, n_runs) - 1];
+    let mut result = HashMap::new();
+    result.insert("p50".to_string(), percentile(0.50));
+    result.insert("p95".to_string(), percentile(0.95));
+    result.insert("p99".to_string(), percentile(0.99));
+    result.insert("mean".to_string(), mean(&drawdowns));
+    Ok(result)
+}
+
+/// 基于回撤的仓位降档系数
+///
+/// 分段阶梯 (不插值)：取阈值不超过当前回撤的最后一档的系数，
+/// 回撤未达到第一档阈值时为 1.0。回撤按绝对值处理 (-15 与 15 等价)。
+///
+/// # 参数
+/// * `current_drawdown_pct` - 当前回撤 (%)
+/// * `throttle_levels` - `(回撤阈值 %, 仓位系数)` 列表，阈值须严格递增，系数在 [0, 1]
+///
+/// # 返回
+/// 仓位系数 (0-1)
+#[pyfunction]
+fn drawdown_throttle(current_drawdown_pct: f64, throttle_levels: Vec<(f64, f64)>) -> PyResult<f64> {
+    if throttle_levels.windows(2).any(|w| w[0].0 >= w[1].0) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Throttle thresholds must be strictly increasing"
+        ));
+    }
+    if throttle_levels.iter().any(|&(_, multiplier)| !(0.0..=1.0).contains(&multiplier)) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Throttle multipliers must be in [0, 1]"
+        ));
+    }
+
+    let drawdown = current_drawdown_pct.abs();
+    Ok(throttle_levels
+        .iter()
+        .take_while(|&&(threshold, _)| threshold <= drawdown)
+        .last()
+        .map_or(1.0, |&(_, multiplier)| multiplier))
+}
+
+/// SplitMix64 伪随机数生成器 (可复现的蒙特卡洛模拟用)
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// (0, 1) 区间均匀分布
+    fn next_f64(&mut self) -> f64 {
+        ((self.next_u64() >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+    }
+
+    /// 标准正态分布 (Box-Muller)
+    fn next_gaussian(&mut self) -> f64 {
+        let (u1, u2) = (self.next_f64(), self.next_f64());
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+}
+
+/// 权益区间的回撤聚合：区间最高、最低与区间内最大回撤 (比例)
+#[derive(Debug, Clone, Copy)]
+struct DrawdownSpan {
+    high: f64,
+    low: f64,
+    max_drawdown: f64,
+}
+
+impl DrawdownSpan {
+    fn single(value: f64) -> Self {
+        DrawdownSpan { high: value, low: value, max_drawdown: 0.0 }
+    }
+
+    /// 按时间顺序拼接 self 与其后的 next
+    fn merge(&self, next: &DrawdownSpan) -> Self {
+        let cross = if self.high > 0.0 {
+            ((self.high - next.low) / self.high).max(0.0)
+        } else {
+            0.0
+        };
+        DrawdownSpan {
+            high: self.high.max(next.high),
+            low: self.low.min(next.low),
+            max_drawdown: self.max_drawdown.max(next.max_drawdown).max(cross),
+        }
+    }
+}
+
+/// 均值回归半衰期 (K线数)
+///
+/// 对价差做 AR(1) 拟合 `Δs_t = α + λ·s_{t-1} + ε` (OLS，含截距以允许非零均值)，
+/// 半衰期为 `-ln(2) / ln(1 + λ)`。λ >= 0 (不回归) 或 λ <= -1 (过度振荡) 时为 None。
+///
+/// # 参数
+/// * `spread` - 价差序列 (至少3个点)
+///
+/// # 返回
+/// 半衰期 (K线数)，序列不均值回归时为 None
+#[pyfunction]
+fn mean_reversion_half_life(spread: Vec<f64>) -> PyResult<Option<f64>> {
+    if spread.len() < 3 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "At least 3 spread values are required"
+        ));
+    }
+
+    let lagged = &spread[..spread.len() - 1];
+    let delta: Vec<f64> = spread.windows(2).map(|w| w[1] - w[0]).collect();
+    Ok(match ols_alpha_beta(&delta, lagged) {
+        Some((_, lambda)) if lambda < 0.0 && lambda > -1.0 => Some(-std::f64::consts::LN_2 / (1.0 + lambda).ln()),
+        _ => None,
+    })
+}
+
+/// 滚动对冲比率 (多空配对的 Beta 中性比例)
+///
+/// 对每个尾部窗口内的资产收益率与对冲标的收益率做 OLS 回归，斜率即对冲比率。
+/// 输出与价格序列对齐：第 i 个值使用截至第 i 根K线的 `window` 个收益率，
+/// 前 `window` 个为 None；窗口内对冲标的收益率方差为0时也为 None。
+///
+/// # 参数
+/// * `asset` - 资产价格序列
+/// * `hedge` - 对冲标的价格序列 (长度须与 `asset` 相同)
+/// * `window` - 回归窗口 (收益率个数，至少2)
+///
+/// # 返回
+/// 逐K线的对冲比率
+#[pyfunction]
+fn rolling_hedge_ratio(asset: Vec<f64>, hedge: Vec<f64>, window: usize) -> PyResult<Vec<Option<f64>>> {
+    if asset.len() != hedge.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Asset and hedge series must have the same length"
+        ));
+    }
+    if window < 2 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Window must be at least 2"
+        ));
+    }
+
+    let asset_returns = simple_returns(&asset);
+    let hedge_returns = simple_returns(&hedge);
+    Ok((0..asset.len())
+        .map(|i| {
+            if i < window {
+                return None;
+            }
+            ols_alpha_beta(&asset_returns[i - window..i], &hedge_returns[i - window..i])
+                .map(|(_, beta)| beta)
+        })
+        .collect())
+}
+
+/// 收益率自相关系数
+///
+/// `ρ_k = Σ(x_t - μ)(x_{t-k} - μ) / Σ(x_t - μ)²`，分母使用全样本方差 (标准ACF估计)。
+/// 序列方差为0 (如全部相同) 时自相关无定义，返回全0。
+///
+/// # 参数
+/// * `returns` - 交易或周期收益率序列
+/// * `max_lag` - 最大滞后阶数，需小于序列长度
+///
+/// # 返回
+/// 滞后 1..=max_lag 的自相关系数列表
+#[pyfunction]
+fn return_autocorrelation(returns: Vec<f64>, max_lag: usize) -> PyResult<Vec<f64>> {
+    if max_lag >= returns.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "max_lag must be less than the number of returns"
+        ));
+    }
+
+    Ok((1..=max_lag)
+        .map(|lag| autocorrelation(&returns, lag).unwrap_or(0.0))
+        .collect())
+}
+
+/// 滞后 `lag` 阶自相关系数 (标准ACF估计)，序列方差为0时为None
+fn autocorrelation(returns: &[f64], lag: usize) -> Option<f64> {
+    // 常数序列的均值可能有舍入误差，直接比较取值判断零方差
+    if returns.iter().all(|&r| r == returns[0]) {
+        return None;
+    }
+
+    let m = mean(returns);
+    let denom: f64 = returns.iter().map(|r| (r - m) * (r - m)).sum();
+    Some((lag..returns.len())
+        .map(|t| (returns[t] - m) * (returns[t - lag] - m))
+        .sum::<f64>() / denom)
+}
+
+/// 滚动自相关系数：每个窗口内收益率的滞后 `lag` 阶自相关
+///
+/// 前 `window - 1` 个值、`lag` 不小于窗口长度及窗口内零方差时为 None
+fn rolling_autocorrelation(returns: &[f64], window: usize, lag: usize) -> Vec<Option<f64>> {
+    (0..returns.len())
+        .map(|i| {
+            if window == 0 || lag >= window || i + 1 < window {
+                return None;
+            }
+            autocorrelation(&returns[i + 1 - window..=i], lag)
+        })
+        .collect()
+}
+
+/// 隔夜跳空统计
+///
+/// 跳空收益 `gap = open[i] / close[i-1] - 1`。开盘高于前收为向上跳空 (gap up)，
+/// 开盘低于前收为向下跳空 (gap down)；向上跳空当日最低价回到前收、向下跳空当日
+/// 最高价回到前收视为回补。开盘等于前收不计为跳空。
+///
+/// # 参数
+/// * `daily_klines` - 日K线 (timestamp, open, high, low, close, volume)，至少2根
+///
+/// # 返回
+/// 字典：`mean_gap` / `std_gap` 为全部隔夜收益的均值与总体标准差，
+/// `avg_gap_size` 为跳空绝对幅度均值，`fill_rate` 为跳空回补比例，
+/// `gap_up_count` / `gap_down_count` 为向上/向下跳空次数 (无跳空时幅度与回补率为0)
+#[pyfunction]
+fn gap_stats(daily_klines: Vec<(i64, f64, f64, f64, f64, f64)>) -> PyResult<HashMap<String, f64>> {
+    if daily_klines.len() < 2 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "At least 2 klines are required"
+        ));
+    }
+    if daily_klines.iter().any(|k| k.4 <= 0.0) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Close prices must be positive"
+        ));
+    }
+
+    let mut gaps = Vec::with_capacity(daily_klines.len() - 1);
+    let mut sizes = Vec::new();
+    let (mut up, mut down, mut filled) = (0usize, 0usize, 0usize);
+    for pair in daily_klines.windows(2) {
+        let prev_close = pair[0].4;
+        let (_, open, high, low, _, _) = pair[1];
+        let gap = open / prev_close - 1.0;
+        gaps.push(gap);
+
+        if open > prev_close {
+            up += 1;
+            filled += usize::from(low <= prev_close);
+        } else if open < prev_close {
+            down += 1;
+            filled += usize::from(high >= prev_close);
+        } else {
+            continue;
+        }
+        sizes.push(gap.abs());
+    }
+
+    let mut result = HashMap::new();
+    result.insert("mean_gap".to_string(), mean(&gaps));
+    result.insert("std_gap".to_string(), std_dev(&gaps));
+    result.insert("avg_gap_size".to_string(), mean(&sizes));
+    let fill_rate = if sizes.is_empty() { 0.0 } else { filled as f64 / sizes.len() as f64 };
+    result.insert("fill_rate".to_string(), fill_rate);
+    result.insert("gap_up_count".to_string(), up as f64);
+    result.insert("gap_down_count".to_string(), down as f64);
+    Ok(result)
+}
+
+/// 两个时间戳之间的交易K线数 (剔除周末与节假日)
+///
+/// 按 `tz_offset_seconds` 时区的本地日期统计 `[start, end)` 区间内的交易日
+/// (起始日计入、结束日不计入)，再乘以每日K线数。节假日按其时间戳所在的本地日期匹配。
+///
+/// # 参数
+/// * `start` - 起始毫秒时间戳
+/// * `end` - 结束毫秒时间戳 (不早于 `start`)
+/// * `bars_per_day` - 每个交易日的K线数 (日线为 1)
+/// * `holidays` - 节假日毫秒时间戳列表
+/// * `tz_offset_seconds` - 划分日期所用时区相对 UTC 的偏移秒数 (默认0，北京时间为 28800)
+///
+/// # 返回
+/// 交易K线数
+#[pyfunction]
+#[pyo3(signature = (start, end, bars_per_day, holidays, tz_offset_seconds=0))]
+fn trading_bars_between(
+    start: i64,
+    end: i64,
+    bars_per_day: f64,
+    holidays: Vec<i64>,
+    tz_offset_seconds: i64,
+) -> PyResult<f64> {
+    if end < start {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "end must not be earlier than start"
+        ));
+    }
+    if bars_per_day <= 0.0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "bars_per_day must be positive"
+        ));
+    }
+
+    let offset = i32::try_from(tz_offset_seconds)
+        .ok()
+        .and_then(chrono::FixedOffset::east_opt)
+        .ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                format!("Invalid tz_offset_seconds: {}", tz_offset_seconds)
+            )
+        })?;
+    let local_date = |timestamp: i64| {
+        chrono::DateTime::from_timestamp_millis(timestamp)
+            .map(|dt| dt.with_timezone(&offset).date_naive())
+            .ok_or_else(|| {
+                PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                    format!("Invalid timestamp: {}", timestamp)
+                )
+            })
+    };
+    let holidays = holidays.into_iter().map(local_date).collect::<PyResult<HashSet<_>>>()?;
+    let end_date = local_date(end)?;
+
+    let trading_days = local_date(start)?
+        .iter_days()
+        .take_while(|date| *date < end_date)
+        .filter(|date| date.weekday().number_from_monday() <= 5 && !holidays.contains(date))
+        .count();
+    Ok(trading_days as f64 * bars_per_day)
+}
+
+/// 日历期收益表 (月度与年度复合收益，%)
+///
+/// 每期收益为期末权益相对上一期期末权益的涨幅，首期以第一个权益点为基准，
+/// 因此首尾不完整的月份/年份只统计区间内实际覆盖的部分。
+///
+/// # 参数
+/// * `equity` - 权益序列
+/// * `timestamps` - 与权益逐点对应的毫秒时间戳 (升序)
+/// * `tz_offset_seconds` - 划分日历期所用时区相对 UTC 的偏移秒数 (如北京时间 28800)
+///
+/// # 返回
+/// 字典，键为 "YYYY-MM" (月度) 与 "YYYY" (年度)，值为复合收益百分比
+#[pyfunction]
+fn calendar_returns(
+    equity: Vec<f64>,
+    timestamps: Vec<i64>,
+    tz_offset_seconds: i64,
+) -> PyResult<HashMap<String, f64>> {
+    if equity.is_empty() || equity.len() != timestamps.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Equity and timestamps must be non-empty and of equal length"
+        ));
+    }
+    if equity.iter().any(|&e| e <= 0.0) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Equity values must be positive"
+        ));
+    }
+    if timestamps.windows(2).any(|w| w[1] < w[0]) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Timestamps must be in ascending order"
+        ));
+    }
+    let offset = i32::try_from(tz_offset_seconds)
+        .ok()
+        .and_then(chrono::FixedOffset::east_opt)
+        .ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                format!("Invalid tz_offset_seconds: {}", tz_offset_seconds)
+            )
+        })?;
+
+    // 各月份、年份的期末权益
+    let mut month_ends: BTreeMap<(i32, u32), f64> = BTreeMap::new();
+    let mut year_ends: BTreeMap<i32, f64> = BTreeMap::new();
+    for (&value, &timestamp) in equity.iter().zip(&timestamps) {
+        let date = chrono::DateTime::from_timestamp_millis(timestamp)
+            .ok_or_else(|| {
+                PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                    format!("Invalid timestamp: {}", timestamp)
+                )
+            })?
+            .with_timezone(&offset);
+        month_ends.insert((date.year(), date.month()), value);
+        year_ends.insert(date.year(), value);
+    }
+
+    let mut result = HashMap::new();
+    let mut base = equity[0];
+    for ((year, month), end) in month_ends {
+        result.insert(format!("{:04}-{:02}", year, month), (end / base - 1.0) * 100.0);
+        base = end;
+    }
+    let mut base = equity[0];
+    for (year, end) in year_ends {
+        result.insert(format!("{:04}", year), (end / base - 1.0) * 100.0);
+        base = end;
+    }
+    Ok(result)
+}
+
+/// 下行偏差：`sqrt(sum(min(r, 0)^2) / n)`，仅负收益计入平方和，空序列为0
+fn downside_deviation(returns: &[f64]) -> f64 {
+    if returns.is_empty() {
+        return 0.0;
+    }
+    (returns.iter().map(|&r| r.min(0.0).powi(2)).sum::<f64>() / returns.len() as f64).sqrt()
+}
+
+/// 总体标准差
+fn std_dev(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let m = mean(values);
+    (values.iter().map(|v| (v - m) * (v - m)).sum::<f64>() / values.len() as f64).sqrt()
+}
+
+/// 标准正态分布分位数 (Acklam 有理逼近，相对误差约 1e-9)
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969683028665376e1, 2.209460984245205e2, -2.759285104469687e2,
+        1.383_577_518_672_69e2, -3.066479806614716e1, 2.506628277459239,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e1, 1.615858368580409e2, -1.556989798598866e2,
+        6.680131188771972e1, -1.328068155288572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-3, -3.223964580411365e-1, -2.400758277161838,
+        -2.549732539343734, 4.374664141464968, 2.938163982698783,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-3, 3.224671290700398e-1, 2.445134137142996, 3.754408661907416,
+    ];
+    const P_LOW: f64 = 0.02425;
+
+    if p < P_LOW {
+        let q = (-2.0 * p.ln()).sqrt();
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    } else if p <= 1.0 - P_LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        -normal_quantile(1.0 - p)
+    }
+}
+
+/// 标准正态分布函数 (Abramowitz-Stegun 7.1.26 误差函数逼近，绝对误差约 1.5e-7)
+fn normal_cdf(x: f64) -> f64 {
+    let z = x.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.327_591_1 * z);
+    let poly = t * (0.254_829_592
+        + t * (-0.284_496_736 + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let erf = 1.0 - poly * (-z * z).exp();
+    if x >= 0.0 {
+        0.5 * (1.0 + erf)
+    } else {
+        0.5 * (1.0 - erf)
+    }
+}
+
+/// 算术平均值，空序列为None
+fn average(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(mean(values))
+    }
+}
+
+/// 算术平均值，空序列为0
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+}
+
+/// 一元 OLS 回归 `y = alpha + beta * x`，观测值不足2个或 x 无波动时为 None
+fn ols_alpha_beta(y: &[f64], x: &[f64]) -> Option<(f64, f64)> {
+    if y.len() != x.len() || y.len() < 2 {
+        return None;
+    }
+
+    let (mean_x, mean_y) = (mean(x), mean(y));
+    let mut sxx = 0.0;
+    let mut sxy = 0.0;
+    for (&xi, &yi) in x.iter().zip(y.iter()) {
+        sxx += (xi - mean_x) * (xi - mean_x);
+        sxy += (xi - mean_x) * (yi - mean_y);
+    }
+
+    if sxx == 0.0 {
+        return None;
+    }
+
+    let beta = sxy / sxx;
+    Some((mean_y - beta * mean_x, beta))
+}
+
+/// Python模块定义
+#[pymodule]
+fn tacn_backtest(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(simple_backtest, m)?)?;
+    m.add_function(wrap_pyfunction!(walk_forward_backtest, m)?)?;
+    m.add_function(wrap_pyfunction!(optimize_backtest, m)?)?;
+    m.add_class::<BacktestEngine>()?;
+    m.add_class::<Order>()?;
+    m.add_class::<Position>()?;
+    m.add_class::<Trade>()?;
+    m.add_class::<TradeType>()?;
+    m.add_class::<OrderStatus>()?;
+    m.add_function(wrap_pyfunction!(mae_distribution, m)?)?;
+    m.add_function(wrap_pyfunction!(risk_contribution, m)?)?;
+    m.add_function(wrap_pyfunction!(strategy_correlation, m)?)?;
+    m.add_function(wrap_pyfunction!(risk_parity_weights, m)?)?;
+    m.add_function(wrap_pyfunction!(capacity_estimate, m)?)?;
+    m.add_function(wrap_pyfunction!(treynor_ratio, m)?)?;
+    m.add_function(wrap_pyfunction!(tracking_error, m)?)?;
+    m.add_function(wrap_pyfunction!(appraisal_ratio, m)?)?;
+    m.add_function(wrap_pyfunction!(multi_factor_betas, m)?)?;
+    m.add_function(wrap_pyfunction!(factor_backtest, m)?)?;
+    m.add_function(wrap_pyfunction!(var_cvar, m)?)?;
+    m.add_function(wrap_pyfunction!(var_backtest, m)?)?;
+    m.add_function(wrap_pyfunction!(rolling_max_drawdown, m)?)?;
+    m.add_function(wrap_pyfunction!(mc_drawdown_distribution, m)?)?;
+    m.add_function(wrap_pyfunction!(drawdown_throttle, m)?)?;
+    m.add_function(wrap_pyfunction!(return_autocorrelation, m)?)?;
+    m.add_function(wrap_pyfunction!(mean_reversion_half_life, m)?)?;
+    m.add_function(wrap_pyfunction!(rolling_hedge_ratio, m)?)?;
+    m.add_function(wrap_pyfunction!(gap_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(calendar_returns, m)?)?;
+    m.add_function(wrap_pyfunction!(trading_bars_between, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve(values: &[f64]) -> Vec<EquityPoint> {
+        values.iter()
+            .enumerate()
+            .map(|(i, &equity)| EquityPoint { timestamp: i as i64 * MS_PER_DAY, equity })
+            .collect()
+    }
+
+    /// 正弦波动 (振幅10，周期参数 `period`) 叠加每根 `drift` 趋势的日K线，高低价为收盘价 ±1；
+    /// `period` 为无穷大时即单纯的线性趋势
+    fn sine_klines(n: usize, period: f64, drift: f64) -> Vec<(i64, f64, f64, f64, f64, f64)> {
+        (0..n)
+            .map(|i| {
+                let close = 100.0 + 10.0 * (i as f64 / period).sin() + i as f64 * drift;
+                (i as i64 * MS_PER_DAY, close, close + 1.0, close - 1.0, close, 1_000.0)
+            })
+            .collect()
+    }
+
+    /// 待成交订单，订单号为 `symbol_timestamp`
+    fn order(symbol: &str, trade_type: TradeType, price: f64, quantity: f64, timestamp: i64) -> Order {
+        Order {
+            id: format!("{}_{}", symbol, timestamp),
+            symbol: symbol.to_string(),
+            trade_type,
+            price,
+            quantity,
+            timestamp,
+            status: OrderStatus::Pending,
+        }
+    }
+
+    /// 以默认可选参数调用 `simple_backtest` (初始资金 100000，手续费率 0.0003，sma_cross)
+    fn run_default_backtest(klines: &[(i64, f64, f64, f64, f64, f64)], params: &str) -> PyObject {
+        simple_backtest(
+            klines.to_vec(), 100_000.0, 0.0003, "sma_cross", params, None, 0.0, 20, 0.0, 252.0, 20, 1, 0.0, 0.0, 0,
+        ).unwrap()
+    }
+
+    #[test]
+    fn test_martin_ratio_prefers_smooth_path() {
+        // 两条路径首尾相同，平滑路径的回撤更浅
+        let smooth: Vec<f64> = (0..250).map(|i| 100.0 + 0.2 * i as f64 - if i % 10 == 5 { 0.5 } else { 0.0 }).collect();
+        let volatile: Vec<f64> = (0..250).map(|i| 100.0 + 0.2 * i as f64 - if i % 10 == 5 { 8.0 } else { 0.0 }).collect();
+
+        let smooth_martin = martin_ratio(&curve(&smooth)).unwrap();
+        let volatile_martin = martin_ratio(&curve(&volatile)).unwrap();
+        assert!(smooth_martin > volatile_martin);
+        assert!(volatile_martin > 0.0);
+
+        // 单调上涨没有回撤，溃疡指数为0
+        let rising: Vec<f64> = (0..50).map(|i| 100.0 + i as f64).collect();
+        assert_eq!(ulcer_index(&curve(&rising)), 0.0);
+        assert_eq!(martin_ratio(&curve(&rising)), None);
+    }
+
+    #[test]
+    fn test_time_in_drawdown_pct() {
+        // 10 根K线中第 3-5 根 (3 根) 与第 8 根 (1 根) 低于此前峰值
+        let path = [100.0, 105.0, 110.0, 104.0, 106.0, 109.0, 112.0, 115.0, 113.0, 120.0];
+        assert!((time_in_drawdown_pct(&curve(&path)) - 40.0).abs() < 1e-9);
+        assert_eq!(time_in_drawdown_pct(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_mae_distribution_partitions_trades() {
+        let maes = vec![0.01, 0.02, 0.03, 0.05, 0.08, 0.10];
+        let winners = vec![true, true, false, true, false, false];
+        let result = mae_distribution(maes, winners, 3).unwrap();
+
+        assert_eq!(result["bin_edges"].len(), 4);
+        assert_eq!(result["winners"], vec![2.0, 1.0, 0.0]);
+        assert_eq!(result["losers"], vec![1.0, 0.0, 2.0]);
+
+        let total: f64 = result["winners"].iter().chain(result["losers"].iter()).sum();
+        assert_eq!(total, 6.0);
+
+        assert!(mae_distribution(vec![0.01], vec![], 3).is_err());
+    }
+
+    #[test]
+    fn test_risk_contribution_sums_to_portfolio_variance() {
+        let returns = HashMap::from([
+            ("A".to_string(), vec![0.01, -0.02, 0.015, 0.03, -0.01]),
+            ("B".to_string(), vec![0.005, 0.01, -0.005, 0.02, 0.0]),
+        ]);
+        let weights = HashMap::from([("A".to_string(), 0.6), ("B".to_string(), 0.4)]);
+
+        let contributions = risk_contribution(returns.clone(), weights).unwrap();
+
+        let portfolio: Vec<f64> = (0..5).map(|t| 0.6 * returns["A"][t] + 0.4 * returns["B"][t]).collect();
+        let portfolio_variance = covariance_matrix(&[portfolio])[0][0];
+        let total: f64 = contributions.values().sum();
+        assert!((total - portfolio_variance).abs() < 1e-12);
+        assert!(contributions["A"] > contributions["B"]);
+
+        let uneven = HashMap::from([
+            ("A".to_string(), vec![0.01, 0.02]),
+            ("B".to_string(), vec![0.01]),
+        ]);
+        assert!(risk_contribution(uneven, HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_risk_parity_weights() {
+        let base = vec![0.01, -0.02, 0.015, 0.03, -0.01, 0.005];
+        let returns = HashMap::from([
+            ("A".to_string(), base.iter().map(|r| r * 2.0).collect::<Vec<f64>>()),
+            ("B".to_string(), base.clone()),
+        ]);
+
+        // 波动率 2:1 -> 权重 1:2
+        let weights = risk_parity_weights(returns.clone(), "inverse_vol").unwrap();
+        assert!((weights["A"] - 1.0 / 3.0).abs() < 1e-12);
+        assert!((weights["B"] - 2.0 / 3.0).abs() < 1e-12);
+
+        // 不相关的两资产，最小方差权重与方差成反比
+        let uncorrelated = HashMap::from([
+            ("A".to_string(), vec![0.02, -0.02, 0.02, -0.02]),
+            ("B".to_string(), vec![0.01, 0.01, -0.01, -0.01]),
+        ]);
+        let min_var = risk_parity_weights(uncorrelated, "min_variance").unwrap();
+        assert!((min_var["A"] - 0.2).abs() < 1e-12);
+        assert!((min_var["B"] - 0.8).abs() < 1e-12);
+
+        // 完全相关时协方差矩阵奇异
+        assert!(risk_parity_weights(returns.clone(), "min_variance").is_err());
+        assert!(risk_parity_weights(returns, "equal").is_err());
+    }
+
+    #[test]
+    fn test_treynor_ratio() {
+        // beta = 1 的资产，每期比市场多 0.05%
+        let market = vec![0.01, -0.005, 0.02, -0.01, 0.015, 0.0];
+        let asset: Vec<f64> = market.iter().map(|r| r + 0.0005).collect();
+
+        let treynor = treynor_ratio(asset.clone(), market.clone(), 0.02, 252.0).unwrap().unwrap();
+        let expected = mean(&asset) * 252.0 - 0.02;
+        assert!(treynor > 0.0);
+        assert!((treynor - expected).abs() < 1e-9);
+
+        // 与市场无关 (市场无波动) 时无法估计 beta
+        assert_eq!(treynor_ratio(asset, vec![0.01; 6], 0.02, 252.0).unwrap(), None);
+        assert!(treynor_ratio(vec![0.01], market, 0.02, 252.0).is_err());
+    }
+
+    #[test]
+    fn test_parse_params() {
+        assert!(parse_params("").unwrap().is_empty());
+        assert!(parse_params("{}").unwrap().is_empty());
+        assert_eq!(parse_params(r#"{"short_period": 10}"#).unwrap()["short_period"], 10.0);
+        assert!(parse_params(r#"{"short_period": 10"#).is_err());
+        assert!(parse_params(r#"{"short_period": "10"}"#).is_err());
+    }
+
+    #[test]
+    fn test_run_strategy_rejects_non_positive_periods() {
+        let klines = sine_klines(40, 10.0, 0.0);
+        let run = |strategy: &str, params: &str| {
+            run_strategy(
+                &klines, 10_000.0, 0.0, strategy, params, 0.0, 0.0, TRADING_DAYS_PER_YEAR, 0.0, 0.0, 0,
+            )
+        };
+
+        assert!(run("sma_cross", r#"{"short_period": 3, "long_period": 5}"#).is_ok());
+        assert!(run("sma_cross", r#"{"short_period": 0}"#).is_err());
+        assert!(run("sma_cross", r#"{"long_period": 0.5}"#).is_err());
+        assert!(run("momentum", r#"{"period": 0}"#).is_err());
+        assert!(run("momentum", r#"{"period": -5}"#).is_err());
+        assert!(run("momentum", r#"{"period": 5}"#).is_ok());
+    }
+
+    #[test]
+    fn test_scale_out_two_tiers() {
+        let params = r#"{"scale_out": [[10, 0.5], [5, 0.5]], "short_period": 5}"#;
+        assert_eq!(parse_params(params).unwrap().len(), 1);
+        let tiers = parse_scale_out(params).unwrap();
+        assert_eq!(tiers, vec![(5.0, 0.5), (10.0, 0.5)]);
+        assert!(parse_scale_out(r#"{"scale_out": [[5, 1.5]]}"#).is_err());
+        assert!(parse_scale_out("{}").unwrap().is_empty());
+
+        let mut engine = BacktestEngine::new(10_000.0, 0.0);
+        let mut plan = ScaleOutPlan::new(tiers);
+        let trade = engine.process_order(Order {
+            id: "buy".to_string(),
+            symbol: "TEST".to_string(),
+            trade_type: TradeType::Buy,
+            price: 100.0,
+            quantity: 90.0,
+            timestamp: 0,
+            status: OrderStatus::Pending,
+        }).unwrap();
+        plan.start(trade.price, trade.quantity);
+
+        // +3%: 未触发
+        assert!(!plan.apply(&mut engine, "TEST", 103.0, 1));
+        assert_eq!(engine.positions["TEST"].quantity, 90.0);
+
+        // +5%: 卖出一半
+        assert!(!plan.apply(&mut engine, "TEST", 105.0, 2));
+        assert!((engine.positions["TEST"].quantity - 45.0).abs() < 1e-9);
+
+        // 同一档位不重复触发
+        assert!(!plan.apply(&mut engine, "TEST", 106.0, 3));
+        assert!((engine.positions["TEST"].quantity - 45.0).abs() < 1e-9);
+
+        // +10%: 卖出剩余
+        assert!(plan.apply(&mut engine, "TEST", 110.0, 4));
+        assert!(!engine.positions.contains_key("TEST"));
+
+        let sells: Vec<(f64, f64)> = engine.trades.iter()
+            .filter(|t| t.trade_type == TradeType::Sell)
+            .map(|t| (t.price, t.quantity))
+            .collect();
+        assert_eq!(sells, vec![(105.0, 45.0), (110.0, 45.0)]);
+        assert!((engine.current_capital - (10_000.0 - 9_000.0 + 45.0 * 105.0 + 45.0 * 110.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_var_cvar_historical() {
+        // -5%, -4.9%, ..., +4.9% 共100个收益
+        let returns: Vec<f64> = (0..100).map(|i| (i as f64 - 50.0) / 1000.0).collect();
+        let result = var_cvar(returns.clone(), 0.95, "historical").unwrap();
+
+        // 最差的5个收益为 -5.0% .. -4.6%，VaR 为第5差的 4.6%
+        assert!((result["var"] - 0.046).abs() < 1e-12);
+        assert!((result["cvar"] - 0.048).abs() < 1e-12);
+        assert!(result["cvar"] >= result["var"]);
+        assert!(result["downside_deviation"] > 0.0);
+
+        let gaussian = var_cvar(returns.clone(), 0.95, "gaussian").unwrap();
+        assert!((normal_quantile(0.95) - 1.644_853_626_951).abs() < 1e-8);
+        assert!(gaussian["cvar"] > gaussian["var"]);
+
+        assert!(var_cvar(returns.clone(), 1.5, "historical").is_err());
+        assert!(var_cvar(returns, 0.95, "monte_carlo").is_err());
+    }
+
+    #[test]
+    fn test_rolling_max_drawdown_matches_brute_force() {
+        let equity = vec![
+            100.0, 105.0, 98.0, 102.0, 110.0, 95.0, 97.0, 120.0, 90.0, 91.0, 130.0, 125.0,
+        ];
+
+        for window in 1..=equity.len() {
+            let fast = rolling_max_drawdown(equity.clone(), window).unwrap();
+            for (i, value) in fast.iter().enumerate() {
+                if i + 1 < window {
+                    assert!(value.is_none());
+                    continue;
+                }
+                let slice = &equity[i + 1 - window..=i];
+                let curve: Vec<EquityPoint> = slice.iter()
+                    .map(|&equity| EquityPoint { timestamp: 0, equity })
+                    .collect();
+                let expected = drawdown_series(&curve).into_iter().fold(0.0, f64::max);
+                assert!((value.unwrap() - expected).abs() < 1e-9, "window {} index {}", window, i);
+            }
+        }
+
+        assert!(rolling_max_drawdown(equity, 0).is_err());
+    }
+
+    #[test]
+    fn test_cost_attribution_slippage_drag() {
+        let run = |slippage_rate: f64| {
+            let mut engine = BacktestEngine::new(10_000.0, 0.0).with_costs(slippage_rate, 0.0);
+            let mut prices = HashMap::new();
+            for (i, (trade_type, price)) in [(TradeType::Buy, 10.0), (TradeType::Sell, 12.0)]
+                .into_iter()
+                .enumerate()
+            {
+                engine.process_order(Order {
+                    id: format!("order_{}", i),
+                    symbol: "TEST".to_string(),
+                    trade_type,
+                    price,
+                    quantity: 500.0,
+                    timestamp: i as i64 * MS_PER_DAY,
+                    status: OrderStatus::Pending,
+                });
+                prices.insert("TEST".to_string(), price);
+                engine.mark_to_market(i as i64 * MS_PER_DAY, &prices);
+            }
+            engine.calculate_result(&prices).cost_attribution
+        };
+
+        let frictionless = run(0.0);
+        let slipped = run(0.001);
+
+        // 买入多付 500 * 0.01，卖出少收 500 * 0.012
+        assert!((slipped.slippage_drag - 11.0).abs() < 1e-9);
+        assert!((slipped.gross_return - frictionless.gross_return).abs() < 1e-9);
+        assert!((frictionless.net_return - slipped.net_return - slipped.slippage_drag).abs() < 1e-9);
+        assert_eq!(frictionless.slippage_drag, 0.0);
+    }
+
+    #[test]
+    fn test_sterling_ratio_averages_yearly_drawdowns() {
+        let ts = |y: i32, m: u32| {
+            chrono::NaiveDate::from_ymd_opt(y, m, 1).unwrap()
+                .and_hms_opt(0, 0, 0).unwrap()
+                .and_utc()
+                .timestamp_millis()
+        };
+        // 2020年回撤10%，2021年回撤20%，2022年无回撤
+        let equity_curve = vec![
+            EquityPoint { timestamp: ts(2020, 1), equity: 100.0 },
+            EquityPoint { timestamp: ts(2020, 6), equity: 90.0 },
+            EquityPoint { timestamp: ts(2020, 12), equity: 95.0 },
+            EquityPoint { timestamp: ts(2021, 1), equity: 100.0 },
+            EquityPoint { timestamp: ts(2021, 6), equity: 80.0 },
+            EquityPoint { timestamp: ts(2021, 12), equity: 120.0 },
+            EquityPoint { timestamp: ts(2022, 1), equity: 130.0 },
+            EquityPoint { timestamp: ts(2022, 12), equity: 150.0 },
+        ];
+
+        let expected = annualized_return(&equity_curve) / (10.0 + 10.0);
+        assert!((sterling_ratio(&equity_curve).unwrap() - expected).abs() < 1e-9);
+
+        // 单一年份退化为整体最大回撤
+        let single_year = &equity_curve[..3];
+        let expected = annualized_return(single_year) / (10.0 + 10.0);
+        assert!((sterling_ratio(single_year).unwrap() - expected).abs() < 1e-9);
+        assert!(sterling_ratio(&equity_curve[..1]).is_none());
+    }
+
+    #[test]
+    fn test_return_autocorrelation_detects_clustering() {
+        // 盈亏成簇出现：连续4笔盈利后连续4笔亏损
+        let returns: Vec<f64> = (0..40)
+            .map(|i| if (i / 4) % 2 == 0 { 0.02 } else { -0.01 })
+            .collect();
+        let acf = return_autocorrelation(returns.clone(), 4).unwrap();
+        assert_eq!(acf.len(), 4);
+        assert!(acf[0] > 0.5);
+        assert!(acf[3] < acf[0]);
+
+        // 盈亏交替出现为负自相关
+        let alternating: Vec<f64> = (0..40).map(|i| if i % 2 == 0 { 0.02 } else { -0.01 }).collect();
+        assert!(return_autocorrelation(alternating, 1).unwrap()[0] < -0.9);
+
+        assert_eq!(return_autocorrelation(vec![0.01; 10], 2).unwrap(), vec![0.0, 0.0]);
+        assert!(return_autocorrelation(returns, 40).is_err());
+    }
+
+    #[test]
+    fn test_appraisal_ratio_pure_alpha() {
+        // 市场收益与策略噪声正交：Beta 为0，Alpha 为 0.1%
+        let market: Vec<f64> = (0..40).map(|i| if i % 2 == 0 { 0.01 } else { -0.01 }).collect();
+        let noise: Vec<f64> = (0..40).map(|i| if (i / 2) % 2 == 0 { 0.002 } else { -0.002 }).collect();
+        let returns: Vec<f64> = noise.iter().map(|e| 0.001 + e).collect();
+
+        let (alpha, beta) = ols_alpha_beta(&returns, &market).unwrap();
+        assert!(beta.abs() < 1e-12);
+        assert!((alpha - 0.001).abs() < 1e-12);
+
+        let residual_std = (40.0 * 0.002f64.powi(2) / 38.0).sqrt();
+        let expected = 0.001 * 252.0 / (residual_std * 252f64.sqrt());
+        let ratio = appraisal_ratio(returns, market.clone(), 252.0).unwrap().unwrap();
+        assert!((ratio - expected).abs() < 1e-9);
+
+        // 完全由市场解释，残差为0
+        let levered: Vec<f64> = market.iter().map(|m| 0.001 + 1.5 * m).collect();
+        assert_eq!(appraisal_ratio(levered, market, 252.0).unwrap(), None);
+    }
+
+    #[test]
+    fn test_cumulative_alpha_curve_zero_for_pure_beta() {
+        let market = vec![0.01, -0.02, 0.015, 0.003, -0.007, 0.012];
+        let strategy: Vec<f64> = market.iter().map(|m| 1.3 * m).collect();
+
+        let curve = cumulative_alpha_curve(&strategy, &market, 0.0).unwrap();
+        assert_eq!(curve.len(), market.len());
+        assert!(curve.iter().all(|a| a.abs() < 1e-12));
+
+        // 每期固定超额 0.1%，累计 Alpha 线性增长
+        let with_alpha: Vec<f64> = strategy.iter().map(|r| r + 0.001).collect();
+        let curve = cumulative_alpha_curve(&with_alpha, &market, 0.0).unwrap();
+        assert!((curve[5] - 0.006).abs() < 1e-12);
+
+        assert!(cumulative_alpha_curve(&strategy, &market[1..], 0.0).is_none());
+    }
+
+    #[test]
+    fn test_factor_backtest_monotonic_factor() {
+        // 因子值与远期收益单调相关：股票 i 的因子为 i，远期收益为 1% * i
+        let mut factors: Panel = HashMap::new();
+        let mut returns: Panel = HashMap::new();
+        for date in ["2024-01-31", "2024-02-29", "2024-03-31"] {
+            let mut f = HashMap::new();
+            let mut r = HashMap::new();
+            for i in 0..10 {
+                f.insert(format!("S{}", i), i as f64);
+                r.insert(format!("S{}", i), 0.01 * i as f64);
+            }
+            factors.insert(date.to_string(), f);
+            returns.insert(date.to_string(), r);
+        }
+
+        let result = factor_backtest(factors.clone(), returns.clone(), 0.2, 10.0).unwrap();
+
+        // 多头 S8/S9 均值 8.5%，空头 S0/S1 均值 0.5%，价差 8%
+        assert!((result["cumulative_gross_return"] - (1.08f64.powi(3) - 1.0)).abs() < 1e-12);
+        // 仅首期建仓换手 2.0，成本 2 * 10bp
+        let expected_net = (1.08 - 0.002) * 1.08 * 1.08 - 1.0;
+        assert!((result["cumulative_net_return"] - expected_net).abs() < 1e-12);
+        assert!((result["average_turnover"] - 2.0 / 3.0).abs() < 1e-12);
+        assert_eq!(result["periods"], 3.0);
+
+        // 因子反向时价差为负
+        let reversed: Panel = factors.iter()
+            .map(|(d, f)| (d.clone(), f.iter().map(|(s, v)| (s.clone(), -v)).collect()))
+            .collect();
+        assert!(factor_backtest(reversed, returns.clone(), 0.2, 0.0).unwrap()["cumulative_gross_return"] < 0.0);
+
+        assert!(factor_backtest(factors, returns, 0.8, 10.0).is_err());
+    }
+
+    #[test]
+    fn test_settlement_t_plus_one() {
+        for (settlement_days, same_bar_rebuy) in [(0, true), (1, false)] {
+            let mut engine = BacktestEngine::new(1_000.0, 0.0).with_settlement(settlement_days);
+            assert!(engine.process_order(order("TEST", TradeType::Buy, 10.0, 100.0, 0)).is_some());
+            assert!(engine.process_order(order("TEST", TradeType::Sell, 10.0, 100.0, MS_PER_DAY)).is_some());
+
+            assert_eq!(engine.current_capital, 1_000.0);
+            let expected_unsettled = if same_bar_rebuy { 0.0 } else { 1_000.0 };
+            assert_eq!(engine.unsettled_cash(MS_PER_DAY), expected_unsettled);
+            assert_eq!(engine.settled_cash(2 * MS_PER_DAY), 1_000.0);
+
+            // T+1 下当天卖出的资金不能当天买回，次日可用
+            assert_eq!(engine.process_order(order("TEST", TradeType::Buy, 10.0, 50.0, MS_PER_DAY)).is_some(), same_bar_rebuy);
+            if !same_bar_rebuy {
+                assert!(engine.process_order(order("TEST", TradeType::Buy, 10.0, 50.0, 2 * MS_PER_DAY)).is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn test_max_drawdown_marks_open_positions() {
+        let mut engine = BacktestEngine::new(10_000.0, 0.0003);
+        let mut prices = HashMap::new();
+        engine.process_order(Order {
+            id: "buy".to_string(),
+            symbol: "TEST".to_string(),
+            trade_type: TradeType::Buy,
+            price: 10.0,
+            quantity: 950.0,
+            timestamp: 0,
+            status: OrderStatus::Pending,
+        }).unwrap();
+
+        // 买入后单边上涨并持有
+        for (day, close) in [10.0, 10.5, 11.0, 12.0].into_iter().enumerate() {
+            prices.insert("TEST".to_string(), close);
+            engine.mark_to_market(day as i64 * MS_PER_DAY, &prices);
+        }
+
+        // 仅有买入手续费造成的微小回撤，而非按现金计算时的 95%
+        let result = engine.calculate_result(&prices);
+        assert!(result.max_drawdown < 0.05, "max_drawdown = {}", result.max_drawdown);
+
+        // 回落时按盯市权益计算回撤
+        prices.insert("TEST".to_string(), 9.0);
+        engine.mark_to_market(4 * MS_PER_DAY, &prices);
+        let cash = 10_000.0 - 9_500.0 - 9_500.0 * 0.0003;
+        let peak = cash + 950.0 * 12.0;
+        let trough = peak - 950.0 * 3.0;
+        let expected = (peak - trough) / peak * 100.0;
+        assert!((engine.calculate_result(&prices).max_drawdown - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_fifo_lot_matching_with_pyramiding() {
+        let mut engine = BacktestEngine::new(10_000.0, 0.0);
+
+        // 加仓两次后分两次卖出
+        engine.process_order(order("TEST", TradeType::Buy, 10.0, 100.0, 1)).unwrap();
+        engine.process_order(order("TEST", TradeType::Buy, 12.0, 100.0, 2)).unwrap();
+        engine.process_order(order("TEST", TradeType::Sell, 11.0, 150.0, 3)).unwrap();
+        engine.process_order(order("TEST", TradeType::Sell, 13.0, 50.0, 4)).unwrap();
+
+        assert_eq!(engine.realized_pnls(), &[100.0, -50.0, 50.0]);
+        let result = engine.calculate_result(&HashMap::new());
+        assert_eq!(result.winning_trades, 2);
+        assert_eq!(result.losing_trades, 1);
+        assert!((result.win_rate - 200.0 / 3.0).abs() < 1e-9);
+        assert!(engine.lots.is_empty());
+
+        // 手续费按匹配数量分摊
+        let mut engine = BacktestEngine::new(10_000.0, 0.001);
+        engine.process_order(order("TEST", TradeType::Buy, 10.0, 100.0, 1)).unwrap();
+        engine.process_order(order("TEST", TradeType::Sell, 11.0, 40.0, 2)).unwrap();
+        let expected = 40.0 - 1.0 * 0.4 - 0.44;
+        assert!((engine.realized_pnls()[0] - expected).abs() < 1e-9);
+        assert!((engine.lots["TEST"][0].commission - 0.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_short_sell_and_cover() {
+
+        // 默认不允许卖空
+        let mut engine = BacktestEngine::new(10_000.0, 0.001);
+        assert!(engine.process_order(order("TEST", TradeType::Sell, 100.0, 10.0, 1)).is_none());
+
+        let mut engine = BacktestEngine::new(10_000.0, 0.001).with_short_selling(true);
+        engine.process_order(order("TEST", TradeType::Sell, 100.0, 10.0, 1)).unwrap();
+        assert_eq!(engine.positions["TEST"].quantity, -10.0);
+        assert_eq!(engine.positions["TEST"].avg_price, 100.0);
+
+        // 空头浮盈随价格下跌为正
+        let mut prices = HashMap::new();
+        prices.insert("TEST".to_string(), 95.0);
+        engine.mark_to_market(2, &prices);
+        assert!((engine.positions["TEST"].unrealized_pnl - 50.0).abs() < 1e-9);
+        assert!((engine.equity_curve[0].equity - (10_000.0 + 999.0 - 950.0)).abs() < 1e-9);
+
+        // 在90买入平空
+        engine.process_order(order("TEST", TradeType::Buy, 90.0, 10.0, 3)).unwrap();
+        assert!(engine.positions.is_empty());
+        let expected = (100.0 - 90.0) * 10.0 - 1.0 - 0.9;
+        assert!((engine.realized_pnls()[0] - expected).abs() < 1e-9);
+        assert!((engine.current_capital - (10_000.0 + expected)).abs() < 1e-9);
+
+        // 多头卖出超过持仓时反手开空
+        engine.process_order(order("TEST", TradeType::Buy, 50.0, 10.0, 4)).unwrap();
+        engine.process_order(order("TEST", TradeType::Sell, 60.0, 15.0, 5)).unwrap();
+        assert_eq!(engine.positions["TEST"].quantity, -5.0);
+        assert_eq!(engine.positions["TEST"].avg_price, 60.0);
+        assert_eq!(engine.realized_pnls().len(), 2);
+    }
+
+    #[test]
+    fn test_result_deducts_open_short_liability() {
+        let mut engine = BacktestEngine::new(10_000.0, 0.0).with_short_selling(true);
+        engine.process_order(Order {
+            id: "short".to_string(),
+            symbol: "TEST".to_string(),
+            trade_type: TradeType::Sell,
+            price: 100.0,
+            quantity: 50.0,
+            timestamp: 0,
+            status: OrderStatus::Pending,
+        }).unwrap();
+        engine.mark_to_market(1, &HashMap::from([("TEST".to_string(), 150.0)]));
+
+        // 现金 15000，回补负债 7500
+        let result = engine.calculate_result(&HashMap::new());
+        assert!((result.final_capital - 7_500.0).abs() < 1e-9);
+        assert!((result.total_return + 25.0).abs() < 1e-9);
+        assert!((result.final_capital - engine.equity_curve[0].equity).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_mark_to_market_keeps_last_price_for_missing_bar() {
+        let mut engine = BacktestEngine::new(10_000.0, 0.0);
+        engine.process_order(Order {
+            id: "buy".to_string(),
+            symbol: "TEST".to_string(),
+            trade_type: TradeType::Buy,
+            price: 100.0,
+            quantity: 50.0,
+            timestamp: 0,
+            status: OrderStatus::Pending,
+        }).unwrap();
+        engine.mark_to_market(1, &HashMap::from([("TEST".to_string(), 120.0)]));
+        // 该K线缺少 TEST 的价格，沿用上一次盯市价格
+        engine.mark_to_market(2, &HashMap::new());
+
+        assert_eq!(engine.equity_curve[1].equity, engine.equity_curve[0].equity);
+        assert!((engine.equity_curve[1].equity - 11_000.0).abs() < 1e-9);
+        assert_eq!(engine.exposures()[1].long, engine.exposures()[0].long);
+        assert!((engine.positions["TEST"].unrealized_pnl - 1_000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_result_values_open_positions_at_final_prices() {
+        let mut engine = BacktestEngine::new(10_000.0, 0.0).with_short_selling(true);
+        engine.process_order(order("LONG", TradeType::Buy, 50.0, 100.0, 0)).unwrap();
+        engine.process_order(order("SHORT", TradeType::Sell, 20.0, 100.0, 0)).unwrap();
+        let marks = HashMap::from([("LONG".to_string(), 60.0), ("SHORT".to_string(), 25.0)]);
+        engine.mark_to_market(1, &marks);
+
+        // 未提供期末价格时按最近盯市价格计价：现金 7000 + 多头 6000 - 空头 2500
+        let result = engine.calculate_result(&HashMap::new());
+        assert!((result.final_capital - 10_500.0).abs() < 1e-9);
+        assert!((result.total_return - 5.0).abs() < 1e-9);
+
+        // 期末价格优先，缺失的股票回退到最近盯市价格
+        let result = engine.calculate_result(&HashMap::from([("LONG".to_string(), 70.0)]));
+        assert!((result.final_capital - 11_500.0).abs() < 1e-9);
+        assert!((result.total_return - 15.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_calculate_sma_first_full_window() {
+        let klines: Vec<(i64, f64, f64, f64, f64, f64)> = [1.0, 2.0, 3.0, 4.0]
+            .iter()
+            .enumerate()
+            .map(|(i, &close)| (i as i64, close, close, close, close, 0.0))
+            .collect();
+        assert_eq!(calculate_sma(&klines, 3), vec![None, None, Some(2.0), Some(3.0)]);
+        assert_eq!(calculate_sma(&klines, 1)[0], Some(1.0));
+    }
+
+    #[test]
+    fn test_sma_cross_waits_for_new_cross_after_exit() {
+        // 持续上涨，第10根K线下影线触发止损，均线始终保持多头排列
+        let klines: Vec<(i64, f64, f64, f64, f64, f64)> = (0..25)
+            .map(|i| {
+                let close = 100.0 + i as f64;
+                let low = if i == 10 { 90.0 } else { close - 1.0 };
+                (i as i64 * MS_PER_DAY, close, close + 1.0, low, close, 1_000.0)
+            })
+            .collect();
+        let buys = |params: &str| -> Vec<i64> {
+            let (engine, _) = run_strategy(
+                &klines, 10_000.0, 0.0, "sma_cross", params, 0.0, 0.0, TRADING_DAYS_PER_YEAR, 0.0, 0.0, 0,
+            ).unwrap();
+            engine.trades.iter()
+                .filter(|t| t.trade_type == TradeType::Buy)
+                .map(|t| t.timestamp / MS_PER_DAY)
+                .collect()
+        };
+
+        let (engine, triggers) = run_strategy(
+            &klines, 10_000.0, 0.0, "sma_cross",
+            r#"{"short_period": 3, "long_period": 5, "stop_loss": 0.05}"#,
+            0.0, 0.0, TRADING_DAYS_PER_YEAR, 0.0, 0.0, 0,
+        ).unwrap();
+        assert_eq!(triggers.len(), 1);
+        assert_eq!(triggers[0].0, 10 * MS_PER_DAY);
+        assert!(engine.positions.is_empty());
+        assert_eq!(buys(r#"{"short_period": 3, "long_period": 5, "stop_loss": 0.05}"#), vec![5]);
+
+        // 分批止盈全部卖出后同样不立即买回
+        assert_eq!(buys(r#"{"short_period": 3, "long_period": 5, "scale_out": [[5, 1.0]]}"#), vec![5]);
+    }
+
+    #[test]
+    fn test_protective_exits_fill_at_trigger_price() {
+        let mut params = HashMap::new();
+        params.insert("stop_loss".to_string(), 0.05);
+        params.insert("take_profit".to_string(), 0.1);
+        let exits = ProtectiveExits::new(&params).unwrap();
+
+        let buy = |engine: &mut BacktestEngine| {
+            engine.process_order(Order {
+                id: "buy".to_string(),
+                symbol: "TEST".to_string(),
+                trade_type: TradeType::Buy,
+                price: 100.0,
+                quantity: 10.0,
+                timestamp: 0,
+                status: OrderStatus::Pending,
+            }).unwrap();
+        };
+
+        // 盘中跌破止损价，以止损价而非收盘价成交
+        let mut engine = BacktestEngine::new(10_000.0, 0.0);
+        buy(&mut engine);
+        assert_eq!(exits.apply(&mut engine, "TEST", &(1, 99.0, 101.0, 98.0, 100.0, 0.0)), None);
+        assert_eq!(exits.apply(&mut engine, "TEST", &(2, 99.0, 99.5, 94.0, 98.0, 0.0)), Some(("stop_loss", 95.0)));
+        assert!(engine.positions.is_empty());
+        assert_eq!(engine.trades[1].price, 95.0);
+
+        // 跳空高开越过止盈价，以开盘价成交
+        let mut engine = BacktestEngine::new(10_000.0, 0.0);
+        buy(&mut engine);
+        assert_eq!(exits.apply(&mut engine, "TEST", &(1, 112.0, 115.0, 111.0, 113.0, 0.0)), Some(("take_profit", 112.0)));
+        assert!((engine.realized_pnls()[0] - 120.0).abs() < 1e-9);
+
+        // 无持仓时不触发
+        assert_eq!(exits.apply(&mut engine, "TEST", &(2, 50.0, 50.0, 50.0, 50.0, 0.0)), None);
+
+        params.insert("stop_loss".to_string(), 1.5);
+        assert!(ProtectiveExits::new(&params).is_err());
+    }
+
+    #[test]
+    fn test_rolling_information_ratio() {
+        // 主动收益按 +0.2%, 0 交替，之后恒为 +0.1%
+        let market: Vec<f64> = (0..12).map(|i| 0.001 * (i % 3) as f64 - 0.001).collect();
+        let active: Vec<f64> = (0..12)
+            .map(|i| if i >= 8 { 0.001 } else if i % 2 == 0 { 0.002 } else { 0.0 })
+            .collect();
+        let strategy: Vec<f64> = market.iter().zip(active.iter()).map(|(m, a)| m + a).collect();
+
+        let ir = rolling_information_ratio(&strategy, &market, 4, 252.0);
+        assert_eq!(ir.len(), 12);
+        assert!(ir[..3].iter().all(|v| v.is_none()));
+        // 均值 0.001，总体标准差 0.001
+        for value in &ir[3..8] {
+            assert!((value.unwrap() - 252f64.sqrt()).abs() < 1e-6);
+        }
+        // 主动收益恒定时跟踪误差为0
+        assert_eq!(ir[11], None);
+    }
+
+    #[test]
+    fn test_simple_backtest_slippage_bps_lowers_return() {
+        pyo3::prepare_freethreaded_python();
+        // 涨跌交替的趋势，产生多次均线交叉
+        let klines = sine_klines(120, 8.0, 0.1);
+        let params = r#"{"short_period": 3, "long_period": 8}"#;
+
+        let total_return = |slippage_bps: f64| -> f64 {
+            let result = simple_backtest(
+                klines.clone(), 100_000.0, 0.0003, "sma_cross", params, None, 0.0, 20, slippage_bps, 252.0, 20, 1, 0.0, 0.0, 0,
+            ).unwrap();
+            Python::with_gil(|py| {
+                let dict = result.downcast_bound::<PyDict>(py).unwrap();
+                assert!(dict.get_item("total_trades").unwrap().unwrap().extract::<usize>().unwrap() > 0);
+                dict.get_item("total_return").unwrap().unwrap().extract().unwrap()
+            })
+        };
+
+        assert!(total_return(10.0) < total_return(0.0));
+
+        let both = simple_backtest(
+            klines.clone(), 100_000.0, 0.0003, "sma_cross", r#"{"slippage": 0.001}"#, None, 0.0, 20, 10.0, 252.0, 20, 1, 0.0, 0.0, 0,
+        );
+        assert!(both.is_err());
+    }
+
+    #[test]
+    fn test_simple_backtest_information_ratio_uses_periods_per_year() {
+        pyo3::prepare_freethreaded_python();
+        let klines = sine_klines(80, 8.0, 0.1);
+        let benchmark: Vec<f64> = (0..80).map(|i| 100.0 + 0.05 * i as f64).collect();
+        let params = r#"{"short_period": 3, "long_period": 8}"#;
+
+        let information_ratio = |periods_per_year: f64| -> Vec<Option<f64>> {
+            let result = simple_backtest(
+                klines.clone(), 100_000.0, 0.0, "sma_cross", params, Some(benchmark.clone()),
+                0.0, 10, 0.0, periods_per_year, 20, 1, 0.0, 0.0, 0,
+            ).unwrap();
+            Python::with_gil(|py| {
+                let dict = result.downcast_bound::<PyDict>(py).unwrap();
+                dict.get_item("rolling_information_ratio").unwrap().unwrap().extract().unwrap()
+            })
+        };
+
+        let daily = information_ratio(252.0);
+        let weekly = information_ratio(52.0);
+        let scale = (52.0f64 / 252.0).sqrt();
+        assert!(daily.iter().any(|v| v.is_some()));
+        for (d, w) in daily.iter().zip(weekly.iter()) {
+            match (d, w) {
+                (Some(d), Some(w)) => assert!((w - d * scale).abs() < 1e-9),
+                (None, None) => {}
+                _ => panic!("information ratio availability should not depend on periods_per_year"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_exposure_summary_long_short_book() {
+        let mut engine = BacktestEngine::new(10_000.0, 0.0).with_short_selling(true);
+        engine.process_order(order("LONG", TradeType::Buy, 50.0, 100.0, 0)).unwrap();
+        engine.process_order(order("SHORT", TradeType::Sell, 20.0, 100.0, 0)).unwrap();
+
+        let mut prices = HashMap::new();
+        for (day, (long_price, short_price)) in [(50.0, 20.0), (55.0, 18.0), (45.0, 25.0)].into_iter().enumerate() {
+            prices.insert("LONG".to_string(), long_price);
+            prices.insert("SHORT".to_string(), short_price);
+            engine.mark_to_market(day as i64, &prices);
+        }
+
+        assert_eq!(engine.exposures().len(), engine.equity_curve.len());
+        for (exposure, point) in engine.exposures().iter().zip(engine.equity_curve.iter()) {
+            assert!((exposure.net - (exposure.long - exposure.short)).abs() < 1e-12);
+            assert!((exposure.gross - (exposure.long + exposure.short)).abs() < 1e-12);
+            assert!(exposure.long > 0.0 && exposure.short > 0.0);
+            assert_eq!(exposure.timestamp, point.timestamp);
+        }
+
+        // 首日: 多头 5000、空头 2000，权益 10000
+        let first = engine.exposures()[0];
+        assert!((first.long - 0.5).abs() < 1e-12);
+        assert!((first.short - 0.2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_python_engine_custom_loop() {
+        pyo3::prepare_freethreaded_python();
+        let mut engine = BacktestEngine::py_new(10_000.0, 0.0, 0.0, 0.0, 0, false, 252.0, 0.0, 0.0, 0.0, 0);
+
+        let trade = engine.submit_order("600519".to_string(), "buy", 100.0, 50.0, 0).unwrap().unwrap();
+        assert_eq!(trade.trade_type, TradeType::Buy);
+        assert_eq!(engine.cash(), 5_000.0);
+        assert_eq!(engine.py_positions()["600519"].quantity, 50.0);
+
+        // 持仓不足被拒绝，非法方向报错
+        assert!(engine.submit_order("600519".to_string(), "sell", 110.0, 80.0, 1).unwrap().is_none());
+        assert!(engine.submit_order("600519".to_string(), "hold", 110.0, 10.0, 1).is_err());
+
+        let mut prices = HashMap::new();
+        prices.insert("600519".to_string(), 110.0);
+        engine.py_mark_to_market(1, prices.clone());
+        engine.submit_order("600519".to_string(), "sell", 110.0, 50.0, 2).unwrap().unwrap();
+        assert!(engine.py_positions().is_empty());
+        assert_eq!(engine.py_trades().len(), 2);
+
+        Python::with_gil(|py| {
+            let result = engine.py_result(py, prices).unwrap();
+            let dict = result.downcast_bound::<PyDict>(py).unwrap();
+            let final_capital: f64 = dict.get_item("final_capital").unwrap().unwrap().extract().unwrap();
+            assert_eq!(final_capital, 10_500.0);
+            let winning: usize = dict.get_item("winning_trades").unwrap().unwrap().extract().unwrap();
+            assert_eq!(winning, 1);
+        });
+    }
+
+    #[test]
+    fn test_sortino_and_calmar_ratios() {
+        // 收益 +10%, -5%, +10%：下行偏差 = sqrt(0.05^2 / 3)
+        let equity = curve(&[100.0, 110.0, 104.5, 114.95]);
+        let expected = (0.1 - 0.05 + 0.1) / 3.0 / (0.0025f64 / 3.0).sqrt();
+        assert!((sortino_ratio(&equity).unwrap() - expected).abs() < 1e-9);
+
+        // 全部为正收益时为 None
+        assert_eq!(sortino_ratio(&curve(&[100.0, 101.0, 102.0])), None);
+
+        let mut engine = BacktestEngine::new(100.0, 0.0);
+        engine.equity_curve = equity;
+        let result = engine.calculate_result(&HashMap::new());
+        let calmar = result.calmar_ratio.unwrap();
+        assert!((calmar - annualized_return(&engine.equity_curve) / 5.0).abs() < 1e-9);
+
+        engine.equity_curve = curve(&[100.0, 101.0, 102.0]);
+        assert_eq!(engine.calculate_result(&HashMap::new()).calmar_ratio, None);
+    }
+
+    #[test]
+    fn test_sqn_and_expectancy_per_bar() {
+        // 高期望、低波动的 R 序列
+        let r_multiples = [0.5, 0.6, 0.55, 0.45, 0.5, 0.6, 0.55, 0.5, 0.45, 0.6];
+        let sqn = system_quality_number(&r_multiples).unwrap();
+        assert!(sqn > 10.0, "sqn = {}", sqn);
+        assert!(system_quality_number(&[0.5, -0.5, 0.4, -0.6]).unwrap() < 1.0);
+        assert_eq!(system_quality_number(&[0.5, 0.5]), None);
+        assert_eq!(system_quality_number(&[0.5]), None);
+
+        // 每天盯市，持有3根K线获利10%，再持有2根K线亏损5%
+        let mut engine = BacktestEngine::new(10_000.0, 0.0);
+        let mut prices = HashMap::new();
+        let closes = [100.0, 105.0, 108.0, 110.0, 110.0, 107.0, 104.5];
+        for (day, &close) in closes.iter().enumerate() {
+            let timestamp = day as i64 * MS_PER_DAY;
+            let trade_type = match day {
+                0 | 4 => Some(TradeType::Buy),
+                3 | 6 => Some(TradeType::Sell),
+                _ => None,
+            };
+            if let Some(trade_type) = trade_type {
+                engine.process_order(Order {
+                    id: format!("{}", day),
+                    symbol: "TEST".to_string(),
+                    trade_type,
+                    price: close,
+                    quantity: 10.0,
+                    timestamp,
+                    status: OrderStatus::Pending,
+                }).unwrap();
+            }
+            prices.insert("TEST".to_string(), close);
+            engine.mark_to_market(timestamp, &prices);
+        }
+
+        let result = engine.calculate_result(&prices);
+        let expected = (0.1 - 0.05) / 5.0;
+        assert!((result.expectancy_per_bar.unwrap() - expected).abs() < 1e-12);
+        assert!(result.sqn.is_some());
+        assert_eq!(BacktestEngine::new(1.0, 0.0).calculate_result(&prices).expectancy_per_bar, None);
+    }
+
+    #[test]
+    fn test_annualized_sharpe_from_equity_curve() {
+        // 收益 +2%, 0%, +2%, 0%：均值 1%，总体标准差 1%
+        let mut equity = vec![100.0];
+        for r in [0.02, 0.0, 0.02, 0.0] {
+            equity.push(equity[equity.len() - 1] * (1.0 + r));
+        }
+        let curve: Vec<EquityPoint> = equity.iter().enumerate()
+            .map(|(i, &equity)| EquityPoint { timestamp: i as i64 * MS_PER_DAY, equity })
+            .collect();
+
+        let mut engine = BacktestEngine::new(100.0, 0.0);
+        engine.equity_curve = curve.clone();
+        assert!((engine.calculate_result(&HashMap::new()).sharpe_ratio - 252f64.sqrt()).abs() < 1e-9);
+
+        // 无风险收益率按周期扣除，周线年化
+        let mut engine = BacktestEngine::new(100.0, 0.0).with_annualization(52.0, 0.005);
+        engine.equity_curve = curve;
+        assert!((engine.calculate_result(&HashMap::new()).sharpe_ratio - 0.5 * 52f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rolling_return_autocorrelation() {
+        // 前半段正负交替 (强负自相关)，后半段成对出现 (+,+,-,-)
+        let mut returns: Vec<f64> = (0..20).map(|i| if i % 2 == 0 { 0.01 } else { -0.01 }).collect();
+        returns.extend((0..20).map(|i| if (i / 2) % 2 == 0 { 0.01 } else { -0.01 }));
+
+        let rolling = rolling_autocorrelation(&returns, 10, 1);
+        assert_eq!(rolling.len(), returns.len());
+        assert!(rolling[..9].iter().all(|v| v.is_none()));
+        // 与整段窗口直接计算一致
+        for (i, value) in rolling.iter().enumerate().skip(9) {
+            assert_eq!(*value, autocorrelation(&returns[i - 9..=i], 1));
+        }
+        assert!(rolling[15].unwrap() < -0.8);
+        assert!(rolling[39].unwrap().abs() < 0.2);
+
+        // 零方差窗口与滞后阶数过大
+        assert_eq!(rolling_autocorrelation(&[0.01; 12], 10, 1)[11], None);
+        assert!(rolling_autocorrelation(&returns, 5, 5).iter().all(|v| v.is_none()));
+    }
+
+    #[test]
+    fn test_profit_factor_and_trade_averages() {
+        let run = |exits: &[f64]| {
+            let mut engine = BacktestEngine::new(100_000.0, 0.0);
+            for (i, &exit) in exits.iter().enumerate() {
+                for (trade_type, price) in [(TradeType::Buy, 100.0), (TradeType::Sell, exit)] {
+                    engine.process_order(Order {
+                        id: format!("{}", i),
+                        symbol: "TEST".to_string(),
+                        trade_type,
+                        price,
+                        quantity: 10.0,
+                        timestamp: i as i64,
+                        status: OrderStatus::Pending,
+                    }).unwrap();
+                }
+            }
+            engine.calculate_result(&HashMap::new())
+        };
+
+        // 盈亏 +50, -100, +20, -60：亏钱的策略盈利因子小于1
+        let result = run(&[105.0, 90.0, 102.0, 94.0]);
+        assert!(result.total_return < 0.0);
+        assert!((result.profit_factor.unwrap() - 70.0 / 160.0).abs() < 1e-12);
+        assert!((result.expectancy.unwrap() - (-22.5)).abs() < 1e-12);
+        assert!((result.avg_win.unwrap() - 35.0).abs() < 1e-12);
+        assert!((result.avg_loss.unwrap() - (-80.0)).abs() < 1e-12);
+
+        // 没有亏损批次
+        let result = run(&[110.0]);
+        assert_eq!(result.profit_factor, None);
+        assert_eq!(result.avg_loss, None);
+        assert_eq!(run(&[]).expectancy, None);
+    }
+
+    #[test]
+    fn test_multi_factor_betas_orthogonal_factors() {
+        let market: Vec<f64> = (0..16).map(|t| if t % 2 == 0 { 0.01 } else { -0.01 }).collect();
+        let size: Vec<f64> = (0..16).map(|t| if (t / 2) % 2 == 0 { 0.02 } else { -0.02 }).collect();
+        let returns: Vec<f64> = market.iter().zip(size.iter())
+            .map(|(m, s)| 0.001 + 0.8 * m - 0.3 * s)
+            .collect();
+
+        let mut factors = HashMap::new();
+        factors.insert("market".to_string(), market.clone());
+        factors.insert("size".to_string(), size);
+        let result = multi_factor_betas(returns.clone(), factors.clone()).unwrap();
+        assert!((result["alpha"] - 0.001).abs() < 1e-12);
+        assert!((result["market"] - 0.8).abs() < 1e-12);
+        assert!((result["size"] - (-0.3)).abs() < 1e-12);
+        assert!((result["r_squared"] - 1.0).abs() < 1e-9);
+
+        // 共线因子、观测值不足、长度不一致
+        factors.insert("market_copy".to_string(), market.iter().map(|m| 2.0 * m).collect());
+        assert!(multi_factor_betas(returns.clone(), factors).is_err());
+        let mut short = HashMap::new();
+        short.insert("market".to_string(), vec![0.01]);
+        assert!(multi_factor_betas(vec![0.01], short.clone()).is_err());
+        assert!(multi_factor_betas(returns, short).is_err());
+    }
+
+    #[test]
+    fn test_commission_min_and_fixed() {
+        let buy = |engine: &mut BacktestEngine, quantity: f64| {
+            engine.process_order(Order {
+                id: "buy".to_string(),
+                symbol: "TEST".to_string(),
+                trade_type: TradeType::Buy,
+                price: 10.0,
+                quantity,
+                timestamp: 0,
+                status: OrderStatus::Pending,
+            }).unwrap().commission
+        };
+
+        // 成交金额 1000，按费率仅 0.3，按最低收费 5
+        let mut engine = BacktestEngine::new(10_000.0, 0.0003).with_commission_schedule(5.0, 0.0);
+        assert_eq!(buy(&mut engine, 100.0), 5.0);
+        // 成交金额 50000 时按费率计算
+        let mut engine = BacktestEngine::new(100_000.0, 0.0003).with_commission_schedule(5.0, 0.0);
+        assert!((buy(&mut engine, 5_000.0) - 15.0).abs() < 1e-9);
+
+        // 固定费用叠加费率
+        let mut engine = BacktestEngine::new(10_000.0, 0.0003).with_commission_schedule(0.0, 1.0);
+        assert!((buy(&mut engine, 100.0) - 1.3).abs() < 1e-9);
+        assert!((engine.current_capital - (10_000.0 - 1_000.0 - 1.3)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_limit_orders_fill_on_touch() {
+        let mut engine = BacktestEngine::new(10_000.0, 0.0);
+        let buy_id = engine.submit_limit("TEST".to_string(), TradeType::Buy, 95.0, 10.0, 0);
+
+        // 同一时间戳的K线不撮合，未触及限价不成交
+        assert!(engine.fill_resting_orders("TEST", 0, 94.0, 96.0, 90.0).is_empty());
+        assert!(engine.fill_resting_orders("TEST", 1, 100.0, 102.0, 96.0).is_empty());
+        assert_eq!(engine.resting_orders().len(), 1);
+
+        // 最低价触及限价，按限价成交
+        let fills = engine.fill_resting_orders("TEST", 2, 98.0, 99.0, 94.0);
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].price, 95.0);
+        assert_eq!(fills[0].timestamp, 2);
+        assert!(engine.resting_orders().is_empty());
+        assert!(!engine.cancel_limit(&buy_id));
+
+        // 卖出限价单跳空高开，按开盘价成交
+        engine.submit_limit("TEST".to_string(), TradeType::Sell, 100.0, 10.0, 2);
+        let fills = engine.fill_resting_orders("TEST", 3, 103.0, 105.0, 101.0);
+        assert_eq!(fills[0].price, 103.0);
+        assert!(engine.positions.is_empty());
+
+        // 撤单后不再撮合
+        let id = engine.submit_limit("TEST".to_string(), TradeType::Buy, 90.0, 10.0, 3);
+        assert!(engine.cancel_limit(&id));
+        assert!(engine.fill_resting_orders("TEST", 4, 90.0, 91.0, 80.0).is_empty());
+
+        let statuses: Vec<OrderStatus> = engine.order_history().iter().map(|o| o.status).collect();
+        assert_eq!(statuses, vec![OrderStatus::Filled, OrderStatus::Filled, OrderStatus::Cancelled]);
+        assert_eq!(engine.order_history()[0].id, buy_id);
+    }
+
+    #[test]
+    fn test_limit_orders_fill_without_slippage() {
+        let mut engine = BacktestEngine::new(10_000.0, 0.0).with_costs(0.01, 0.0);
+        engine.submit_limit("TEST".to_string(), TradeType::Buy, 95.0, 10.0, 0);
+        let fills = engine.fill_resting_orders("TEST", 1, 98.0, 99.0, 94.0);
+        assert_eq!(fills[0].price, 95.0);
+        assert_eq!(fills[0].slippage, 0.0);
+
+        engine.submit_limit("TEST".to_string(), TradeType::Sell, 100.0, 10.0, 1);
+        let fills = engine.fill_resting_orders("TEST", 2, 99.0, 101.0, 98.0);
+        assert_eq!(fills[0].price, 100.0);
+
+        // 持仓不足的卖出限价单被拒绝
+        engine.submit_limit("TEST".to_string(), TradeType::Sell, 100.0, 10.0, 2);
+        assert!(engine.fill_resting_orders("TEST", 3, 99.0, 101.0, 98.0).is_empty());
+        assert_eq!(engine.order_history().last().unwrap().status, OrderStatus::Rejected);
+    }
+
+    #[test]
+    fn test_gap_stats() {
+        let klines = vec![
+            (0, 100.0, 101.0, 99.0, 100.0, 1000.0),
+            // 向上跳空 +2%，最低回到前收，回补
+            (1, 102.0, 103.0, 100.0, 101.0, 1000.0),
+            // 向下跳空 -1%，最高未回到前收
+            (2, 99.99, 100.5, 99.0, 100.0, 1000.0),
+            // 平开，不计为跳空
+            (3, 100.0, 101.0, 99.5, 100.0, 1000.0),
+            // 向上跳空 +3%，未回补
+            (4, 103.0, 104.0, 102.0, 103.5, 1000.0),
+        ];
+        let stats = gap_stats(klines).unwrap();
+        let gaps = [0.02, -0.01, 0.0, 0.03];
+
+        assert!((stats["mean_gap"] - 0.01).abs() < 1e-9);
+        assert!((stats["std_gap"] - std_dev(&gaps)).abs() < 1e-9);
+        assert!((stats["avg_gap_size"] - 0.02).abs() < 1e-9);
+        assert!((stats["fill_rate"] - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(stats["gap_up_count"], 2.0);
+        assert_eq!(stats["gap_down_count"], 1.0);
+
+        assert!(gap_stats(vec![(0, 1.0, 1.0, 1.0, 1.0, 0.0)]).is_err());
+    }
+
+    #[test]
+    fn test_simple_backtest_trade_log() {
+        pyo3::prepare_freethreaded_python();
+        let klines = sine_klines(120, 8.0, 0.0);
+        let result = run_default_backtest(&klines, r#"{"short_period": 3, "long_period": 8}"#);
+
+        Python::with_gil(|py| {
+            let dict = result.downcast_bound::<PyDict>(py).unwrap();
+            let total_trades: usize = dict.get_item("total_trades").unwrap().unwrap().extract().unwrap();
+            let trades = dict.get_item("trades").unwrap().unwrap();
+            let trades = trades.downcast::<PyList>().unwrap();
+            assert!(total_trades > 0);
+            assert_eq!(trades.len(), total_trades);
+
+            // 多头策略买卖交替，成交价取自K线收盘价，手续费按费率计算
+            for (i, trade) in trades.iter().enumerate() {
+                let trade = trade.downcast::<PyDict>().unwrap();
+                let side: String = trade.get_item("side").unwrap().unwrap().extract().unwrap();
+                assert_eq!(side, if i % 2 == 0 { "buy" } else { "sell" });
+                let symbol: String = trade.get_item("symbol").unwrap().unwrap().extract().unwrap();
+                assert_eq!(symbol, "TEST");
+
+                let timestamp: i64 = trade.get_item("timestamp").unwrap().unwrap().extract().unwrap();
+                let price: f64 = trade.get_item("price").unwrap().unwrap().extract().unwrap();
+                let quantity: f64 = trade.get_item("quantity").unwrap().unwrap().extract().unwrap();
+                let commission: f64 = trade.get_item("commission").unwrap().unwrap().extract().unwrap();
+                let kline = klines[(timestamp / MS_PER_DAY) as usize];
+                assert_eq!(price, kline.4);
+                assert!((commission - price * quantity * 0.0003).abs() < 1e-9);
+            }
+        });
+    }
+
+    #[test]
+    fn test_calendar_returns_two_months() {
+        // 2024-01-15 起每日 +1%，共 31 天：1月 17 天，2月 14 天
+        let start = 1_705_276_800_000; // 2024-01-15 00:00 UTC
+        let equity: Vec<f64> = (0..31).map(|i| 100.0 * 1.01f64.powi(i)).collect();
+        let timestamps: Vec<i64> = (0..31).map(|i| start + i as i64 * MS_PER_DAY).collect();
+
+        let table = calendar_returns(equity.clone(), timestamps.clone(), 0).unwrap();
+        // 1月首个点为基准，至 1月31日共 16 个涨幅；2月 14 个涨幅
+        assert!((table["2024-01"] - (1.01f64.powi(16) - 1.0) * 100.0).abs() < 1e-9);
+        assert!((table["2024-02"] - (1.01f64.powi(14) - 1.0) * 100.0).abs() < 1e-9);
+        assert!((table["2024"] - (1.01f64.powi(30) - 1.0) * 100.0).abs() < 1e-9);
+        assert_eq!(table.len(), 3);
+
+        // 西五区下 2月1日 00:00 UTC 仍属 1月31日
+        let table = calendar_returns(equity.clone(), timestamps.clone(), -5 * 3600).unwrap();
+        assert!((table["2024-01"] - (1.01f64.powi(17) - 1.0) * 100.0).abs() < 1e-9);
+        assert!((table["2024-02"] - (1.01f64.powi(13) - 1.0) * 100.0).abs() < 1e-9);
+
+        assert!(calendar_returns(equity, timestamps[..30].to_vec(), 0).is_err());
+    }
+
+    #[test]
+    fn test_simple_backtest_markers_align_to_klines() {
+        pyo3::prepare_freethreaded_python();
+        let klines = sine_klines(120, 8.0, 0.0);
+        let result = run_default_backtest(&klines, r#"{"short_period": 3, "long_period": 8}"#);
+
+        Python::with_gil(|py| {
+            let dict = result.downcast_bound::<PyDict>(py).unwrap();
+            let trades = dict.get_item("trades").unwrap().unwrap();
+            let trades = trades.downcast::<PyList>().unwrap();
+            let markers = dict.get_item("markers").unwrap().unwrap();
+            let markers = markers.downcast::<PyDict>().unwrap();
+            let bar_index: Vec<usize> = markers.get_item("bar_index").unwrap().unwrap().extract().unwrap();
+            let prices: Vec<f64> = markers.get_item("price").unwrap().unwrap().extract().unwrap();
+            let sides: Vec<String> = markers.get_item("side").unwrap().unwrap().extract().unwrap();
+
+            assert!(!bar_index.is_empty());
+            assert_eq!(bar_index.len(), trades.len());
+            assert_eq!(prices.len(), trades.len());
+            for (i, trade) in trades.iter().enumerate() {
+                let trade = trade.downcast::<PyDict>().unwrap();
+                let timestamp: i64 = trade.get_item("timestamp").unwrap().unwrap().extract().unwrap();
+                let side: String = trade.get_item("side").unwrap().unwrap().extract().unwrap();
+                assert_eq!(klines[bar_index[i]].0, timestamp);
+                assert_eq!(prices[i], klines[bar_index[i]].4);
+                assert_eq!(sides[i], side);
+            }
+        });
+    }
+
+    #[test]
+    fn test_walk_forward_segments_reset_capital() {
+        pyo3::prepare_freethreaded_python();
+        let klines = sine_klines(130, 6.0, 0.0);
+        let params = r#"{"short_period": 3, "long_period": 8}"#;
+        let result = walk_forward_backtest(klines.clone(), 100_000.0, 0.0003, "sma_cross", params, 40, 30).unwrap();
+
+        Python::with_gil(|py| {
+            let dict = result.downcast_bound::<PyDict>(py).unwrap();
+            let segments = dict.get_item("segments").unwrap().unwrap();
+            let segments = segments.downcast::<PyList>().unwrap();
+            // 测试段 [40,70) [70,100) [100,130)
+            assert_eq!(segments.len(), 3);
+
+            let mut growth = 1.0;
+            for (k, segment) in segments.iter().enumerate() {
+                let segment = segment.downcast::<PyDict>().unwrap();
+                let test_start: usize = segment.get_item("test_start").unwrap().unwrap().extract().unwrap();
+                let train_start: usize = segment.get_item("train_start").unwrap().unwrap().extract().unwrap();
+                assert_eq!(test_start, 40 + 30 * k);
+                assert_eq!(train_start, 30 * k);
+
+                // 每段从初始资金重新开始，样本内K线只用于预热，交易与盯市都在测试段内
+                let (alone, _) = run_strategy(
+                    &klines[train_start..test_start + 30], 100_000.0, 0.0003, "sma_cross", params,
+                    0.0, 0.0, TRADING_DAYS_PER_YEAR, 0.0, 0.0, 40,
+                ).unwrap();
+                assert_eq!(alone.equity_curve.len(), 30);
+                assert_eq!(alone.equity_curve[0].timestamp, klines[test_start].0);
+                assert!(alone.trades.iter().all(|t| t.timestamp >= klines[test_start].0));
+                let expected = alone.calculate_result(&HashMap::new()).final_capital;
+                let final_capital: f64 = segment.get_item("final_capital").unwrap().unwrap().extract().unwrap();
+                assert_eq!(final_capital, expected);
+
+                let total_return: f64 = segment.get_item("total_return").unwrap().unwrap().extract().unwrap();
+                growth *= 1.0 + total_return / 100.0;
+            }
+
+            let aggregate = dict.get_item("aggregate").unwrap().unwrap();
+            let aggregate = aggregate.downcast::<PyDict>().unwrap();
+            let compounded: f64 = aggregate.get_item("compounded_return").unwrap().unwrap().extract().unwrap();
+            assert!((compounded - (growth - 1.0) * 100.0).abs() < 1e-9);
+        });
+
+        assert!(walk_forward_backtest(klines, 100_000.0, 0.0003, "sma_cross", params, 120, 30).is_err());
+    }
+
+    #[test]
+    fn test_walk_forward_marks_open_positions_at_segment_end() {
+        pyo3::prepare_freethreaded_python();
+        // 持续上涨，每段都以持仓结束
+        let klines = sine_klines(60, f64::INFINITY, 1.0);
+        let params = r#"{"short_period": 3, "long_period": 8}"#;
+        let result = walk_forward_backtest(klines, 100_000.0, 0.0003, "sma_cross", params, 20, 10).unwrap();
+
+        Python::with_gil(|py| {
+            let dict = result.downcast_bound::<PyDict>(py).unwrap();
+            let aggregate = dict.get_item("aggregate").unwrap().unwrap();
+            let aggregate = aggregate.downcast::<PyDict>().unwrap();
+            let get = |key: &str| -> f64 { aggregate.get_item(key).unwrap().unwrap().extract().unwrap() };
+            assert_eq!(get("segments"), 4.0);
+            assert!(get("compounded_return") > 0.0);
+            assert_eq!(get("positive_segment_pct"), 100.0);
+        });
+    }
+
+    #[test]
+    fn test_pain_index_and_ratio() {
+        // 回撤深度 0, 10, 20, 0, 5 (%)，平均 7
+        let path = curve(&[100.0, 90.0, 80.0, 100.0, 95.0]);
+        assert!((pain_index(&path) - 7.0).abs() < 1e-9);
+
+        let expected = annualized_return(&path) / 7.0;
+        assert!((pain_ratio(&path, 0.0, 252.0).unwrap() - expected).abs() < 1e-9);
+        let annual_rf = (1.0001f64.powf(252.0) - 1.0) * 100.0;
+        let expected = (annualized_return(&path) - annual_rf) / 7.0;
+        assert!((pain_ratio(&path, 0.0001, 252.0).unwrap() - expected).abs() < 1e-9);
+
+        // 无回撤时痛苦比率为 None
+        let rising = curve(&[100.0, 101.0, 102.0]);
+        assert_eq!(pain_index(&rising), 0.0);
+        assert_eq!(pain_ratio(&rising, 0.0, 252.0), None);
+    }
+
+    #[test]
+    fn test_optimize_backtest_ranks_grid() {
+        pyo3::prepare_freethreaded_python();
+        let klines = sine_klines(150, 7.0, 0.05);
+        let grid = r#"{"short_period": [3, 5], "long_period": [8, 12, 20]}"#;
+
+        Python::with_gil(|py| {
+            let result = optimize_backtest(py, klines.clone(), 100_000.0, 0.0003, "sma_cross", grid, "total_return", 2).unwrap();
+            let dict = result.downcast_bound::<PyDict>(py).unwrap();
+            let combinations: usize = dict.get_item("combinations").unwrap().unwrap().extract().unwrap();
+            assert_eq!(combinations, 6);
+            let table = dict.get_item("table").unwrap().unwrap();
+            let table = table.downcast::<PyList>().unwrap();
+            assert_eq!(table.len(), 6);
+            let top = dict.get_item("top").unwrap().unwrap();
+            assert_eq!(top.downcast::<PyList>().unwrap().len(), 2);
+
+            // 按总收益降序，且每行与单独回测该组参数一致
+            let mut previous = f64::INFINITY;
+            for row in table.iter() {
+                let row = row.downcast::<PyDict>().unwrap();
+                let score: f64 = row.get_item("score").unwrap().unwrap().extract().unwrap();
+                assert!(score <= previous);
+                previous = score;
+
+                let params: HashMap<String, f64> = row.get_item("params").unwrap().unwrap().extract().unwrap();
+                let params = format!(
+                    r#"{{"short_period": {}, "long_period": {}}}"#,
+                    params["short_period"], params["long_period"],
+                );
+                let alone = run_default_backtest(&klines, &params);
+                let alone = alone.downcast_bound::<PyDict>(py).unwrap();
+                let expected: f64 = alone.get_item("total_return").unwrap().unwrap().extract().unwrap();
+                assert_eq!(score, expected);
+            }
+
+            assert!(optimize_backtest(py, klines.clone(), 100_000.0, 0.0003, "sma_cross", grid, "alpha", 2).is_err());
+            let huge = format!(r#"{{"short_period": [{0}], "long_period": [{0}]}}"#, vec!["1"; 101].join(","));
+            assert!(optimize_backtest(py, klines, 100_000.0, 0.0003, "sma_cross", &huge, "total_return", 2).is_err());
+        });
+
+        // 持续上涨时各组合都以持仓结束，收益按期末收盘价计入
+        let rising = sine_klines(60, f64::INFINITY, 1.0);
+        Python::with_gil(|py| {
+            let grid = r#"{"short_period": [3], "long_period": [5, 8]}"#;
+            let result = optimize_backtest(py, rising, 100_000.0, 0.0003, "sma_cross", grid, "total_return", 2).unwrap();
+            let dict = result.downcast_bound::<PyDict>(py).unwrap();
+            let table = dict.get_item("table").unwrap().unwrap();
+            for row in table.downcast::<PyList>().unwrap().iter() {
+                let row = row.downcast::<PyDict>().unwrap();
+                let score: f64 = row.get_item("score").unwrap().unwrap().extract().unwrap();
+                assert!(score > 0.0);
+            }
+            // 均线周期更短的组合更早入场，排名第一
+            let top = dict.get_item("top").unwrap().unwrap();
+            let best = top.downcast::<PyList>().unwrap().get_item(0).unwrap();
+            let params: HashMap<String, f64> = best.get_item("params").unwrap().extract().unwrap();
+            assert_eq!(params["long_period"], 5.0);
+        });
+    }
+
+    #[test]
+    fn test_capacity_estimate_binds_on_thinnest_name() {
+        let map = |a: f64, b: f64| -> HashMap<String, f64> {
+            [("600519".to_string(), a), ("300750".to_string(), b)].into_iter().collect()
+        };
+        // 600519: 10% × 1e6 股 × 100 / 0.5 = 2e7；300750: 10% × 5e4 股 × 20 / 0.5 = 2e5
+        let capacity = capacity_estimate(
+            map(1_000_000.0, 50_000.0),
+            map(100.0, 20.0),
+            map(0.5, 0.5),
+            0.1,
+        ).unwrap();
+        assert!((capacity - 200_000.0).abs() < 1e-6);
+
+        assert!(capacity_estimate(map(1.0, 1.0), map(1.0, 1.0), map(0.0, 0.0), 0.1).is_err());
+        assert!(capacity_estimate(map(1.0, 1.0), map(1.0, 1.0), map(0.5, 0.5), 1.5).is_err());
+    }
+
+    #[test]
+    fn test_pnl_by_exit_weekday_and_hour() {
+        // 2024-01-01 为周一；北京时间 (UTC+8) 周三 14:00 平仓的交易盈利，其余亏损
+        let monday = 1_704_067_200_000; // 2024-01-01 00:00 UTC
+        let hour_ms = 3_600_000;
+        let mut engine = BacktestEngine::new(100_000.0, 0.0).with_timezone(8 * 3600);
+        let mut trade = |side: TradeType, price: f64, timestamp: i64| {
+            engine.process_order(Order {
+                id: String::new(),
+                symbol: "TEST".to_string(),
+                trade_type: side,
+                price,
+                quantity: 10.0,
+                timestamp,
+                status: OrderStatus::Pending,
+            }).unwrap();
+        };
+        for week in 0..3 {
+            let base = monday + week * 7 * MS_PER_DAY;
+            // 周二买入、周三 14:00 (UTC 06:00) 卖出获利 10
+            trade(TradeType::Buy, 100.0, base + MS_PER_DAY + 2 * hour_ms);
+            trade(TradeType::Sell, 101.0, base + 2 * MS_PER_DAY + 6 * hour_ms);
+            // 周四买入、周五 10:00 (UTC 02:00) 卖出亏损 5
+            trade(TradeType::Buy, 100.0, base + 3 * MS_PER_DAY + 2 * hour_ms);
+            trade(TradeType::Sell, 99.5, base + 4 * MS_PER_DAY + 2 * hour_ms);
+        }
+
+        let result = engine.calculate_result(&HashMap::new());
+        assert_eq!(result.pnl_by_weekday.len(), 2);
+        assert!((result.pnl_by_weekday[&2] - 30.0).abs() < 1e-9);
+        assert!((result.pnl_by_weekday[&4] + 15.0).abs() < 1e-9);
+        assert!((result.pnl_by_hour[&14] - 30.0).abs() < 1e-9);
+        assert!((result.pnl_by_hour[&10] + 15.0).abs() < 1e-9);
+
+        // UTC 下同一批交易归入 6 点与 2 点
+        let (by_hour, _) = pnl_by_exit_time(&engine.closed_lots, 0);
+        assert_eq!(by_hour.keys().copied().collect::<Vec<_>>(), vec![2, 6]);
+    }
+
+    #[test]
+    fn test_tracking_error() {
+        let target = vec![0.01, -0.02, 0.015, 0.005, -0.01, 0.02];
+        assert_eq!(tracking_error(target.clone(), target.clone(), 252.0).unwrap(), 0.0);
+
+        // 主动收益交替 +0.1% / -0.1%，标准差 0.001
+        let portfolio: Vec<f64> = target.iter().enumerate()
+            .map(|(i, t)| t + if i % 2 == 0 { 0.001 } else { -0.001 })
+            .collect();
+        let te = tracking_error(portfolio, target.clone(), 252.0).unwrap();
+        assert!((te - 0.001 * 252f64.sqrt()).abs() < 1e-12);
+
+        assert!(tracking_error(vec![0.01], target, 252.0).is_err());
+    }
+
+    #[test]
+    fn test_var_backtest_counts_breaches() {
+        // 100 期收益，恒定 VaR 2%，其中 5 期损失超过 2% (恰为 95% 置信度的期望)
+        let returns: Vec<f64> = (0..100)
+            .map(|i| if i % 20 == 0 { -0.03 } else { 0.001 * (i % 7) as f64 - 0.01 })
+            .collect();
+        let var_series = vec![0.02; 100];
+
+        let result = var_backtest(returns.clone(), var_series.clone(), 0.95).unwrap();
+        assert_eq!(result["breaches"], 5.0);
+        assert!((result["breach_rate"] - 0.05).abs() < 1e-12);
+        assert!((result["expected_breaches"] - 5.0).abs() < 1e-9);
+        assert!(result["kupiec_lr"].abs() < 1e-9);
+        assert!((result["kupiec_p_value"] - 1.0).abs() < 1e-6);
+
+        // 99% 置信度下 5 次突破过多，检验拒绝
+        let result = var_backtest(returns.clone(), var_series, 0.99).unwrap();
+        assert!(result["kupiec_lr"] > 3.84);
+        assert!(result["kupiec_p_value"] < 0.05);
+
+        assert!(var_backtest(returns, vec![0.02; 99], 0.95).is_err());
+        assert!((normal_cdf(1.959_964) - 0.975).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_mean_reversion_half_life() {
+        // 离散 OU 过程 s_t = 5 + 0.9 (s_{t-1} - 5) + ε，半衰期 -ln2 / ln0.9 ≈ 6.58
+        let mut seed: u64 = 42;
+        let mut noise = || {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+            ((seed >> 11) as f64 / (1u64 << 53) as f64 - 0.5) * 0.2
+        };
+        let mut spread = vec![10.0];
+        for _ in 0..2000 {
+            let last = *spread.last().unwrap();
+            spread.push(5.0 + 0.9 * (last - 5.0) + noise());
+        }
+        let expected = -std::f64::consts::LN_2 / 0.9f64.ln();
+        let half_life = mean_reversion_half_life(spread).unwrap().unwrap();
+        assert!((half_life - expected).abs() / expected < 0.1);
+
+        // 趋势序列不回归
+        let trending: Vec<f64> = (0..50).map(|i| 1.02f64.powi(i)).collect();
+        assert_eq!(mean_reversion_half_life(trending).unwrap(), None);
+        assert!(mean_reversion_half_life(vec![1.0, 2.0]).is_err());
+    }
+
+    #[test]
+    fn test_mc_drawdown_distribution_reproducible() {
+        let first = mc_drawdown_distribution(0.0005, 0.02, 252, 2000, 7).unwrap();
+        let second = mc_drawdown_distribution(0.0005, 0.02, 252, 2000, 7).unwrap();
+        assert_eq!(first, second);
+
+        assert!(first["p50"] > 0.0);
+        assert!(first["p50"] <= first["p95"] && first["p95"] <= first["p99"]);
+        assert!(first["p99"] < 100.0);
+
+        // 种子不同结果不同；零波动的上涨路径没有回撤
+        let other = mc_drawdown_distribution(0.0005, 0.02, 252, 2000, 8).unwrap();
+        assert_ne!(first["p95"], other["p95"]);
+        let flat = mc_drawdown_distribution(0.001, 0.0, 50, 10, 1).unwrap();
+        assert_eq!(flat["p99"], 0.0);
+
+        assert!(mc_drawdown_distribution(0.0, 0.02, 0, 10, 1).is_err());
+    }
+
+    #[test]
+    fn test_drawdown_throttle() {
+        let levels = vec![(10.0, 0.75), (15.0, 0.5), (25.0, 0.0)];
+        assert_eq!(drawdown_throttle(15.0, levels.clone()).unwrap(), 0.5);
+        assert_eq!(drawdown_throttle(-18.0, levels.clone()).unwrap(), 0.5);
+        assert_eq!(drawdown_throttle(5.0, levels.clone()).unwrap(), 1.0);
+        assert_eq!(drawdown_throttle(12.0, levels.clone()).unwrap(), 0.75);
+        assert_eq!(drawdown_throttle(40.0, levels).unwrap(), 0.0);
+        assert_eq!(drawdown_throttle(40.0, vec![]).unwrap(), 1.0);
+
+        assert!(drawdown_throttle(15.0, vec![(15.0, 0.5), (10.0, 0.75)]).is_err());
+        assert!(drawdown_throttle(15.0, vec![(10.0, 1.5)]).is_err());
+    }
+
+    #[test]
+    fn test_strategy_correlation() {
+        let base = vec![100.0, 102.0, 101.0, 105.0, 104.0, 108.0];
+        // 收益率与 base 完全相反的曲线
+        let returns = simple_returns(&base);
+        let mut mirror = vec![100.0];
+        for r in &returns {
+            let last = *mirror.last().unwrap();
+            mirror.push(last * (1.0 - r));
+        }
+        let mut curves = HashMap::new();
+        curves.insert("a".to_string(), base.clone());
+        // 更长的曲线按末尾对齐
+        let mut longer = vec![90.0, 95.0];
+        longer.extend(base.iter().map(|v| v * 2.0));
+        curves.insert("b".to_string(), longer);
+        curves.insert("c".to_string(), mirror);
+
+        let (names, corr) = strategy_correlation(curves).unwrap();
+        assert_eq!(names, vec!["a", "b", "c"]);
+        assert!((corr[0][1] - 1.0).abs() < 1e-12);
+        assert!((corr[0][2] + 1.0).abs() < 1e-12);
+        assert_eq!(corr[2][2], 1.0);
+        assert_eq!(corr[1][2], corr[2][1]);
+
+        let mut short = HashMap::new();
+        short.insert("a".to_string(), vec![1.0, 2.0]);
+        assert!(strategy_correlation(short).is_err());
+    }
+
+    #[test]
+    fn test_rolling_hedge_ratio_tracks_beta() {
+        // 前20期资产收益为对冲标的的1倍，之后为2倍
+        let hedge_returns: Vec<f64> = (0..40).map(|t| 0.01 * ((t % 5) as f64 - 2.0)).collect();
+        let mut hedge = vec![100.0];
+        let mut asset = vec![50.0];
+        for (t, r) in hedge_returns.iter().enumerate() {
+            let beta = if t < 20 { 1.0 } else { 2.0 };
+            hedge.push(hedge.last().unwrap() * (1.0 + r));
+            asset.push(asset.last().unwrap() * (1.0 + beta * r));
+        }
+
+        let ratios = rolling_hedge_ratio(asset.clone(), hedge.clone(), 10).unwrap();
+        assert_eq!(ratios.len(), asset.len());
+        assert!(ratios[..10].iter().all(|r| r.is_none()));
+        assert!((ratios[20].unwrap() - 1.0).abs() < 1e-9);
+        assert!(ratios[25].unwrap() > 1.0 && ratios[25].unwrap() < 2.0);
+        assert!((ratios[40].unwrap() - 2.0).abs() < 1e-9);
+
+        // 对冲标的价格不变时方差为0
+        assert_eq!(rolling_hedge_ratio(asset.clone(), vec![10.0; 41], 10).unwrap()[20], None);
+        assert!(rolling_hedge_ratio(asset.clone(), hedge[..40].to_vec(), 10).is_err());
+        assert!(rolling_hedge_ratio(asset, hedge, 1).is_err());
+    }
+
+    #[test]
+    fn test_trading_bars_between() {
+        let ts = |y, m, d| {
+            chrono::NaiveDate::from_ymd_opt(y, m, d).unwrap()
+                .and_hms_opt(0, 0, 0).unwrap()
+                .and_utc()
+                .timestamp_millis()
+        };
+        // 2024-06-03 (周一) 至 2024-06-10 (周一)，06-05 为节假日
+        let holiday = ts(2024, 6, 5) + 3_600_000;
+        assert_eq!(trading_bars_between(ts(2024, 6, 3), ts(2024, 6, 10), 1.0, vec![holiday], 0).unwrap(), 4.0);
+        assert_eq!(trading_bars_between(ts(2024, 6, 3), ts(2024, 6, 10), 4.0, vec![], 0).unwrap(), 20.0);
+        // 周末节假日不重复扣减
+        assert_eq!(trading_bars_between(ts(2024, 6, 3), ts(2024, 6, 10), 1.0, vec![ts(2024, 6, 8)], 0).unwrap(), 5.0);
+        assert_eq!(trading_bars_between(ts(2024, 6, 8), ts(2024, 6, 10), 1.0, vec![], 0).unwrap(), 0.0);
+
+        // 北京时间 2024-06-10 (周一，端午) 零点的节假日按本地日期剔除：06-07 (周五) 至 06-11 只有周五
+        let beijing = |y, m, d| ts(y, m, d) - 8 * 3_600_000;
+        let dragon_boat = vec![beijing(2024, 6, 10)];
+        assert_eq!(
+            trading_bars_between(beijing(2024, 6, 7), beijing(2024, 6, 11), 1.0, dragon_boat.clone(), 28_800).unwrap(),
+            1.0
+        );
+        // 按 UTC 划分时节假日落在前一天 (周日)，区间变为周四至周日
+        assert_eq!(trading_bars_between(beijing(2024, 6, 7), beijing(2024, 6, 11), 1.0, dragon_boat, 0).unwrap(), 2.0);
+
+        assert!(trading_bars_between(ts(2024, 6, 10), ts(2024, 6, 3), 1.0, vec![], 0).is_err());
+        assert!(trading_bars_between(0, 1, 1.0, vec![], 100_000).is_err());
+        assert!(trading_bars_between(ts(2024, 6, 3), ts(2024, 6, 10), 0.0, vec![], 0).is_err());
+    }
 }
@@ -8,6 +8,10 @@
 use pyo3::prelude::*;
 use pyo3::types::PyList;
 use rayon::prelude::*;
+use std::collections::BTreeMap;
+
+/// K线元组 (timestamp, open, high, low, close, volume)，时间戳为毫秒
+type Kline = (i64, f64, f64, f64, f64, f64);
 
 /// K线数据结构 (简化版，不直接暴露给Python)
 #[derive(Debug, Clone)]
@@ -32,6 +36,17 @@ pub struct InternalMergedKline {
     pub count: usize,
 }
 
+/// 开盘区间突破事件
+#[derive(Debug, Clone)]
+pub struct RangeBreakout {
+    pub index: usize,
+    pub timestamp: i64,
+    pub signal: &'static str,
+    pub price: f64,
+    pub range_high: f64,
+    pub range_low: f64,
+}
+
 /// 并行过滤K线数据
 ///
 /// 接收原始数据数组，返回过滤后的数组
@@ -191,6 +206,310 @@ fn batch_process(
         .collect())
 }
 
+/// 计算A/H股溢价率 (双重上市股票)
+///
+/// # 参数
+/// * `a_prices` - A股价格列表 (人民币)
+/// * `h_prices` - H股价格列表 (港币)
+/// * `fx_rate` - 港币兑人民币汇率
+///
+/// # 返回
+/// 每根K线的A股相对H股溢价率 (%)，H股价格为0时为None
+#[pyfunction]
+fn dual_listing_premium(
+    a_prices: Vec<f64>,
+    h_prices: Vec<f64>,
+    fx_rate: f64,
+) -> PyResult<Vec<Option<f64>>> {
+    if a_prices.len() != h_prices.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Input arrays must have the same length"
+        ));
+    }
+
+    Ok(a_prices
+        .iter()
+        .zip(h_prices.iter())
+        .map(|(&a, &h)| {
+            let h_cny = h * fx_rate;
+            if h_cny == 0.0 {
+                None
+            } else {
+                Some((a / h_cny - 1.0) * 100.0)
+            }
+        })
+        .collect())
+}
+
+/// 指数回归 (对数线性) 年化增长率
+///
+/// 对 `ln(price)` 与时间 (毫秒时间戳) 做最小二乘拟合，
+/// 年化增长率 = `exp(斜率 * 一年毫秒数) - 1`
+///
+/// # 参数
+/// * `prices` - 价格列表 (必须为正)
+/// * `timestamps` - 毫秒时间戳列表
+///
+/// # 返回
+/// (年化增长率 %, 拟合 R²)，数据点不足或时间跨度为0时为None
+#[pyfunction]
+fn exp_growth_rate(prices: Vec<f64>, timestamps: Vec<i64>) -> PyResult<Option<(f64, f64)>> {
+    if prices.len() != timestamps.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Input arrays must have the same length"
+        ));
+    }
+    if prices.iter().any(|&p| p <= 0.0) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Prices must be positive"
+        ));
+    }
+    if prices.len() < 2 {
+        return Ok(None);
+    }
+
+    const MS_PER_YEAR: f64 = 365.25 * 24.0 * 3600.0 * 1000.0;
+
+    // 以首个时间戳为原点，避免大数相减的精度损失
+    let t0 = timestamps[0];
+    let xs: Vec<f64> = timestamps.iter().map(|&t| (t - t0) as f64).collect();
+    let ys: Vec<f64> = prices.iter().map(|p| p.ln()).collect();
+
+    let n = xs.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+
+    let mut sxx = 0.0;
+    let mut sxy = 0.0;
+    let mut syy = 0.0;
+    for (&x, &y) in xs.iter().zip(ys.iter()) {
+        sxx += (x - mean_x) * (x - mean_x);
+        sxy += (x - mean_x) * (y - mean_y);
+        syy += (y - mean_y) * (y - mean_y);
+    }
+
+    if sxx == 0.0 {
+        return Ok(None);
+    }
+
+    let slope = sxy / sxx;
+    let growth = ((slope * MS_PER_YEAR).exp() - 1.0) * 100.0;
+    // 价格恒定时拟合完全，R² 记为1
+    let r_squared = if syy > 0.0 { (sxy * sxy) / (sxx * syy) } else { 1.0 };
+
+    Ok(Some((growth, r_squared)))
+}
+
+/// 按交易时段切分K线序列
+///
+/// 相邻K线时间间隔超过 `session_gap_seconds`，或跨越本地零点时切分为新时段
+///
+/// # 参数
+/// * `klines` - K线数据 (timestamp, open, high, low, close, volume)，时间戳为毫秒
+/// * `session_gap_seconds` - 时段间隔阈值 (秒)
+/// * `tz_offset_seconds` - 本地时区相对UTC的偏移 (秒)，如北京时间为 28800
+///
+/// # 返回
+/// 每个交易时段的K线列表
+#[pyfunction]
+fn split_sessions(
+    klines: Vec<Kline>,
+    session_gap_seconds: i64,
+    tz_offset_seconds: i64,
+) -> PyResult<Vec<Vec<Kline>>> {
+    let local_day = |timestamp_ms: i64| (timestamp_ms / 1000 + tz_offset_seconds).div_euclid(86_400);
+
+    let mut sessions: Vec<Vec<Kline>> = Vec::new();
+    let mut current: Vec<Kline> = Vec::new();
+
+    for kline in klines {
+        if let Some(prev) = current.last() {
+            let gap_seconds = (kline.0 - prev.0) / 1000;
+            if gap_seconds > session_gap_seconds || local_day(kline.0) != local_day(prev.0) {
+                sessions.push(std::mem::take(&mut current));
+            }
+        }
+        current.push(kline);
+    }
+
+    if !current.is_empty() {
+        sessions.push(current);
+    }
+
+    Ok(sessions)
+}
+
+/// 计算开盘区间 (Opening Range)
+///
+/// # 参数
+/// * `session_klines` - 单个交易时段的K线 (见 `split_sessions`)
+/// * `minutes` - 开盘区间分钟数
+///
+/// # 返回
+/// (区间最高价, 区间最低价)，取时段首根K线起 `minutes` 分钟内的K线
+#[pyfunction]
+fn opening_range(session_klines: Vec<Kline>, minutes: i64) -> PyResult<(f64, f64)> {
+    calc_opening_range(&session_klines, minutes).ok_or_else(|| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>("Session klines must not be empty")
+    })
+}
+
+/// 开盘区间突破信号
+///
+/// 先按 `split_sessions` 切分交易时段，每个时段内收盘价首次突破开盘区间最高价时发出买入信号，
+/// 首次跌破区间最低价时发出卖出信号 (每个方向每个时段最多一次)
+///
+/// # 参数
+/// * `klines` - K线数据 (timestamp, open, high, low, close, volume)，时间戳为毫秒
+/// * `minutes` - 开盘区间分钟数
+/// * `session_gap_seconds` - 时段间隔阈值 (秒)
+/// * `tz_offset_seconds` - 本地时区相对UTC的偏移 (秒)
+///
+/// # 返回
+/// 信号字典列表: index, timestamp, signal ("buy"/"sell"), price, range_high, range_low
+#[pyfunction]
+fn opening_range_breakout_signals(
+    klines: Vec<Kline>,
+    minutes: i64,
+    session_gap_seconds: i64,
+    tz_offset_seconds: i64,
+) -> PyResult<Vec<PyObject>> {
+    let sessions = split_sessions(klines, session_gap_seconds, tz_offset_seconds)?;
+    let breakouts = find_range_breakouts(&sessions, minutes);
+
+    Python::with_gil(|py| {
+        breakouts
+            .iter()
+            .map(|b| {
+                let dict = pyo3::types::PyDict::new(py);
+                dict.set_item("index", b.index)?;
+                dict.set_item("timestamp", b.timestamp)?;
+                dict.set_item("signal", b.signal)?;
+                dict.set_item("price", b.price)?;
+                dict.set_item("range_high", b.range_high)?;
+                dict.set_item("range_low", b.range_low)?;
+                Ok(dict.into())
+            })
+            .collect()
+    })
+}
+
+/// 计算开盘区间，时段为空时返回 None
+fn calc_opening_range(session: &[Kline], minutes: i64) -> Option<(f64, f64)> {
+    let first = session.first()?;
+    let range_end = first.0 + minutes * 60 * 1000;
+
+    let opening: Vec<&Kline> = session.iter().take_while(|k| k.0 < range_end).collect();
+    // 区间不足一根K线时，以首根K线为准
+    let opening = if opening.is_empty() { vec![first] } else { opening };
+
+    let high = opening.iter().map(|k| k.2).fold(f64::NEG_INFINITY, f64::max);
+    let low = opening.iter().map(|k| k.3).fold(f64::INFINITY, f64::min);
+    Some((high, low))
+}
+
+/// 在各交易时段内查找开盘区间突破，index 为原始序列中的位置
+fn find_range_breakouts(sessions: &[Vec<Kline>], minutes: i64) -> Vec<RangeBreakout> {
+    let mut breakouts = Vec::new();
+    let mut offset = 0;
+
+    for session in sessions {
+        if let Some((range_high, range_low)) = calc_opening_range(session, minutes) {
+            let range_end = session[0].0 + minutes * 60 * 1000;
+            let mut buy_fired = false;
+            let mut sell_fired = false;
+
+            for (i, kline) in session.iter().enumerate() {
+                if kline.0 < range_end {
+                    continue;
+                }
+
+                let signal = if !buy_fired && kline.4 > range_high {
+                    buy_fired = true;
+                    "buy"
+                } else if !sell_fired && kline.4 < range_low {
+                    sell_fired = true;
+                    "sell"
+                } else {
+                    continue;
+                };
+
+                breakouts.push(RangeBreakout {
+                    index: offset + i,
+                    timestamp: kline.0,
+                    signal,
+                    price: kline.4,
+                    range_high,
+                    range_low,
+                });
+            }
+        }
+        offset += session.len();
+    }
+
+    breakouts
+}
+
+/// 核对两个数据源的价格序列，返回超出容差的分歧点
+///
+/// 按时间戳对齐 (同一时间戳重复出现时取最后一个值)，差异率 = `(b / a - 1) * 100`；
+/// `a` 价格为0而 `b` 不为0时差异率为正无穷。仅出现在一方的时间戳见 `unmatched_timestamps`
+///
+/// # 参数
+/// * `a` - 基准数据源 (timestamp, price) 列表
+/// * `b` - 待核对数据源 (timestamp, price) 列表
+/// * `tol_pct` - 容差 (%)，差异率绝对值超过该值时报告
+///
+/// # 返回
+/// 按时间戳升序的 (timestamp, a_price, b_price, pct_diff) 列表
+#[pyfunction]
+fn reconcile_series(
+    a: Vec<(i64, f64)>,
+    b: Vec<(i64, f64)>,
+    tol_pct: f64,
+) -> PyResult<Vec<(i64, f64, f64, f64)>> {
+    if tol_pct < 0.0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "tol_pct must be non-negative"
+        ));
+    }
+
+    let b_map: BTreeMap<i64, f64> = b.into_iter().collect();
+    Ok(a.into_iter()
+        .collect::<BTreeMap<i64, f64>>()
+        .into_iter()
+        .filter_map(|(ts, a_price)| {
+            let b_price = *b_map.get(&ts)?;
+            let pct_diff = if a_price != 0.0 {
+                (b_price / a_price - 1.0) * 100.0
+            } else if b_price == 0.0 {
+                0.0
+            } else {
+                f64::INFINITY
+            };
+            (pct_diff.abs() > tol_pct).then_some((ts, a_price, b_price, pct_diff))
+        })
+        .collect())
+}
+
+/// 找出只出现在一个数据源中的时间戳
+///
+/// # 参数
+/// * `a` - 数据源A (timestamp, price) 列表
+/// * `b` - 数据源B (timestamp, price) 列表
+///
+/// # 返回
+/// (仅在A中的时间戳, 仅在B中的时间戳)，均升序去重
+#[pyfunction]
+fn unmatched_timestamps(a: Vec<(i64, f64)>, b: Vec<(i64, f64)>) -> PyResult<(Vec<i64>, Vec<i64>)> {
+    let a_map: BTreeMap<i64, f64> = a.into_iter().collect();
+    let b_map: BTreeMap<i64, f64> = b.into_iter().collect();
+
+    let only_a = a_map.keys().filter(|ts| !b_map.contains_key(ts)).copied().collect();
+    let only_b = b_map.keys().filter(|ts| !a_map.contains_key(ts)).copied().collect();
+    Ok((only_a, only_b))
+}
+
 /// Python模块定义
 #[pymodule]
 fn tacn_data(m: &Bound<'_, PyModule>) -> PyResult<()> {
@@ -198,5 +517,117 @@ fn tacn_data(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(merge_klines, m)?)?;
     m.add_function(wrap_pyfunction!(calculate_stats, m)?)?;
     m.add_function(wrap_pyfunction!(batch_process, m)?)?;
+    m.add_function(wrap_pyfunction!(dual_listing_premium, m)?)?;
+    m.add_function(wrap_pyfunction!(exp_growth_rate, m)?)?;
+    m.add_function(wrap_pyfunction!(split_sessions, m)?)?;
+    m.add_function(wrap_pyfunction!(opening_range, m)?)?;
+    m.add_function(wrap_pyfunction!(opening_range_breakout_signals, m)?)?;
+    m.add_function(wrap_pyfunction!(reconcile_series, m)?)?;
+    m.add_function(wrap_pyfunction!(unmatched_timestamps, m)?)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dual_listing_premium() {
+        // A股 6.0 元, H股 5.0 港币, 汇率 0.92 -> 溢价约 30.43%
+        let result = dual_listing_premium(vec![6.0, 4.6], vec![5.0, 5.0], 0.92).unwrap();
+        assert!((result[0].unwrap() - 30.434_782).abs() < 1e-4);
+        assert!(result[1].unwrap().abs() < 1e-9);
+
+        let zero_h = dual_listing_premium(vec![6.0], vec![0.0], 0.92).unwrap();
+        assert_eq!(zero_h[0], None);
+
+        assert!(dual_listing_premium(vec![6.0], vec![], 0.92).is_err());
+    }
+
+    #[test]
+    fn test_exp_growth_rate() {
+        // 每年增长 12%，按周采样三年
+        let week_ms = 7 * 24 * 3600 * 1000_i64;
+        let year_ms = 365.25 * 24.0 * 3600.0 * 1000.0;
+        let timestamps: Vec<i64> = (0..156).map(|i| 1_600_000_000_000 + i * week_ms).collect();
+        let prices: Vec<f64> = timestamps
+            .iter()
+            .map(|&t| 50.0 * 1.12_f64.powf((t - timestamps[0]) as f64 / year_ms))
+            .collect();
+
+        let (growth, r_squared) = exp_growth_rate(prices, timestamps).unwrap().unwrap();
+        assert!((growth - 12.0).abs() < 1e-6);
+        assert!((r_squared - 1.0).abs() < 1e-9);
+
+        assert!(exp_growth_rate(vec![1.0, 0.0], vec![0, 1]).is_err());
+        assert_eq!(exp_growth_rate(vec![1.0], vec![0]).unwrap(), None);
+    }
+
+    /// 生成从 day_start_utc_ms 之后90分钟开始的分钟K线
+    fn intraday_bars(day_start_utc_ms: i64, minutes: i64) -> Vec<Kline> {
+        (0..minutes)
+            .map(|m| {
+                let ts = day_start_utc_ms + (90 * 60 + m * 60) * 1000;
+                (ts, 10.0, 10.1, 9.9, 10.0, 1000.0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_split_sessions_two_days() {
+        // 2024-01-02 00:00 UTC = 北京时间 08:00，再过90分钟为 09:30 开盘
+        let day1 = 1_704_153_600_000;
+        let day2 = day1 + 86_400_000;
+        let mut klines = intraday_bars(day1, 120);
+        klines.extend(intraday_bars(day2, 120));
+
+        let sessions = split_sessions(klines, 4 * 3600, 8 * 3600).unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].len(), 120);
+        assert_eq!(sessions[1].len(), 120);
+        assert_eq!(sessions[1][0].0, day2 + 90 * 60 * 1000);
+
+        // 间隔阈值很大时，仍按本地零点切分
+        let klines = [intraday_bars(day1, 10), intraday_bars(day2, 10)].concat();
+        assert_eq!(split_sessions(klines, 7 * 86_400, 8 * 3600).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_opening_range_breakout_buy() {
+        let day1 = 1_704_153_600_000;
+        let mut klines = intraday_bars(day1, 60);
+        // 前30分钟区间 [9.9, 10.1]，第45分钟收盘突破区间上沿
+        klines[45].2 = 10.6;
+        klines[45].4 = 10.5;
+
+        let session = &split_sessions(klines.clone(), 4 * 3600, 8 * 3600).unwrap()[0];
+        assert_eq!(opening_range(session.clone(), 30).unwrap(), (10.1, 9.9));
+        assert!(opening_range(vec![], 30).is_err());
+
+        let sessions = split_sessions(klines, 4 * 3600, 8 * 3600).unwrap();
+        let breakouts = find_range_breakouts(&sessions, 30);
+        assert_eq!(breakouts.len(), 1);
+        assert_eq!(breakouts[0].index, 45);
+        assert_eq!(breakouts[0].signal, "buy");
+        assert_eq!(breakouts[0].price, 10.5);
+    }
+
+    #[test]
+    fn test_reconcile_series_reports_discrepancy() {
+        let a: Vec<(i64, f64)> = (0..10).map(|i| (i * 1000, 10.0 + i as f64)).collect();
+        let mut b: Vec<(i64, f64)> = a.iter().map(|&(ts, p)| (ts, p * 1.0001)).collect();
+        // 注入一个 5% 的分歧，并让两边各缺一个时间戳
+        b[4].1 = a[4].1 * 1.05;
+        b.remove(7);
+        b.push((99_000, 20.0));
+
+        let divergences = reconcile_series(a.clone(), b.clone(), 0.5).unwrap();
+        assert_eq!(divergences.len(), 1);
+        let (ts, a_price, b_price, pct_diff) = divergences[0];
+        assert_eq!((ts, a_price, b_price), (4000, 14.0, 14.0 * 1.05));
+        assert!((pct_diff - 5.0).abs() < 1e-9);
+
+        assert_eq!(unmatched_timestamps(a.clone(), b).unwrap(), (vec![7000], vec![99_000]));
+        assert!(reconcile_series(a, vec![], -1.0).is_err());
+    }
+}
@@ -1,9 +1,10 @@
 use pyo3::prelude::*;
 use pyo3::types::PyDict;
+use rayon::prelude::*;
 use std::collections::HashMap;
 
 /// 财务数据输入结构
-#[derive(Debug, Clone, FromPyObject)]
+#[derive(Debug, Clone, Default, FromPyObject)]
 pub struct FinancialData {
     pub price: Option<f64>,
     pub eps: Option<f64>,  // 每股收益
@@ -16,23 +17,72 @@ pub struct FinancialData {
     pub cogs: Option<f64>,  // 营业成本
     pub operating_cash_flow: Option<f64>,  // 经营现金流
     pub market_cap: Option<f64>,  // 市值
+    pub current_assets: Option<f64>,  // 流动资产
+    pub current_liabilities: Option<f64>,  // 流动负债
+    pub inventory: Option<f64>,  // 存货
+    pub eps_growth: Option<f64>,  // 每股收益增长率 (%)
+    pub dividend_per_share: Option<f64>,  // 每股股利
+    pub cash: Option<f64>,  // 货币资金
+    pub ebitda: Option<f64>,  // 息税折旧摊销前利润
+    pub ebit: Option<f64>,  // 息税前利润
+    pub interest_expense: Option<f64>,  // 利息费用
 }
 
 /// 财务指标输出结构
+#[pyclass]
 #[derive(Debug, Clone)]
 pub struct FinancialMetrics {
+    #[pyo3(get)]
     pub pe_ratio: Option<f64>,  // 市盈率
+    #[pyo3(get)]
     pub pb_ratio: Option<f64>,  // 市净率
+    #[pyo3(get)]
     pub roe: Option<f64>,  // 净资产收益率 (%)
+    #[pyo3(get)]
     pub roa: Option<f64>,  // 总资产收益率 (%)
+    #[pyo3(get)]
     pub debt_ratio: Option<f64>,  // 资产负债率 (%)
+    #[pyo3(get)]
     pub gross_margin: Option<f64>,  // 毛利率 (%)
+    #[pyo3(get)]
     pub net_margin: Option<f64>,  // 净利率 (%)
+    #[pyo3(get)]
     pub asset_turnover: Option<f64>,  // 总资产周转率
+    #[pyo3(get)]
     pub equity_multiplier: Option<f64>,  // 权益乘数
+    #[pyo3(get)]
     pub current_ratio: Option<f64>,  // 流动比率
+    #[pyo3(get)]
     pub quick_ratio: Option<f64>,  // 速动比率
+    #[pyo3(get)]
     pub operating_cash_flow_ratio: Option<f64>,  // 现金流比率
+    #[pyo3(get)]
+    pub peg_ratio: Option<f64>,  // 市盈率相对盈利增长比率
+    #[pyo3(get)]
+    pub dividend_yield: Option<f64>,  // 股息率 (%)
+    #[pyo3(get)]
+    pub payout_ratio: Option<f64>,  // 股利支付率 (%)
+    #[pyo3(get)]
+    pub enterprise_value: Option<f64>,  // 企业价值
+    #[pyo3(get)]
+    pub ev_ebitda: Option<f64>,  // 企业价值倍数
+    #[pyo3(get)]
+    pub debt_to_equity: Option<f64>,  // 产权比率
+    #[pyo3(get)]
+    pub interest_coverage: Option<f64>,  // 利息保障倍数
+    #[pyo3(get)]
+    pub ps_ratio: Option<f64>,  // 市销率
+    #[pyo3(get)]
+    pub earnings_yield: Option<f64>,  // 盈利收益率 (%)
+}
+
+/// 杜邦分析：ROE = 净利率 × 总资产周转率 × 权益乘数
+#[derive(Debug, Clone, Copy)]
+pub struct DupontRoe {
+    pub net_margin: f64,  // 净利率 (%)
+    pub asset_turnover: f64,  // 总资产周转率
+    pub equity_multiplier: f64,  // 权益乘数
+    pub roe: f64,  // 三因子乘积 (%)
 }
 
 impl FinancialMetrics {
@@ -50,8 +100,52 @@ impl FinancialMetrics {
             current_ratio: None,
             quick_ratio: None,
             operating_cash_flow_ratio: None,
+            peg_ratio: None,
+            dividend_yield: None,
+            payout_ratio: None,
+            enterprise_value: None,
+            ev_ebitda: None,
+            debt_to_equity: None,
+            interest_coverage: None,
+            ps_ratio: None,
+            earnings_yield: None,
         }
     }
+
+    /// 杜邦分解，三个因子任一缺失时为 None
+    pub fn dupont_roe(&self) -> Option<DupontRoe> {
+        let (net_margin, asset_turnover, equity_multiplier) =
+            (self.net_margin?, self.asset_turnover?, self.equity_multiplier?);
+        Some(DupontRoe {
+            net_margin,
+            asset_turnover,
+            equity_multiplier,
+            roe: net_margin / 100.0 * asset_turnover * equity_multiplier * 100.0,
+        })
+    }
+}
+
+#[pymethods]
+impl FinancialMetrics {
+    /// 转换为字典 (兼容旧版返回的键值结构)
+    fn to_dict(&self, py: Python<'_>) -> PyObject {
+        metrics_to_dict(py, self)
+    }
+
+    /// 杜邦分解字典: net_margin (%), asset_turnover, equity_multiplier, roe (%)
+    ///
+    /// 三个因子任一缺失时返回 None
+    #[pyo3(name = "dupont_roe")]
+    fn py_dupont_roe(&self) -> Option<HashMap<String, f64>> {
+        self.dupont_roe().map(|dupont| {
+            HashMap::from([
+                ("net_margin".to_string(), dupont.net_margin),
+                ("asset_turnover".to_string(), dupont.asset_turnover),
+                ("equity_multiplier".to_string(), dupont.equity_multiplier),
+                ("roe".to_string(), dupont.roe),
+            ])
+        })
+    }
 }
 
 /// 计算单个股票的财务指标
@@ -65,6 +159,54 @@ pub fn calculate_metrics(data: &FinancialData) -> FinancialMetrics {
         }
     }
 
+    // Earnings Yield = EPS / Price (盈利收益率, %，市盈率的倒数)
+    if let (Some(eps), Some(price)) = (data.eps, data.price) {
+        if price > 0.0 {
+            metrics.earnings_yield = Some((eps / price) * 100.0);
+        }
+    }
+
+    // PS = Market Cap / Revenue (市销率)
+    if let (Some(market_cap), Some(revenue)) = (data.market_cap, data.revenue) {
+        if revenue > 0.0 {
+            metrics.ps_ratio = Some(market_cap / revenue);
+        }
+    }
+
+    // PEG = PE / EPS Growth (%) (市盈率相对盈利增长比率，增长率非正时不计算)
+    if let (Some(pe), Some(eps_growth)) = (metrics.pe_ratio, data.eps_growth) {
+        if eps_growth > 0.0 {
+            metrics.peg_ratio = Some(pe / eps_growth);
+        }
+    }
+
+    // Dividend Yield = DPS / Price (股息率, %)
+    if let (Some(dps), Some(price)) = (data.dividend_per_share, data.price) {
+        if price > 0.0 {
+            metrics.dividend_yield = Some((dps / price) * 100.0);
+        }
+    }
+
+    // Payout Ratio = DPS / EPS (股利支付率, %)
+    if let (Some(dps), Some(eps)) = (data.dividend_per_share, data.eps) {
+        if eps > 0.0 {
+            metrics.payout_ratio = Some((dps / eps) * 100.0);
+        }
+    }
+
+    // EV = Market Cap + Total Debt - Cash (企业价值)
+    if let (Some(market_cap), Some(total_debt), Some(cash)) = (data.market_cap, data.total_debt, data.cash) {
+        let enterprise_value = market_cap + total_debt - cash;
+        metrics.enterprise_value = Some(enterprise_value);
+
+        // EV/EBITDA (企业价值倍数，EBITDA 非正时不计算)
+        if let Some(ebitda) = data.ebitda {
+            if ebitda > 0.0 {
+                metrics.ev_ebitda = Some(enterprise_value / ebitda);
+            }
+        }
+    }
+
     // PB = Price / BPS (市净率)
     if let (Some(price), Some(bps)) = (data.price, data.bps) {
         if bps > 0.0 {
@@ -93,6 +235,20 @@ pub fn calculate_metrics(data: &FinancialData) -> FinancialMetrics {
         }
     }
 
+    // Debt to Equity = Total Debt / Total Equity (产权比率)
+    if let (Some(total_debt), Some(total_equity)) = (data.total_debt, data.total_equity) {
+        if total_equity > 0.0 {
+            metrics.debt_to_equity = Some(total_debt / total_equity);
+        }
+    }
+
+    // Interest Coverage = EBIT / Interest Expense (利息保障倍数)
+    if let (Some(ebit), Some(interest_expense)) = (data.ebit, data.interest_expense) {
+        if interest_expense > 0.0 {
+            metrics.interest_coverage = Some(ebit / interest_expense);
+        }
+    }
+
     // Gross Margin = (Revenue - COGS) / Revenue (毛利率, %)
     if let (Some(revenue), Some(cogs)) = (data.revenue, data.cogs) {
         if revenue > 0.0 {
@@ -122,13 +278,26 @@ pub fn calculate_metrics(data: &FinancialData) -> FinancialMetrics {
     }
 
     // Current Ratio = Current Assets / Current Liabilities (流动比率)
-    // 这里用总资产和总债务作为近似
-    if let (Some(total_assets), Some(total_debt)) = (data.total_assets, data.total_debt) {
+    // 缺少流动资产/流动负债时用总资产和总债务作为近似
+    if let (Some(current_assets), Some(current_liabilities)) = (data.current_assets, data.current_liabilities) {
+        if current_liabilities > 0.0 {
+            metrics.current_ratio = Some(current_assets / current_liabilities);
+        }
+    } else if let (Some(total_assets), Some(total_debt)) = (data.total_assets, data.total_debt) {
         if total_debt > 0.0 {
             metrics.current_ratio = Some(total_assets / total_debt);
         }
     }
 
+    // Quick Ratio = (Current Assets - Inventory) / Current Liabilities (速动比率)
+    if let (Some(current_assets), Some(inventory), Some(current_liabilities)) =
+        (data.current_assets, data.inventory, data.current_liabilities)
+    {
+        if current_liabilities > 0.0 {
+            metrics.quick_ratio = Some((current_assets - inventory) / current_liabilities);
+        }
+    }
+
     // Operating Cash Flow Ratio = Operating Cash Flow / Total Debt (现金流比率)
     if let (Some(ocf), Some(total_debt)) = (data.operating_cash_flow, data.total_debt) {
         if total_debt > 0.0 {
@@ -146,11 +315,18 @@ fn tacn_financial(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(calculate_financial_metrics_wrapper, m)?)?;
     m.add_function(wrap_pyfunction!(batch_calculate_pe_pb, m)?)?;
     m.add_function(wrap_pyfunction!(batch_calculate_metrics_from_dicts, m)?)?;
+    m.add_function(wrap_pyfunction!(blended_rating, m)?)?;
+    m.add_function(wrap_pyfunction!(calculate_growth, m)?)?;
+    m.add_function(wrap_pyfunction!(calculate_piotroski, m)?)?;
+    m.add_class::<FinancialMetrics>()?;
 
     Ok(())
 }
 
 /// 计算单个股票的财务指标 (Python 包装器)
+///
+/// # 返回
+/// FinancialMetrics 对象，可通过属性访问各指标，`to_dict()` 转为字典
 #[pyfunction]
 #[pyo3(signature = (
     price=None,
@@ -163,7 +339,16 @@ fn tacn_financial(m: &Bound<'_, PyModule>) -> PyResult<()> {
     total_debt=None,
     cogs=None,
     operating_cash_flow=None,
-    market_cap=None
+    market_cap=None,
+    current_assets=None,
+    current_liabilities=None,
+    inventory=None,
+    eps_growth=None,
+    dividend_per_share=None,
+    cash=None,
+    ebitda=None,
+    ebit=None,
+    interest_expense=None
 ))]
 fn calculate_financial_metrics_wrapper(
     price: Option<f64>,
@@ -177,7 +362,16 @@ fn calculate_financial_metrics_wrapper(
     cogs: Option<f64>,
     operating_cash_flow: Option<f64>,
     market_cap: Option<f64>,
-) -> PyResult<HashMap<String, Option<f64>>> {
+    current_assets: Option<f64>,
+    current_liabilities: Option<f64>,
+    inventory: Option<f64>,
+    eps_growth: Option<f64>,
+    dividend_per_share: Option<f64>,
+    cash: Option<f64>,
+    ebitda: Option<f64>,
+    ebit: Option<f64>,
+    interest_expense: Option<f64>,
+) -> PyResult<FinancialMetrics> {
     let data = FinancialData {
         price,
         eps,
@@ -190,25 +384,18 @@ fn calculate_financial_metrics_wrapper(
         cogs,
         operating_cash_flow,
         market_cap,
+        current_assets,
+        current_liabilities,
+        inventory,
+        eps_growth,
+        dividend_per_share,
+        cash,
+        ebitda,
+        ebit,
+        interest_expense,
     };
 
-    let metrics = calculate_metrics(&data);
-
-    let mut result = HashMap::new();
-    result.insert("pe_ratio".to_string(), metrics.pe_ratio);
-    result.insert("pb_ratio".to_string(), metrics.pb_ratio);
-    result.insert("roe".to_string(), metrics.roe);
-    result.insert("roa".to_string(), metrics.roa);
-    result.insert("debt_ratio".to_string(), metrics.debt_ratio);
-    result.insert("gross_margin".to_string(), metrics.gross_margin);
-    result.insert("net_margin".to_string(), metrics.net_margin);
-    result.insert("asset_turnover".to_string(), metrics.asset_turnover);
-    result.insert("equity_multiplier".to_string(), metrics.equity_multiplier);
-    result.insert("current_ratio".to_string(), metrics.current_ratio);
-    result.insert("quick_ratio".to_string(), metrics.quick_ratio);
-    result.insert("operating_cash_flow_ratio".to_string(), metrics.operating_cash_flow_ratio);
-
-    Ok(result)
+    Ok(calculate_metrics(&data))
 }
 
 /// 批量计算 PE 和 PB
@@ -253,23 +440,206 @@ fn batch_calculate_pe_pb(
 }
 
 /// 从字典列表批量计算财务指标
+///
+/// 先在持有 GIL 时提取 `FinancialData`，指标计算在释放 GIL 后由 rayon 并行完成，
+/// 最后统一转换为 Python 字典
 #[pyfunction]
 fn batch_calculate_metrics_from_dicts(
     py: Python<'_>,
     dict_list: Vec<Bound<'_, PyDict>>,
 ) -> PyResult<Vec<PyObject>> {
-    let results: Vec<PyObject> = dict_list
+    let data_list: Vec<FinancialData> = dict_list
         .iter()
-        .map(|dict| {
-            let data = extract_financial_data_from_dict(py, dict);
-            let metrics = calculate_metrics(&data);
-            metrics_to_dict(py, &metrics)
-        })
+        .map(|dict| extract_financial_data_from_dict(py, dict))
+        .collect();
+
+    let metrics_list: Vec<FinancialMetrics> = py.allow_threads(|| {
+        data_list.par_iter().map(calculate_metrics).collect()
+    });
+
+    let results: Vec<PyObject> = metrics_list
+        .iter()
+        .map(|metrics| metrics_to_dict(py, metrics))
         .collect();
 
     Ok(results)
 }
 
+/// 基本面与技术面综合评分
+///
+/// 按 `weights = (基本面权重, 技术面权重)` 加权平均；
+/// 缺少一项评分时按剩余项的权重重新归一化，两项都缺失时返回 None
+#[pyfunction]
+#[pyo3(signature = (fundamental_score, technical_score, weights=(0.5, 0.5)))]
+fn blended_rating(
+    fundamental_score: Option<f64>,
+    technical_score: Option<f64>,
+    weights: (f64, f64),
+) -> PyResult<Option<f64>> {
+    let (fundamental_weight, technical_weight) = weights;
+    if fundamental_weight < 0.0 || technical_weight < 0.0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Weights must be non-negative"
+        ));
+    }
+
+    let mut weighted_sum = 0.0;
+    let mut weight_total = 0.0;
+
+    if let Some(score) = fundamental_score {
+        weighted_sum += score * fundamental_weight;
+        weight_total += fundamental_weight;
+    }
+    if let Some(score) = technical_score {
+        weighted_sum += score * technical_weight;
+        weight_total += technical_weight;
+    }
+
+    if weight_total > 0.0 {
+        Ok(Some(weighted_sum / weight_total))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Piotroski F-score 的九项信号
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PiotroskiScore {
+    pub positive_net_income: bool,  // 净利润为正
+    pub positive_operating_cash_flow: bool,  // 经营现金流为正
+    pub rising_roa: bool,  // ROA 提升
+    pub cash_flow_exceeds_net_income: bool,  // 经营现金流 > 净利润
+    pub falling_leverage: bool,  // 资产负债率下降
+    pub rising_current_ratio: bool,  // 流动比率提升
+    pub no_dilution: bool,  // 股本未增加
+    pub rising_gross_margin: bool,  // 毛利率提升
+    pub rising_asset_turnover: bool,  // 总资产周转率提升
+}
+
+impl PiotroskiScore {
+    /// 按顺序列出的 (信号名称, 是否满足)
+    pub fn signals(&self) -> [(&'static str, bool); 9] {
+        [
+            ("positive_net_income", self.positive_net_income),
+            ("positive_operating_cash_flow", self.positive_operating_cash_flow),
+            ("rising_roa", self.rising_roa),
+            ("cash_flow_exceeds_net_income", self.cash_flow_exceeds_net_income),
+            ("falling_leverage", self.falling_leverage),
+            ("rising_current_ratio", self.rising_current_ratio),
+            ("no_dilution", self.no_dilution),
+            ("rising_gross_margin", self.rising_gross_margin),
+            ("rising_asset_turnover", self.rising_asset_turnover),
+        ]
+    }
+
+    /// F-score (0-9)
+    pub fn score(&self) -> u32 {
+        self.signals().iter().filter(|(_, passed)| *passed).count() as u32
+    }
+}
+
+/// 根据本期与上期财务数据计算 Piotroski F-score
+///
+/// 股本由 净利润 / 每股收益 推算；任一信号所需数据缺失时该信号记为未满足
+pub fn piotroski_score(current: &FinancialData, previous: &FinancialData) -> PiotroskiScore {
+    let curr = calculate_metrics(current);
+    let prev = calculate_metrics(previous);
+    let rising = |c: Option<f64>, p: Option<f64>| matches!((c, p), (Some(c), Some(p)) if c > p);
+    let shares = |data: &FinancialData| match (data.net_income, data.eps) {
+        (Some(net_income), Some(eps)) if eps != 0.0 => Some(net_income / eps),
+        _ => None,
+    };
+
+    PiotroskiScore {
+        positive_net_income: current.net_income.is_some_and(|v| v > 0.0),
+        positive_operating_cash_flow: current.operating_cash_flow.is_some_and(|v| v > 0.0),
+        rising_roa: rising(curr.roa, prev.roa),
+        cash_flow_exceeds_net_income: rising(current.operating_cash_flow, current.net_income),
+        falling_leverage: rising(prev.debt_ratio, curr.debt_ratio),
+        rising_current_ratio: rising(curr.current_ratio, prev.current_ratio),
+        no_dilution: matches!(
+            (shares(current), shares(previous)),
+            (Some(c), Some(p)) if c <= p * (1.0 + 1e-9)
+        ),
+        rising_gross_margin: rising(curr.gross_margin, prev.gross_margin),
+        rising_asset_turnover: rising(curr.asset_turnover, prev.asset_turnover),
+    }
+}
+
+/// 计算 Piotroski F-score
+///
+/// # 参数
+/// * `current` - 本期财务数据字典 (键同 `batch_calculate_metrics_from_dicts`)
+/// * `previous` - 上期财务数据字典
+///
+/// # 返回
+/// (F-score 0-9, 九项信号字典)
+#[pyfunction]
+fn calculate_piotroski(
+    py: Python<'_>,
+    current: Bound<'_, PyDict>,
+    previous: Bound<'_, PyDict>,
+) -> PyResult<(u32, HashMap<String, bool>)> {
+    let result = piotroski_score(
+        &extract_financial_data_from_dict(py, &current),
+        &extract_financial_data_from_dict(py, &previous),
+    );
+    let signals = result.signals()
+        .iter()
+        .map(|(name, passed)| (name.to_string(), *passed))
+        .collect();
+    Ok((result.score(), signals))
+}
+
+/// 逐期同比增长率序列 (%)，首期为 None
+type GrowthSeries = Vec<Option<f64>>;
+
+/// 计算营业收入、净利润和每股收益的同比增长率
+///
+/// 增长率 = (本期 - 上期) / 上期 × 100，首期及上期为0时为 None
+///
+/// # 参数
+/// * `revenue_series` - 营业收入序列 (按时间升序)
+/// * `net_income_series` - 净利润序列
+/// * `eps_series` - 每股收益序列
+///
+/// # 返回
+/// (营业收入增长率, 净利润增长率, 每股收益增长率)
+#[pyfunction]
+fn calculate_growth(
+    revenue_series: Vec<f64>,
+    net_income_series: Vec<f64>,
+    eps_series: Vec<f64>,
+) -> PyResult<(GrowthSeries, GrowthSeries, GrowthSeries)> {
+    if revenue_series.len() != net_income_series.len() || revenue_series.len() != eps_series.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Revenue, net income and EPS series must have the same length"
+        ));
+    }
+
+    Ok((
+        yoy_growth(&revenue_series),
+        yoy_growth(&net_income_series),
+        yoy_growth(&eps_series),
+    ))
+}
+
+/// 单个序列的逐期增长率 (%)
+fn yoy_growth(values: &[f64]) -> GrowthSeries {
+    let mut growth = Vec::with_capacity(values.len());
+    if !values.is_empty() {
+        growth.push(None);
+    }
+    growth.extend(values.windows(2).map(|w| {
+        if w[0] != 0.0 {
+            Some((w[1] - w[0]) / w[0] * 100.0)
+        } else {
+            None
+        }
+    }));
+    growth
+}
+
 /// 从 Python 字典提取财务数据
 fn extract_financial_data_from_dict(py: Python<'_>, dict: &Bound<'_, PyDict>) -> FinancialData {
     FinancialData {
@@ -284,6 +654,15 @@ fn extract_financial_data_from_dict(py: Python<'_>, dict: &Bound<'_, PyDict>) ->
         cogs: get_optional_f64_from_dict(py, dict, "cogs"),
         operating_cash_flow: get_optional_f64_from_dict(py, dict, "operating_cash_flow"),
         market_cap: get_optional_f64_from_dict(py, dict, "market_cap"),
+        current_assets: get_optional_f64_from_dict(py, dict, "current_assets"),
+        current_liabilities: get_optional_f64_from_dict(py, dict, "current_liabilities"),
+        inventory: get_optional_f64_from_dict(py, dict, "inventory"),
+        eps_growth: get_optional_f64_from_dict(py, dict, "eps_growth"),
+        dividend_per_share: get_optional_f64_from_dict(py, dict, "dividend_per_share"),
+        cash: get_optional_f64_from_dict(py, dict, "cash"),
+        ebitda: get_optional_f64_from_dict(py, dict, "ebitda"),
+        ebit: get_optional_f64_from_dict(py, dict, "ebit"),
+        interest_expense: get_optional_f64_from_dict(py, dict, "interest_expense"),
     }
 }
 
@@ -311,5 +690,267 @@ fn metrics_to_dict(py: Python<'_>, metrics: &FinancialMetrics) -> PyObject {
     dict.set_item("current_ratio", metrics.current_ratio).unwrap();
     dict.set_item("quick_ratio", metrics.quick_ratio).unwrap();
     dict.set_item("operating_cash_flow_ratio", metrics.operating_cash_flow_ratio).unwrap();
+    dict.set_item("peg_ratio", metrics.peg_ratio).unwrap();
+    dict.set_item("dividend_yield", metrics.dividend_yield).unwrap();
+    dict.set_item("payout_ratio", metrics.payout_ratio).unwrap();
+    dict.set_item("enterprise_value", metrics.enterprise_value).unwrap();
+    dict.set_item("ev_ebitda", metrics.ev_ebitda).unwrap();
+    dict.set_item("debt_to_equity", metrics.debt_to_equity).unwrap();
+    dict.set_item("interest_coverage", metrics.interest_coverage).unwrap();
+    dict.set_item("ps_ratio", metrics.ps_ratio).unwrap();
+    dict.set_item("earnings_yield", metrics.earnings_yield).unwrap();
     dict.into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blended_rating() {
+        assert_eq!(blended_rating(Some(80.0), Some(60.0), (0.5, 0.5)).unwrap(), Some(70.0));
+
+        // 缺少技术面评分时只使用基本面评分
+        assert_eq!(blended_rating(Some(80.0), None, (0.7, 0.3)).unwrap(), Some(80.0));
+        assert_eq!(blended_rating(None, Some(60.0), (0.7, 0.3)).unwrap(), Some(60.0));
+        assert_eq!(blended_rating(None, None, (0.5, 0.5)).unwrap(), None);
+
+        assert!(blended_rating(Some(80.0), Some(60.0), (-0.5, 1.5)).is_err());
+    }
+
+    #[test]
+    fn test_current_and_quick_ratio() {
+        let result = calculate_financial_metrics_wrapper(
+            None, None, None, None, None, Some(1200.0), None, Some(500.0), None, None, None,
+            Some(300.0), Some(150.0), Some(60.0), None, None, None, None, None, None,
+        ).unwrap();
+        assert_eq!(result.current_ratio, Some(2.0));
+        assert_eq!(result.quick_ratio, Some(1.6));
+
+        // 缺少流动项目时流动比率退回总资产/总负债近似，速动比率为 None
+        let result = calculate_financial_metrics_wrapper(
+            None, None, None, None, None, Some(1200.0), None, Some(500.0), None, None, None,
+            None, None, None, None, None, None, None, None, None,
+        ).unwrap();
+        assert_eq!(result.current_ratio, Some(2.4));
+        assert_eq!(result.quick_ratio, None);
+    }
+
+    #[test]
+    fn test_debt_to_equity_and_interest_coverage() {
+        let metrics = calculate_metrics(&FinancialData {
+            total_debt: Some(600.0),
+            total_equity: Some(400.0),
+            ebit: Some(90.0),
+            interest_expense: Some(30.0),
+            ..Default::default()
+        });
+        assert_eq!(metrics.debt_to_equity, Some(1.5));
+        assert_eq!(metrics.interest_coverage, Some(3.0));
+
+        // 分母非正或缺失时不计算
+        let metrics = calculate_metrics(&FinancialData {
+            total_debt: Some(600.0),
+            total_equity: Some(-100.0),
+            ebit: Some(90.0),
+            interest_expense: Some(0.0),
+            ..Default::default()
+        });
+        assert_eq!(metrics.debt_to_equity, None);
+        assert_eq!(metrics.interest_coverage, None);
+        assert_eq!(calculate_metrics(&FinancialData { ebit: Some(90.0), ..Default::default() }).interest_coverage, None);
+    }
+
+    #[test]
+    fn test_ps_ratio_and_earnings_yield() {
+        let metrics = calculate_metrics(&FinancialData {
+            price: Some(20.0),
+            eps: Some(-0.5),
+            market_cap: Some(5000.0),
+            revenue: Some(2000.0),
+            ..Default::default()
+        });
+        assert_eq!(metrics.ps_ratio, Some(2.5));
+        // 亏损公司没有市盈率，但盈利收益率仍可计算
+        assert_eq!(metrics.pe_ratio, None);
+        assert_eq!(metrics.earnings_yield, Some(-2.5));
+
+        let metrics = calculate_metrics(&FinancialData {
+            price: Some(0.0),
+            eps: Some(1.0),
+            market_cap: Some(5000.0),
+            revenue: Some(0.0),
+            ..Default::default()
+        });
+        assert_eq!(metrics.ps_ratio, None);
+        assert_eq!(metrics.earnings_yield, None);
+    }
+
+    #[test]
+    fn test_peg_ratio() {
+        let peg = |eps_growth: Option<f64>| {
+            calculate_metrics(&FinancialData {
+                price: Some(30.0),
+                eps: Some(1.5),
+                eps_growth,
+                ..Default::default()
+            })
+            .peg_ratio
+        };
+
+        // PE 20，盈利增长 25%
+        assert_eq!(peg(Some(25.0)), Some(0.8));
+        assert_eq!(peg(Some(0.0)), None);
+        assert_eq!(peg(Some(-10.0)), None);
+        assert_eq!(peg(None), None);
+    }
+
+    #[test]
+    fn test_dividend_yield_and_payout_ratio() {
+        let metrics = calculate_metrics(&FinancialData {
+            price: Some(40.0),
+            eps: Some(2.5),
+            dividend_per_share: Some(1.0),
+            ..Default::default()
+        });
+        assert_eq!(metrics.dividend_yield, Some(2.5));
+        assert_eq!(metrics.payout_ratio, Some(40.0));
+
+        // 价格或每股收益为0/缺失时不计算
+        let metrics = calculate_metrics(&FinancialData {
+            price: Some(0.0),
+            eps: None,
+            dividend_per_share: Some(1.0),
+            ..Default::default()
+        });
+        assert_eq!(metrics.dividend_yield, None);
+        assert_eq!(metrics.payout_ratio, None);
+    }
+
+    #[test]
+    fn test_financial_metrics_to_dict() {
+        pyo3::prepare_freethreaded_python();
+        let metrics = calculate_metrics(&FinancialData {
+            price: Some(30.0),
+            eps: Some(1.5),
+            ..Default::default()
+        });
+
+        Python::with_gil(|py| {
+            let dict = metrics.to_dict(py);
+            let dict = dict.downcast_bound::<PyDict>(py).unwrap();
+            let pe: Option<f64> = dict.get_item("pe_ratio").unwrap().unwrap().extract().unwrap();
+            assert_eq!(pe, metrics.pe_ratio);
+            let pb: Option<f64> = dict.get_item("pb_ratio").unwrap().unwrap().extract().unwrap();
+            assert_eq!(pb, None);
+            let earnings_yield: Option<f64> = dict.get_item("earnings_yield").unwrap().unwrap().extract().unwrap();
+            assert_eq!(earnings_yield, metrics.earnings_yield);
+        });
+    }
+
+    #[test]
+    fn test_enterprise_value_and_ev_ebitda() {
+        let metrics = calculate_metrics(&FinancialData {
+            market_cap: Some(1000.0),
+            total_debt: Some(300.0),
+            cash: Some(100.0),
+            ebitda: Some(150.0),
+            ..Default::default()
+        });
+        assert_eq!(metrics.enterprise_value, Some(1200.0));
+        assert_eq!(metrics.ev_ebitda, Some(8.0));
+
+        // EBITDA 非正时只给出企业价值
+        let metrics = calculate_metrics(&FinancialData {
+            market_cap: Some(1000.0),
+            total_debt: Some(300.0),
+            cash: Some(100.0),
+            ebitda: Some(-50.0),
+            ..Default::default()
+        });
+        assert_eq!(metrics.enterprise_value, Some(1200.0));
+        assert_eq!(metrics.ev_ebitda, None);
+    }
+
+    #[test]
+    fn test_dupont_roe_reconciles_with_roe() {
+        let metrics = calculate_metrics(&FinancialData {
+            revenue: Some(800.0),
+            net_income: Some(96.0),
+            total_assets: Some(1600.0),
+            total_equity: Some(640.0),
+            ..Default::default()
+        });
+        let dupont = metrics.dupont_roe().unwrap();
+        assert!((dupont.net_margin - 12.0).abs() < 1e-9);
+        assert!((dupont.asset_turnover - 0.5).abs() < 1e-9);
+        assert!((dupont.equity_multiplier - 2.5).abs() < 1e-9);
+        assert!((dupont.roe - metrics.roe.unwrap()).abs() < 1e-9);
+
+        // 缺少营业收入时无法分解
+        let metrics = calculate_metrics(&FinancialData {
+            net_income: Some(96.0),
+            total_assets: Some(1600.0),
+            total_equity: Some(640.0),
+            ..Default::default()
+        });
+        assert!(metrics.dupont_roe().is_none());
+    }
+
+    #[test]
+    fn test_calculate_growth() {
+        let (revenue, net_income, eps) = calculate_growth(
+            vec![100.0, 120.0, 90.0],
+            vec![0.0, 10.0, 15.0],
+            vec![1.0, 1.5, 1.2],
+        ).unwrap();
+
+        assert_eq!(revenue, vec![None, Some(20.0), Some(-25.0)]);
+        // 上期为0时无法计算增长率
+        assert_eq!(net_income, vec![None, None, Some(50.0)]);
+        assert_eq!(eps[0], None);
+        assert!((eps[2].unwrap() + 20.0).abs() < 1e-9);
+
+        assert!(calculate_growth(vec![1.0, 2.0], vec![1.0], vec![1.0, 2.0]).is_err());
+    }
+
+    #[test]
+    fn test_piotroski_score() {
+        let previous = FinancialData {
+            eps: Some(1.0),
+            revenue: Some(1000.0),
+            net_income: Some(50.0),
+            total_assets: Some(1000.0),
+            total_debt: Some(500.0),
+            cogs: Some(700.0),
+            operating_cash_flow: Some(40.0),
+            current_assets: Some(300.0),
+            current_liabilities: Some(200.0),
+            ..Default::default()
+        };
+        let current = FinancialData {
+            eps: Some(2.0),
+            revenue: Some(1200.0),
+            net_income: Some(100.0),
+            total_assets: Some(1000.0),
+            total_debt: Some(400.0),
+            cogs: Some(780.0),
+            operating_cash_flow: Some(150.0),
+            current_assets: Some(400.0),
+            current_liabilities: Some(200.0),
+            ..Default::default()
+        };
+
+        let strong = piotroski_score(&current, &previous);
+        assert_eq!(strong.score(), 9);
+        assert!(strong.signals().iter().all(|(_, passed)| *passed));
+
+        // 反向比较时只有盈利、现金流与股本信号仍满足
+        let weak = piotroski_score(&previous, &current);
+        assert!(weak.positive_net_income && weak.positive_operating_cash_flow && weak.no_dilution);
+        assert!(!weak.cash_flow_exceeds_net_income && !weak.rising_roa);
+        assert_eq!(weak.score(), 3);
+
+        assert_eq!(piotroski_score(&FinancialData::default(), &FinancialData::default()).score(), 0);
+    }
+}
@@ -181,7 +181,7 @@ fn bollinger_bands(prices: Vec<f64>, period: usize, k: f64) -> PyResult<HashMap<
     let mut lower = Vec::new();
 
     for (i, &sma_val) in sma_vals.iter().enumerate() {
-        let start = if i >= period - 1 { i - period + 1 } else { 0 };
+        let start = (i + 1).saturating_sub(period);
         let slice = &prices[start..=i];
 
         let mean = slice.iter().sum::<f64>() / slice.len() as f64;
@@ -200,74 +200,419 @@ fn bollinger_bands(prices: Vec<f64>, period: usize, k: f64) -> PyResult<HashMap<
     Ok(result)
 }
 
+/// 计算布林带 %B
+///
+/// `%B = (price - lower) / (upper - lower)`，价格位于上轨时为 1，下轨时为 0
+///
+/// # 参数
+/// * `prices` - 价格列表
+/// * `period` - 周期，默认 20
+/// * `k` - 标准差倍数，默认 2.0
+///
+/// # 返回
+/// Python 列表，带宽为0时为 None
+#[pyfunction]
+fn bollinger_percent_b(prices: Vec<f64>, period: usize, k: f64) -> PyResult<Vec<Option<f64>>> {
+    let boll = bollinger_bands(prices.clone(), period, k)?;
+    let upper = &boll["upper"];
+    let lower = &boll["lower"];
+
+    Ok(prices
+        .iter()
+        .enumerate()
+        .map(|(i, &price)| {
+            let width = upper[i] - lower[i];
+            if width > 0.0 {
+                Some((price - lower[i]) / width)
+            } else {
+                None
+            }
+        })
+        .collect())
+}
+
+/// 计算布林带宽度
+///
+/// `bandwidth = (upper - lower) / middle`
+///
+/// # 参数
+/// * `prices` - 价格列表
+/// * `period` - 周期，默认 20
+/// * `k` - 标准差倍数，默认 2.0
+///
+/// # 返回
+/// Python 列表，带宽或中轨为0时为 None
+#[pyfunction]
+fn bollinger_bandwidth(prices: Vec<f64>, period: usize, k: f64) -> PyResult<Vec<Option<f64>>> {
+    let boll = bollinger_bands(prices, period, k)?;
+    let upper = &boll["upper"];
+    let mid = &boll["mid"];
+    let lower = &boll["lower"];
+
+    Ok((0..mid.len())
+        .map(|i| {
+            let width = upper[i] - lower[i];
+            if width > 0.0 && mid[i] != 0.0 {
+                Some(width / mid[i])
+            } else {
+                None
+            }
+        })
+        .collect())
+}
+
+/// 成交量异动检测 (滚动 Z 分数)
+///
+/// 以前 `window` 根K线的成交量均值和标准差计算当前成交量的 Z 分数，
+/// 超过 `threshold` 记为 1，否则为 0。窗口标准差为 0 时，成交量高于均值即视为异动。
+///
+/// # 参数
+/// * `volumes` - 成交量列表
+/// * `window` - 滚动窗口
+/// * `threshold` - Z 分数阈值 (如 3.0)
+///
+/// # 返回
+/// Python 列表，前 `window` 个值为 None
+#[pyfunction]
+fn volume_spike(volumes: Vec<f64>, window: usize, threshold: f64) -> PyResult<Vec<Option<i8>>> {
+    if window == 0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "window must be greater than 0"
+        ));
+    }
+
+    let stats = rolling_mean_std(&volumes, window);
+
+    Ok(volumes
+        .iter()
+        .enumerate()
+        .map(|(i, &volume)| {
+            if i == 0 {
+                return None;
+            }
+            stats[i - 1].map(|(mean, std)| {
+                let is_spike = if std > 0.0 {
+                    (volume - mean) / std > threshold
+                } else {
+                    volume > mean
+                };
+                is_spike as i8
+            })
+        })
+        .collect())
+}
+
+/// 相对成交量 (RVOL)
+///
+/// 当前成交量除以前 `period` 根K线 (不含当前) 的平均成交量
+///
+/// # 参数
+/// * `volumes` - 成交量列表
+/// * `period` - 平均周期
+///
+/// # 返回
+/// Python 列表，前 `period` 个值及平均成交量为 0 时为 None
+#[pyfunction]
+fn relative_volume(volumes: Vec<f64>, period: usize) -> PyResult<Vec<Option<f64>>> {
+    if period == 0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "period must be greater than 0"
+        ));
+    }
+
+    let stats = rolling_mean_std(&volumes, period);
+
+    Ok(volumes
+        .iter()
+        .enumerate()
+        .map(|(i, &volume)| {
+            if i == 0 {
+                return None;
+            }
+            stats[i - 1].and_then(|(mean, _)| {
+                if mean > 0.0 {
+                    Some(volume / mean)
+                } else {
+                    None
+                }
+            })
+        })
+        .collect())
+}
+
+/// 滚动均值与总体标准差
+///
+/// 第 i 个值基于 `values[i + 1 - window..=i]` 计算，前 `window - 1` 个值为 None
+fn rolling_mean_std(values: &[f64], window: usize) -> Vec<Option<(f64, f64)>> {
+    let mut result = Vec::with_capacity(values.len());
+    if window == 0 {
+        result.resize(values.len(), None);
+        return result;
+    }
+
+    let mut sum = 0.0;
+    let mut sum_sq = 0.0;
+
+    for (i, &value) in values.iter().enumerate() {
+        sum += value;
+        sum_sq += value * value;
+
+        if i >= window {
+            let old = values[i - window];
+            sum -= old;
+            sum_sq -= old * old;
+        }
+
+        if i + 1 >= window {
+            let mean = sum / window as f64;
+            let variance = (sum_sq / window as f64 - mean * mean).max(0.0);
+            result.push(Some((mean, variance.sqrt())));
+        } else {
+            result.push(None);
+        }
+    }
+
+    result
+}
+
+/// 均线交叉事件 (金叉/死叉)
+///
+/// 仅在两条均线都已满周期后判断交叉，事件位于交叉发生的K线
+///
+/// # 参数
+/// * `prices` - 价格列表
+/// * `timestamps` - 时间戳列表
+/// * `short_period` - 短期均线周期
+/// * `long_period` - 长期均线周期
+///
+/// # 返回
+/// Python 字典列表: timestamp, type ("golden"/"death"), short, long, price
+#[pyfunction]
+fn ma_cross_events(
+    prices: Vec<f64>,
+    timestamps: Vec<i64>,
+    short_period: usize,
+    long_period: usize,
+) -> PyResult<Vec<PyObject>> {
+    if prices.len() != timestamps.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Input arrays must have the same length"
+        ));
+    }
+
+    let short_ma = sma(prices.clone(), short_period)?;
+    let long_ma = sma(prices.clone(), long_period)?;
+    let warmup = short_period.max(long_period).saturating_sub(1);
+
+    Python::with_gil(|py| {
+        crossovers(&short_ma, &long_ma, warmup)
+            .into_iter()
+            .map(|(i, golden)| {
+                let dict = pyo3::types::PyDict::new(py);
+                dict.set_item("timestamp", timestamps[i])?;
+                dict.set_item("type", if golden { "golden" } else { "death" })?;
+                dict.set_item("short", short_ma[i])?;
+                dict.set_item("long", long_ma[i])?;
+                dict.set_item("price", prices[i])?;
+                Ok(dict.into())
+            })
+            .collect()
+    })
+}
+
+/// 两条序列的交叉点: (位置, 是否上穿)
+///
+/// 只判断 `start` 之后的K线 (与前一根比较)，上穿为 `true`，下穿为 `false`
+fn crossovers(fast: &[f64], slow: &[f64], start: usize) -> Vec<(usize, bool)> {
+    let n = fast.len().min(slow.len());
+    (start + 1..n)
+        .filter_map(|i| {
+            let (prev_diff, diff) = (fast[i - 1] - slow[i - 1], fast[i] - slow[i]);
+            if prev_diff <= 0.0 && diff > 0.0 {
+                Some((i, true))
+            } else if prev_diff >= 0.0 && diff < 0.0 {
+                Some((i, false))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// 按持仓权重加权的组合指标
+///
+/// 对同时出现在两个字典中的股票，计算指标值的权重归一化平均，
+/// 可用于任意标量指标 (如组合RSI、组合回撤)
+///
+/// # 参数
+/// * `per_symbol_values` - 每只股票的指标值
+/// * `weights` - 每只股票的权重 (如持仓市值)
+///
+/// # 返回
+/// 加权平均值，无重叠股票或权重和为0时为 None
+#[pyfunction]
+fn weighted_indicator(
+    per_symbol_values: HashMap<String, f64>,
+    weights: HashMap<String, f64>,
+) -> PyResult<Option<f64>> {
+    let mut weighted_sum = 0.0;
+    let mut weight_total = 0.0;
+
+    for (symbol, value) in &per_symbol_values {
+        if let Some(weight) = weights.get(symbol) {
+            weighted_sum += value * weight;
+            weight_total += weight;
+        }
+    }
+
+    if weight_total == 0.0 {
+        return Ok(None);
+    }
+
+    Ok(Some(weighted_sum / weight_total))
+}
+
 /// 批量计算技术指标
 ///
 /// # 参数
 /// * `prices` - 价格列表
 /// * `indicators` - 要计算的指标列表 ["ma5", "ma10", "ma20", "rsi", "macd", "boll"]
 ///
+/// 除固定别名外，也支持带参数的指标键（以冒号分隔）：
+/// * `"ma:30"` - 30日简单移动平均
+/// * `"ema:9"` - 9日指数移动平均
+/// * `"rsi:21"` - 21日RSI
+/// * `"boll:20:2.5"` - 20日、2.5倍标准差的布林带，输出 `boll:20:2.5_upper/_mid/_lower`
+/// * `"macd:12:26:9"` - 自定义周期的MACD，输出 `macd:12:26:9_dif/_dea/_hist`
+///
+/// 无法识别的指标键返回 ValueError
+///
 /// # 返回
 /// Python 字典，包含所有计算结果
 #[pyfunction]
 fn compute_indicators(prices: Vec<f64>, indicators: Vec<String>) -> PyResult<PyObject> {
+    let result = compute_indicator_map(&prices, &indicators)?;
+
+    Python::with_gil(|py| {
+        let dict = pyo3::types::PyDict::new(py);
+        for (key, value) in result {
+            let py_list = PyList::new(py, value.iter())?;
+            dict.set_item(key, py_list)?;
+        }
+        Ok(dict.into())
+    })
+}
+
+/// 计算指标键对应的结果 (compute_indicators 的纯 Rust 实现)
+fn compute_indicator_map(prices: &[f64], indicators: &[String]) -> PyResult<HashMap<String, Vec<f64>>> {
     let mut result: HashMap<String, Vec<f64>> = HashMap::new();
 
     for indicator in indicators {
         match indicator.as_str() {
             "ma5" => {
-                result.insert("ma5".to_string(), sma(prices.clone(), 5)?);
+                result.insert("ma5".to_string(), sma(prices.to_vec(), 5)?);
             }
             "ma10" => {
-                result.insert("ma10".to_string(), sma(prices.clone(), 10)?);
+                result.insert("ma10".to_string(), sma(prices.to_vec(), 10)?);
             }
             "ma20" => {
-                result.insert("ma20".to_string(), sma(prices.clone(), 20)?);
+                result.insert("ma20".to_string(), sma(prices.to_vec(), 20)?);
             }
             "ma60" => {
-                result.insert("ma60".to_string(), sma(prices.clone(), 60)?);
+                result.insert("ma60".to_string(), sma(prices.to_vec(), 60)?);
             }
             "ema12" => {
-                result.insert("ema12".to_string(), ema(prices.clone(), 12)?);
+                result.insert("ema12".to_string(), ema(prices.to_vec(), 12)?);
             }
             "ema26" => {
-                result.insert("ema26".to_string(), ema(prices.clone(), 26)?);
+                result.insert("ema26".to_string(), ema(prices.to_vec(), 26)?);
             }
             "rsi" => {
-                result.insert("rsi".to_string(), rsi(prices.clone(), 14)?);
+                result.insert("rsi".to_string(), rsi(prices.to_vec(), 14)?);
             }
             "rsi6" => {
-                result.insert("rsi6".to_string(), rsi(prices.clone(), 6)?);
+                result.insert("rsi6".to_string(), rsi(prices.to_vec(), 6)?);
             }
             "rsi12" => {
-                result.insert("rsi12".to_string(), rsi(prices.clone(), 12)?);
+                result.insert("rsi12".to_string(), rsi(prices.to_vec(), 12)?);
             }
             "rsi24" => {
-                result.insert("rsi24".to_string(), rsi(prices.clone(), 24)?);
+                result.insert("rsi24".to_string(), rsi(prices.to_vec(), 24)?);
             }
             "boll" => {
-                let boll = bollinger_bands(prices.clone(), 20, 2.0)?;
+                let boll = bollinger_bands(prices.to_vec(), 20, 2.0)?;
                 result.insert("boll_upper".to_string(), boll.get("upper").cloned().unwrap());
                 result.insert("boll_mid".to_string(), boll.get("mid").cloned().unwrap());
                 result.insert("boll_lower".to_string(), boll.get("lower").cloned().unwrap());
             }
             "macd" => {
-                let macd_data = macd(prices.clone(), 12, 26, 9)?;
+                let macd_data = macd(prices.to_vec(), 12, 26, 9)?;
                 result.insert("macd_dif".to_string(), macd_data.get("dif").cloned().unwrap());
                 result.insert("macd_dea".to_string(), macd_data.get("dea").cloned().unwrap());
                 result.insert("macd_hist".to_string(), macd_data.get("macd_hist").cloned().unwrap());
             }
-            _ => {}
+            key if key.contains(':') => {
+                compute_parameterized_indicator(prices, key, &mut result)?;
+            }
+            key => return Err(invalid_indicator_key(key)),
         }
     }
 
-    Python::with_gil(|py| {
-        let dict = pyo3::types::PyDict::new(py);
-        for (key, value) in result {
-            let py_list = PyList::new(py, value.iter())?;
-            dict.set_item(key, py_list)?;
+    Ok(result)
+}
+
+/// 计算带参数的指标键，如 "ma:30"、"boll:20:2.5"
+fn compute_parameterized_indicator(
+    prices: &[f64],
+    key: &str,
+    result: &mut HashMap<String, Vec<f64>>,
+) -> PyResult<()> {
+    let mut parts = key.split(':');
+    let name = parts.next().unwrap_or("");
+    let params = parts
+        .map(|p| p.trim().parse::<f64>())
+        .collect::<Result<Vec<f64>, _>>()
+        .map_err(|_| invalid_indicator_key(key))?;
+
+    let period_at = |idx: usize| -> PyResult<usize> {
+        match params.get(idx) {
+            Some(&p) if p >= 1.0 && p.fract() == 0.0 => Ok(p as usize),
+            _ => Err(invalid_indicator_key(key)),
         }
-        Ok(dict.into())
-    })
+    };
+
+    match name {
+        "ma" | "sma" if params.len() == 1 => {
+            result.insert(key.to_string(), sma(prices.to_vec(), period_at(0)?)?);
+        }
+        "ema" if params.len() == 1 => {
+            result.insert(key.to_string(), ema(prices.to_vec(), period_at(0)?)?);
+        }
+        "rsi" if params.len() == 1 => {
+            result.insert(key.to_string(), rsi(prices.to_vec(), period_at(0)?)?);
+        }
+        "boll" if params.len() == 2 => {
+            let mut boll = bollinger_bands(prices.to_vec(), period_at(0)?, params[1])?;
+            result.insert(format!("{}_upper", key), boll.remove("upper").unwrap());
+            result.insert(format!("{}_mid", key), boll.remove("mid").unwrap());
+            result.insert(format!("{}_lower", key), boll.remove("lower").unwrap());
+        }
+        "macd" if params.len() == 3 => {
+            let mut macd_data = macd(prices.to_vec(), period_at(0)?, period_at(1)?, period_at(2)?)?;
+            result.insert(format!("{}_dif", key), macd_data.remove("dif").unwrap());
+            result.insert(format!("{}_dea", key), macd_data.remove("dea").unwrap());
+            result.insert(format!("{}_hist", key), macd_data.remove("macd_hist").unwrap());
+        }
+        _ => return Err(invalid_indicator_key(key)),
+    }
+
+    Ok(())
+}
+
+fn invalid_indicator_key(key: &str) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(
+        format!("Invalid indicator key: {}", key)
+    )
 }
 
 /// Rust 模块定义
@@ -278,7 +623,13 @@ fn tacn_indicators(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(rsi, m)?)?;
     m.add_function(wrap_pyfunction!(macd, m)?)?;
     m.add_function(wrap_pyfunction!(bollinger_bands, m)?)?;
+    m.add_function(wrap_pyfunction!(bollinger_percent_b, m)?)?;
+    m.add_function(wrap_pyfunction!(bollinger_bandwidth, m)?)?;
     m.add_function(wrap_pyfunction!(compute_indicators, m)?)?;
+    m.add_function(wrap_pyfunction!(weighted_indicator, m)?)?;
+    m.add_function(wrap_pyfunction!(ma_cross_events, m)?)?;
+    m.add_function(wrap_pyfunction!(volume_spike, m)?)?;
+    m.add_function(wrap_pyfunction!(relative_volume, m)?)?;
     Ok(())
 }
 
@@ -307,4 +658,120 @@ mod tests {
         let result = rsi(prices, 14).unwrap();
         assert_eq!(result.len(), 50);
     }
+
+    #[test]
+    fn test_parameterized_indicator_keys() {
+        let prices: Vec<f64> = (0..60).map(|i| 100.0 + (i as f64 * 0.7).sin() * 5.0).collect();
+        let keys = vec!["ma:30".to_string(), "rsi:21".to_string(), "ma5".to_string()];
+        let result = compute_indicator_map(&prices, &keys).unwrap();
+
+        assert_eq!(result["ma:30"], sma(prices.clone(), 30).unwrap());
+        assert_eq!(result["rsi:21"], rsi(prices.clone(), 21).unwrap());
+        assert_ne!(result["rsi:21"], rsi(prices.clone(), 14).unwrap());
+        assert!(result.contains_key("ma5"));
+
+        let boll = compute_indicator_map(&prices, &["boll:20:2.5".to_string()]).unwrap();
+        assert!(boll.contains_key("boll:20:2.5_upper"));
+
+        assert!(compute_indicator_map(&prices, &["ma:abc".to_string()]).is_err());
+        assert!(compute_indicator_map(&prices, &["ma:0".to_string()]).is_err());
+        assert!(compute_indicator_map(&prices, &["ma7".to_string()]).is_err());
+        assert!(compute_indicator_map(&prices, &["ma5".to_string(), "bol".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_volume_spike() {
+        let mut volumes: Vec<f64> = (0..30).map(|i| 1000.0 + (i % 3) as f64 * 50.0).collect();
+        volumes[25] = 5000.0;
+        let result = volume_spike(volumes, 10, 3.0).unwrap();
+
+        assert_eq!(result.len(), 30);
+        assert!(result[..10].iter().all(|v| v.is_none()));
+        assert_eq!(result[25], Some(1));
+        assert!(result[10..25].iter().all(|v| *v == Some(0)));
+    }
+
+    #[test]
+    fn test_relative_volume() {
+        let mut volumes = vec![1000.0; 8];
+        volumes[6] = 2000.0;
+        let rvol = relative_volume(volumes, 5).unwrap();
+
+        assert!(rvol[..5].iter().all(|v| v.is_none()));
+        assert!((rvol[5].unwrap() - 1.0).abs() < 1e-12);
+        assert!((rvol[6].unwrap() - 2.0).abs() < 1e-12);
+        // 前5根均值 (1000*4 + 2000) / 5 = 1200
+        assert!((rvol[7].unwrap() - 1000.0 / 1200.0).abs() < 1e-12);
+
+        assert_eq!(relative_volume(vec![0.0; 4], 2).unwrap()[3], None);
+        assert!(relative_volume(vec![1.0], 0).is_err());
+    }
+
+    #[test]
+    fn test_bollinger_percent_b_and_bandwidth() {
+        // 周期2、1倍标准差: [1, 3] 均值2、标准差1，上轨为3，价格正好位于上轨
+        let prices = vec![1.0, 3.0, 1.0];
+        let percent_b = bollinger_percent_b(prices.clone(), 2, 1.0).unwrap();
+        assert_eq!(percent_b[0], None);
+        assert!((percent_b[1].unwrap() - 1.0).abs() < 1e-12);
+        assert!(percent_b[2].unwrap().abs() < 1e-12);
+
+        let bandwidth = bollinger_bandwidth(prices, 2, 1.0).unwrap();
+        assert_eq!(bandwidth[0], None);
+        assert!((bandwidth[1].unwrap() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_weighted_indicator() {
+        let values = HashMap::from([
+            ("600519".to_string(), 70.0),
+            ("000001".to_string(), 40.0),
+            ("300750".to_string(), 90.0),
+        ]);
+        let weights = HashMap::from([
+            ("600519".to_string(), 300_000.0),
+            ("000001".to_string(), 100_000.0),
+        ]);
+
+        // (70 * 3 + 40 * 1) / 4 = 62.5，300750 无权重不参与
+        let result = weighted_indicator(values.clone(), weights).unwrap();
+        assert!((result.unwrap() - 62.5).abs() < 1e-12);
+
+        let disjoint = HashMap::from([("601398".to_string(), 1.0)]);
+        assert_eq!(weighted_indicator(values, disjoint).unwrap(), None);
+    }
+
+    #[test]
+    fn test_ma_cross_events_golden_then_death() {
+        pyo3::prepare_freethreaded_python();
+        // 横盘 -> 上台阶 -> 下台阶：MA2 在第4根K线上穿 MA3，在第8根K线下穿
+        let prices = vec![10.0, 10.0, 10.0, 10.0, 13.0, 13.0, 13.0, 13.0, 7.0, 7.0, 7.0, 7.0];
+        let timestamps: Vec<i64> = (0..prices.len() as i64).map(|i| i * 1000).collect();
+
+        let events = ma_cross_events(prices, timestamps, 2, 3).unwrap();
+
+        Python::with_gil(|py| {
+            let events: Vec<(i64, String, f64)> = events
+                .iter()
+                .map(|event| {
+                    let event = event.downcast_bound::<pyo3::types::PyDict>(py).unwrap();
+                    let get = |key: &str| event.get_item(key).unwrap().unwrap();
+                    (
+                        get("timestamp").extract().unwrap(),
+                        get("type").extract().unwrap(),
+                        get("price").extract().unwrap(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                events,
+                vec![
+                    (4000, "golden".to_string(), 13.0),
+                    (8000, "death".to_string(), 7.0),
+                ]
+            );
+        });
+
+        assert!(ma_cross_events(vec![1.0, 2.0], vec![0], 2, 3).is_err());
+    }
 }
@@ -10,7 +10,14 @@ use pyo3::types::{PyList, PyDict};
 use rayon::prelude::*;
 use std::collections::HashMap;
 
+/// 带预热期 (None) 的指标序列
+type IndicatorSeries = Vec<Option<f64>>;
+
+/// K线 (timestamp, open, high, low, close, volume)
+type Kline = (i64, f64, f64, f64, f64, f64);
+
 /// 信号类型
+#[pyclass(eq, eq_int)]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Signal {
     Buy,
@@ -18,7 +25,27 @@ pub enum Signal {
     Hold,
 }
 
+impl Signal {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Signal::Buy => "buy",
+            Signal::Sell => "sell",
+            Signal::Hold => "hold",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "buy" => Some(Signal::Buy),
+            "sell" => Some(Signal::Sell),
+            "hold" => Some(Signal::Hold),
+            _ => None,
+        }
+    }
+}
+
 /// 信号强度
+#[pyclass(eq, eq_int)]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SignalStrength {
     Weak,
@@ -26,6 +53,25 @@ pub enum SignalStrength {
     Strong,
 }
 
+impl SignalStrength {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SignalStrength::Weak => "weak",
+            SignalStrength::Moderate => "moderate",
+            SignalStrength::Strong => "strong",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "weak" => Some(SignalStrength::Weak),
+            "moderate" => Some(SignalStrength::Moderate),
+            "strong" => Some(SignalStrength::Strong),
+            _ => None,
+        }
+    }
+}
+
 /// 技术指标结果
 #[derive(Debug, Clone)]
 pub struct IndicatorResult {
@@ -36,15 +82,168 @@ pub struct IndicatorResult {
 }
 
 /// 策略信号
+#[pyclass]
 #[derive(Debug, Clone)]
 pub struct StrategySignal {
+    #[pyo3(get, set)]
     pub symbol: String,
+    #[pyo3(get, set)]
     pub timestamp: i64,
+    #[pyo3(get, set)]
     pub signal: Signal,
+    #[pyo3(get, set)]
     pub strength: SignalStrength,
+    #[pyo3(get, set)]
     pub price: f64,
+    #[pyo3(get, set)]
     pub indicators: HashMap<String, f64>,
+    #[pyo3(get, set)]
     pub reason: String,
+    /// 置信度 (0..1)
+    #[pyo3(get, set)]
+    pub confidence: f64,
+}
+
+#[pymethods]
+impl StrategySignal {
+    #[new]
+    #[pyo3(signature = (symbol, timestamp, signal, strength, price, indicators=HashMap::new(), reason=String::new(), confidence=0.0))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        symbol: String,
+        timestamp: i64,
+        signal: Signal,
+        strength: SignalStrength,
+        price: f64,
+        indicators: HashMap<String, f64>,
+        reason: String,
+        confidence: f64,
+    ) -> Self {
+        StrategySignal {
+            symbol,
+            timestamp,
+            signal,
+            strength,
+            price,
+            indicators,
+            reason,
+            confidence,
+        }
+    }
+
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("symbol", &self.symbol)?;
+        dict.set_item("timestamp", self.timestamp)?;
+        dict.set_item("signal", self.signal.as_str())?;
+        dict.set_item("strength", self.strength.as_str())?;
+        dict.set_item("price", self.price)?;
+        dict.set_item("indicators", &self.indicators)?;
+        dict.set_item("reason", &self.reason)?;
+        dict.set_item("confidence", self.confidence)?;
+        Ok(dict)
+    }
+
+    /// 从信号字典重建 StrategySignal
+    ///
+    /// 必需键: symbol, timestamp, signal, strength, price；
+    /// 可选键: indicators, reason, confidence。`generate_signals` 输出中的
+    /// `indicator_value` 会存入 `indicators["indicator_value"]`。
+    #[staticmethod]
+    fn from_dict(d: &Bound<'_, PyDict>) -> PyResult<StrategySignal> {
+        let signal_str: String = required_item(d, "signal")?.extract()?;
+        let signal = Signal::parse(&signal_str).ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                format!("Invalid signal: {}", signal_str)
+            )
+        })?;
+
+        let strength_str: String = required_item(d, "strength")?.extract()?;
+        let strength = SignalStrength::parse(&strength_str).ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                format!("Invalid strength: {}", strength_str)
+            )
+        })?;
+
+        let mut indicators: HashMap<String, f64> = match d.get_item("indicators")? {
+            Some(value) if !value.is_none() => value.extract()?,
+            _ => HashMap::new(),
+        };
+        if let Some(value) = d.get_item("indicator_value")? {
+            if !value.is_none() {
+                indicators.entry("indicator_value".to_string()).or_insert(value.extract()?);
+            }
+        }
+
+        let reason = match d.get_item("reason")? {
+            Some(value) if !value.is_none() => value.extract()?,
+            _ => String::new(),
+        };
+
+        let confidence = match d.get_item("confidence")? {
+            Some(value) if !value.is_none() => value.extract()?,
+            _ => 0.0,
+        };
+
+        Ok(StrategySignal {
+            symbol: required_item(d, "symbol")?.extract()?,
+            timestamp: required_item(d, "timestamp")?.extract()?,
+            signal,
+            strength,
+            price: required_item(d, "price")?.extract()?,
+            indicators,
+            reason,
+            confidence,
+        })
+    }
+}
+
+/// 读取字典中的必需键，缺失时返回 KeyError
+fn required_item<'py>(d: &Bound<'py, PyDict>, key: &str) -> PyResult<Bound<'py, PyAny>> {
+    d.get_item(key)?.ok_or_else(|| {
+        PyErr::new::<pyo3::exceptions::PyKeyError, _>(
+            format!("Missing required key: {}", key)
+        )
+    })
+}
+
+/// 背离检测结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    pub index: usize,
+    pub kind: &'static str,
+    pub first_pivot: usize,
+    pub second_pivot: usize,
+}
+
+/// K线形态识别结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandlePattern {
+    pub index: usize,
+    pub timestamp: i64,
+    pub pattern: &'static str,
+    pub direction: &'static str,
+}
+
+/// K线形态阈值
+#[derive(Debug, Clone)]
+pub struct PatternThresholds {
+    /// 十字星：实体不超过振幅的该比例 (默认0.1)
+    pub doji_body_ratio: f64,
+    /// 锤子线/流星线：长影线至少为实体的该倍数 (默认2.0)
+    pub shadow_body_ratio: f64,
+    /// 锤子线/流星线：另一侧影线不超过振幅的该比例 (默认0.1)
+    pub opposite_shadow_ratio: f64,
+}
+
+impl Default for PatternThresholds {
+    fn default() -> Self {
+        PatternThresholds {
+            doji_body_ratio: 0.1,
+            shadow_body_ratio: 2.0,
+            opposite_shadow_ratio: 0.1,
+        }
+    }
 }
 
 /// 计算RSI指标
@@ -52,15 +251,32 @@ pub struct StrategySignal {
 /// # 参数
 /// * `prices` - 价格列表
 /// * `period` - RSI周期 (通常14)
+/// * `method` - 平均方法: "simple" (滑动窗口简单平均，默认) 或 "wilder" (Wilder平滑，与主流行情软件一致)
 ///
 /// # 返回
-/// RSI值列表 (0-100)
+/// RSI值列表 (0-100)，前 `period` 个值为 None
 #[pyfunction]
-fn calculate_rsi(prices: Vec<f64>, period: usize) -> PyResult<Vec<Option<f64>>> {
+#[pyo3(signature = (prices, period, method="simple"))]
+fn calculate_rsi(prices: Vec<f64>, period: usize, method: &str) -> PyResult<Vec<Option<f64>>> {
+    if method != "simple" && method != "wilder" {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            format!("Unknown RSI method: {}", method)
+        ));
+    }
+    if method == "wilder" && period == 0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Wilder RSI period must be positive"
+        ));
+    }
+
     if prices.len() < period + 1 {
         return Ok((0..prices.len()).map(|_| None).collect());
     }
 
+    if method == "wilder" {
+        return Ok(calculate_rsi_wilder(&prices, period));
+    }
+
     let mut result = Vec::with_capacity(prices.len());
 
     for i in 0..prices.len() {
@@ -82,19 +298,50 @@ fn calculate_rsi(prices: Vec<f64>, period: usize) -> PyResult<Vec<Option<f64>>>
             let avg_gain = gains / period as f64;
             let avg_loss = losses / period as f64;
 
-            let rsi = if avg_loss == 0.0 {
-                100.0
-            } else {
-                100.0 - (100.0 / (1.0 + avg_gain / avg_loss))
-            };
-
-            result.push(Some(rsi));
+            result.push(Some(rsi_from_averages(avg_gain, avg_loss)));
         }
     }
 
     Ok(result)
 }
 
+/// Wilder平滑RSI：以前 `period` 个价格变化的简单平均为初值，
+/// 之后按 `avg = (prev * (period - 1) + current) / period` 递推
+fn calculate_rsi_wilder(prices: &[f64], period: usize) -> Vec<Option<f64>> {
+    let mut result = vec![None; period];
+    let mut avg_gain = 0.0;
+    let mut avg_loss = 0.0;
+
+    for i in 1..prices.len() {
+        let change = prices[i] - prices[i - 1];
+        let gain = change.max(0.0);
+        let loss = (-change).max(0.0);
+
+        if i <= period {
+            avg_gain += gain / period as f64;
+            avg_loss += loss / period as f64;
+        } else {
+            avg_gain = (avg_gain * (period - 1) as f64 + gain) / period as f64;
+            avg_loss = (avg_loss * (period - 1) as f64 + loss) / period as f64;
+        }
+
+        if i >= period {
+            result.push(Some(rsi_from_averages(avg_gain, avg_loss)));
+        }
+    }
+
+    result
+}
+
+/// 由平均涨幅和平均跌幅计算RSI
+fn rsi_from_averages(avg_gain: f64, avg_loss: f64) -> f64 {
+    if avg_loss == 0.0 {
+        100.0
+    } else {
+        100.0 - (100.0 / (1.0 + avg_gain / avg_loss))
+    }
+}
+
 /// 计算MACD指标
 ///
 /// # 参数
@@ -104,7 +351,8 @@ fn calculate_rsi(prices: Vec<f64>, period: usize) -> PyResult<Vec<Option<f64>>>
 /// * `signal_period` - 信号线周期 (默认9)
 ///
 /// # 返回
-/// (macd线, 信号线, 柱状图)
+/// (macd线, 信号线, 柱状图)；信号线在 MACD 线连续有效 `signal_period` 根后才有值，
+/// 以这段 MACD 值的均值为初值
 #[pyfunction]
 fn calculate_macd(
     prices: Vec<f64>,
@@ -163,13 +411,33 @@ fn calculate_bollinger_bands(
     let mut middle = Vec::new();
     let mut lower = Vec::new();
 
-    for i in 0..prices.len() {
-        if i < period - 1 {
-            upper.push(None);
-            middle.push(None);
-            lower.push(None);
-        } else {
-            let slice = &prices[i - period + 1..=i];
+    for stats in rolling_mean_std(&prices, period) {
+        match stats {
+            Some((avg, std)) => {
+                middle.push(Some(avg));
+                upper.push(Some(avg + std_dev * std));
+                lower.push(Some(avg - std_dev * std));
+            }
+            None => {
+                upper.push(None);
+                middle.push(None);
+                lower.push(None);
+            }
+        }
+    }
+
+    Ok((upper, middle, lower))
+}
+
+/// 辅助函数：滚动窗口的 (均值, 总体标准差)，前 `period - 1` 个值为 None
+fn rolling_mean_std(prices: &[f64], period: usize) -> Vec<Option<(f64, f64)>> {
+    (0..prices.len())
+        .map(|i| {
+            if period == 0 || i + 1 < period {
+                return None;
+            }
+
+            let slice = &prices[i + 1 - period..=i];
             let sum: f64 = slice.iter().sum();
             let avg = sum / period as f64;
 
@@ -180,11 +448,88 @@ fn calculate_bollinger_bands(
                 })
                 .sum::<f64>() / period as f64;
 
-            let std = variance.sqrt();
+            Some((avg, variance.sqrt()))
+        })
+        .collect()
+}
+
+/// 计算唐奇安通道 (Donchian Channel)
+///
+/// # 参数
+/// * `highs` - 最高价列表
+/// * `lows` - 最低价列表
+/// * `period` - 周期 (默认20)
+///
+/// # 返回
+/// (上轨 = 周期内最高价, 中轨 = 上下轨均值, 下轨 = 周期内最低价)，前 `period - 1` 个值为 None
+#[pyfunction]
+fn calculate_donchian(
+    highs: Vec<f64>,
+    lows: Vec<f64>,
+    period: usize,
+) -> PyResult<(IndicatorSeries, IndicatorSeries, IndicatorSeries)> {
+    if highs.len() != lows.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Input arrays must have the same length"
+        ));
+    }
+
+    let mut upper = Vec::with_capacity(highs.len());
+    let mut middle = Vec::with_capacity(highs.len());
+    let mut lower = Vec::with_capacity(highs.len());
+
+    for extremes in rolling_high_low(&highs, &lows, period) {
+        upper.push(extremes.map(|(highest, _)| highest));
+        middle.push(extremes.map(|(highest, lowest)| (highest + lowest) / 2.0));
+        lower.push(extremes.map(|(_, lowest)| lowest));
+    }
+
+    Ok((upper, middle, lower))
+}
 
-            middle.push(Some(avg));
-            upper.push(Some(avg + std_dev * std));
-            lower.push(Some(avg - std_dev * std));
+/// 计算肯特纳通道 (Keltner Channel)
+///
+/// 中轨为收盘价 EMA，上下轨为中轨 ± `mult` × ATR
+///
+/// # 参数
+/// * `highs` - 最高价列表
+/// * `lows` - 最低价列表
+/// * `closes` - 收盘价列表
+/// * `ema_period` - EMA周期 (默认20)
+/// * `atr_period` - ATR周期 (默认10)
+/// * `mult` - ATR倍数 (默认2)
+///
+/// # 返回
+/// (上轨, 中轨, 下轨)，前 `max(ema_period, atr_period) - 1` 个值为 None
+#[pyfunction]
+fn calculate_keltner(
+    highs: Vec<f64>,
+    lows: Vec<f64>,
+    closes: Vec<f64>,
+    ema_period: usize,
+    atr_period: usize,
+    mult: f64,
+) -> PyResult<(IndicatorSeries, IndicatorSeries, IndicatorSeries)> {
+    let atr = calculate_atr(highs, lows, closes.clone(), atr_period)?;
+    let ema = calculate_ema(&closes, ema_period);
+    let warmup = ema_period.max(atr_period).saturating_sub(1);
+
+    let mut upper = Vec::with_capacity(closes.len());
+    let mut middle = Vec::with_capacity(closes.len());
+    let mut lower = Vec::with_capacity(closes.len());
+
+    for i in 0..closes.len() {
+        match (ema[i], atr[i]) {
+            (Some(mid), Some(range)) if i >= warmup => {
+                upper.push(Some(mid + mult * range));
+                middle.push(Some(mid));
+                lower.push(Some(mid - mult * range));
+            }
+            _ => {
+                upper.push(None);
+                middle.push(None);
+                lower.push(None);
+            }
         }
     }
 
@@ -214,131 +559,1355 @@ fn calculate_atr(
         ));
     }
 
-    let mut true_ranges = Vec::with_capacity(highs.len());
-
-    for i in 0..highs.len() {
-        if i == 0 {
-            true_ranges.push(highs[0] - lows[0]);
-        } else {
-            let tr = (highs[i] - lows[i])
-                .max((highs[i] - closes[i - 1]).abs())
-                .max((lows[i] - closes[i - 1]).abs());
-            true_ranges.push(tr);
-        }
-    }
+    let true_ranges = calculate_true_ranges(&highs, &lows, &closes);
 
     // 计算ATR (使用EMA方法)
     Ok(calculate_ema(&true_ranges, period))
 }
 
-/// 并行计算多个技术指标
+/// 计算威廉指标 (Williams %R)
+///
+/// `%R = -100 * (highest_high - close) / (highest_high - lowest_low)`，取值 -100..0
 ///
 /// # 参数
-/// * `prices` - 价格列表
-/// * `rsi_period` - RSI周期
-/// * `macd_fast` - MACD快线
-/// * `macd_slow` - MACD慢线
-/// * `bb_period` - 布林带周期
+/// * `highs` - 最高价列表
+/// * `lows` - 最低价列表
+/// * `closes` - 收盘价列表
+/// * `period` - 周期 (默认14)
 ///
 /// # 返回
-/// 指标字典
+/// %R值列表，前 `period - 1` 个值及最高价等于最低价的窗口为 None
 #[pyfunction]
-fn calculate_indicators(
-    prices: Vec<f64>,
-    rsi_period: usize,
-    macd_fast: usize,
-    macd_slow: usize,
-    bb_period: usize,
-) -> PyResult<PyObject> {
-    // 并行计算多个指标 (rayon::join 只接受2个闭包，使用嵌套)
-    let (rsi, (macd, bb)) = rayon::join(
-        || calculate_rsi(prices.clone(), rsi_period),
-        || rayon::join(
-            || calculate_macd(prices.clone(), macd_fast, macd_slow, 9),
-            || calculate_bollinger_bands(prices.clone(), bb_period, 2.0),
-        ),
-    );
+fn calculate_williams_r(
+    highs: Vec<f64>,
+    lows: Vec<f64>,
+    closes: Vec<f64>,
+    period: usize,
+) -> PyResult<Vec<Option<f64>>> {
+    if highs.len() != lows.len() || highs.len() != closes.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Input arrays must have the same length"
+        ));
+    }
 
-    // 解包结果
-    let rsi = rsi?;
-    let (macd_line, signal_line, histogram) = macd?;
-    let (upper, middle, lower) = bb?;
+    Ok(rolling_high_low(&highs, &lows, period)
+        .iter()
+        .zip(closes.iter())
+        .map(|(extremes, &close)| {
+            extremes.and_then(|(highest, lowest)| {
+                if highest > lowest {
+                    Some(-100.0 * (highest - close) / (highest - lowest))
+                } else {
+                    None
+                }
+            })
+        })
+        .collect())
+}
 
-    Python::with_gil(|py| {
-        let dict = PyDict::new(py);
+/// 计算顺势指标 (CCI)
+///
+/// 典型价 `tp = (high + low + close) / 3`，`CCI = (tp - SMA(tp)) / (0.015 * 平均绝对偏差)`
+///
+/// # 参数
+/// * `highs` - 最高价列表
+/// * `lows` - 最低价列表
+/// * `closes` - 收盘价列表
+/// * `period` - 周期 (默认20)
+///
+/// # 返回
+/// CCI值列表，前 `period - 1` 个值及平均绝对偏差为0的窗口为 None
+#[pyfunction]
+fn calculate_cci(
+    highs: Vec<f64>,
+    lows: Vec<f64>,
+    closes: Vec<f64>,
+    period: usize,
+) -> PyResult<Vec<Option<f64>>> {
+    if highs.len() != lows.len() || highs.len() != closes.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Input arrays must have the same length"
+        ));
+    }
 
-        // RSI
-        let rsi_list = PyList::new(py, &rsi)?;
-        dict.set_item("rsi", rsi_list)?;
+    let typical: Vec<f64> = (0..closes.len())
+        .map(|i| (highs[i] + lows[i] + closes[i]) / 3.0)
+        .collect();
 
-        // MACD
-        let macd_list = PyList::new(py, &macd_line)?;
-        let signal_list = PyList::new(py, &signal_line)?;
-        let hist_list = PyList::new(py, &histogram)?;
-        dict.set_item("macd", macd_list)?;
-        dict.set_item("macd_signal", signal_list)?;
-        dict.set_item("macd_histogram", hist_list)?;
+    let mut result = Vec::with_capacity(typical.len());
+    for i in 0..typical.len() {
+        if period == 0 || i + 1 < period {
+            result.push(None);
+            continue;
+        }
 
-        // Bollinger Bands
-        let upper_list = PyList::new(py, &upper)?;
-        let middle_list = PyList::new(py, &middle)?;
-        let lower_list = PyList::new(py, &lower)?;
-        dict.set_item("bb_upper", upper_list)?;
-        dict.set_item("bb_middle", middle_list)?;
-        dict.set_item("bb_lower", lower_list)?;
+        let window = &typical[i + 1 - period..=i];
+        let sma = window.iter().sum::<f64>() / period as f64;
+        let mean_dev = window.iter().map(|tp| (tp - sma).abs()).sum::<f64>() / period as f64;
 
-        Ok(dict.into())
-    })
+        if mean_dev == 0.0 {
+            result.push(None);
+        } else {
+            result.push(Some((typical[i] - sma) / (0.015 * mean_dev)));
+        }
+    }
+
+    Ok(result)
 }
 
-/// 生成交易信号
+/// 计算抛物线转向指标 (Parabolic SAR)
+///
+/// Wilder 算法：第二根K线按中价变化判定初始趋势，上升趋势初始 SAR 为前一根最低价，
+/// 下降趋势为前一根最高价。之后 `SAR = 前SAR + AF × (EP - 前SAR)`，且不得进入前两根K线的
+/// 价格区间；价格穿越 SAR 时反转，SAR 取原极值点，AF 重置为 `af_start`。
+/// 每创新极值 AF 增加 `af_step`，上限 `af_max`。
 ///
 /// # 参数
-/// * `symbol` - 股票代码
-/// * `prices` - 价格列表
-/// * `strategy` - 策略类型 ("rsi", "macd", "bb", "combined")
-/// * `params` - 策略参数 (JSON字符串)
+/// * `highs` - 最高价列表
+/// * `lows` - 最低价列表
+/// * `af_start` - 初始加速因子 (默认0.02)
+/// * `af_step` - 加速因子步长 (默认0.02)
+/// * `af_max` - 加速因子上限 (默认0.2)
 ///
 /// # 返回
-/// 信号列表
+/// SAR值列表，第一个值为 None
 #[pyfunction]
-fn generate_signals(
-    symbol: String,
-    prices: Vec<f64>,
-    timestamps: Vec<i64>,
-    strategy: &str,
-    params: &str,
-) -> PyResult<Vec<PyObject>> {
-    let params_map: HashMap<String, f64> = serde_json::from_str(params)
-        .unwrap_or_else(|_| HashMap::new());
+fn calculate_psar(
+    highs: Vec<f64>,
+    lows: Vec<f64>,
+    af_start: f64,
+    af_step: f64,
+    af_max: f64,
+) -> PyResult<Vec<Option<f64>>> {
+    if highs.len() != lows.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Input arrays must have the same length"
+        ));
+    }
 
-    let signals = match strategy {
-        "rsi" => {
+    let n = highs.len();
+    let mut result = vec![None; n];
+    if n < 2 {
+        return Ok(result);
+    }
+
+    let mut uptrend = highs[1] + lows[1] >= highs[0] + lows[0];
+    let mut sar = if uptrend { lows[0] } else { highs[0] };
+    let mut ep = if uptrend { highs[0].max(highs[1]) } else { lows[0].min(lows[1]) };
+    let mut af = af_start;
+
+    for i in 1..n {
+        if i > 1 {
+            sar += af * (ep - sar);
+            // SAR 不得进入前两根K线的价格区间
+            if uptrend {
+                sar = sar.min(lows[i - 1]).min(lows[i - 2]);
+            } else {
+                sar = sar.max(highs[i - 1]).max(highs[i - 2]);
+            }
+        }
+
+        if uptrend && lows[i] < sar {
+            uptrend = false;
+            sar = ep;
+            ep = lows[i];
+            af = af_start;
+        } else if !uptrend && highs[i] > sar {
+            uptrend = true;
+            sar = ep;
+            ep = highs[i];
+            af = af_start;
+        } else if uptrend && highs[i] > ep {
+            ep = highs[i];
+            af = (af + af_step).min(af_max);
+        } else if !uptrend && lows[i] < ep {
+            ep = lows[i];
+            af = (af + af_step).min(af_max);
+        }
+
+        result[i] = Some(sar);
+    }
+
+    Ok(result)
+}
+
+/// 计算变动率 (ROC)
+///
+/// `ROC = 100 * (price[i] - price[i - period]) / price[i - period]`
+///
+/// # 参数
+/// * `prices` - 价格列表
+/// * `period` - 周期
+///
+/// # 返回
+/// ROC值列表 (%)，前 `period` 个值及基期价格为0时为 None
+#[pyfunction]
+fn calculate_roc(prices: Vec<f64>, period: usize) -> PyResult<Vec<Option<f64>>> {
+    Ok(lagged_values(&prices, period, |current, past| {
+        if past == 0.0 {
+            None
+        } else {
+            Some(100.0 * (current - past) / past)
+        }
+    }))
+}
+
+/// 计算动量 (Momentum)
+///
+/// `MOM = price[i] - price[i - period]`
+///
+/// # 参数
+/// * `prices` - 价格列表
+/// * `period` - 周期
+///
+/// # 返回
+/// 动量值列表，前 `period` 个值为 None
+#[pyfunction]
+fn calculate_momentum(prices: Vec<f64>, period: usize) -> PyResult<Vec<Option<f64>>> {
+    Ok(lagged_values(&prices, period, |current, past| Some(current - past)))
+}
+
+/// 计算考夫曼效率比 (Efficiency Ratio)
+///
+/// `ER = |price[i] - price[i - period]| / sum(|price[j] - price[j - 1]|)`，j 取最近 `period` 个变动，取值 0..1
+///
+/// # 参数
+/// * `prices` - 价格列表
+/// * `period` - 周期
+///
+/// # 返回
+/// ER值列表，前 `period` 个值及窗口内价格无变动时为 None
+#[pyfunction]
+fn efficiency_ratio(prices: Vec<f64>, period: usize) -> PyResult<Vec<Option<f64>>> {
+    Ok(efficiency_ratios(&prices, period))
+}
+
+/// 辅助函数：效率比序列，用前缀和累计价格变动绝对值
+fn efficiency_ratios(prices: &[f64], period: usize) -> Vec<Option<f64>> {
+    let mut path = Vec::with_capacity(prices.len());
+    path.push(0.0);
+    for i in 1..prices.len() {
+        path.push(path[i - 1] + (prices[i] - prices[i - 1]).abs());
+    }
+
+    (0..prices.len())
+        .map(|i| {
+            if period == 0 || i < period {
+                return None;
+            }
+            let noise = path[i] - path[i - period];
+            if noise > 0.0 {
+                Some((prices[i] - prices[i - period]).abs() / noise)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// 计算考夫曼自适应均线 (KAMA)
+///
+/// 平滑系数 `sc = (ER * (2/(fast+1) - 2/(slow+1)) + 2/(slow+1))^2`，
+/// `KAMA[i] = KAMA[i-1] + sc * (price[i] - KAMA[i-1])`，以第 `er_period - 1` 根的价格为初值；
+/// 窗口内价格无变动时 ER 按0处理
+///
+/// # 参数
+/// * `prices` - 价格列表
+/// * `er_period` - 效率比周期 (常用10)
+/// * `fast` - 快速EMA周期 (常用2)
+/// * `slow` - 慢速EMA周期 (常用30)
+///
+/// # 返回
+/// KAMA值列表，前 `er_period - 1` 个值为 None
+#[pyfunction]
+fn kama(prices: Vec<f64>, er_period: usize, fast: usize, slow: usize) -> PyResult<Vec<Option<f64>>> {
+    if er_period == 0 || fast == 0 || slow == 0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "er_period, fast and slow must be positive"
+        ));
+    }
+
+    let fast_sc = 2.0 / (fast as f64 + 1.0);
+    let slow_sc = 2.0 / (slow as f64 + 1.0);
+    let ratios = efficiency_ratios(&prices, er_period);
+
+    let mut result = vec![None; prices.len()];
+    if prices.len() < er_period {
+        return Ok(result);
+    }
+
+    let mut value = prices[er_period - 1];
+    result[er_period - 1] = Some(value);
+    for i in er_period..prices.len() {
+        let sc = (ratios[i].unwrap_or(0.0) * (fast_sc - slow_sc) + slow_sc).powi(2);
+        value += sc * (prices[i] - value);
+        result[i] = Some(value);
+    }
+
+    Ok(result)
+}
+
+/// 辅助函数：以 (当前价, `period` 根之前的价格) 计算指标，前 `period` 个值为 None
+fn lagged_values<F>(prices: &[f64], period: usize, f: F) -> Vec<Option<f64>>
+where
+    F: Fn(f64, f64) -> Option<f64>,
+{
+    (0..prices.len())
+        .map(|i| if i < period { None } else { f(prices[i], prices[i - period]) })
+        .collect()
+}
+
+/// 计算能量潮指标 (OBV)
+///
+/// 自0开始累计：收盘价上涨加当期成交量，下跌减当期成交量，持平不变
+///
+/// # 参数
+/// * `closes` - 收盘价列表
+/// * `volumes` - 成交量列表
+///
+/// # 返回
+/// OBV值列表，与输入等长，首个值为0
+#[pyfunction]
+fn calculate_obv(closes: Vec<f64>, volumes: Vec<f64>) -> PyResult<Vec<f64>> {
+    if closes.len() != volumes.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Closes and volumes must have the same length"
+        ));
+    }
+
+    let mut obv = Vec::with_capacity(closes.len());
+    let mut total = 0.0;
+    for i in 0..closes.len() {
+        if i > 0 {
+            if closes[i] > closes[i - 1] {
+                total += volumes[i];
+            } else if closes[i] < closes[i - 1] {
+                total -= volumes[i];
+            }
+        }
+        obv.push(total);
+    }
+
+    Ok(obv)
+}
+
+/// 计算ADX / DMI (平均趋向指标)
+///
+/// 真实波幅与 +DM/-DM 使用 Wilder 平滑，+DI/-DI 自第 `period` 根K线起有值，
+/// ADX 为 DX 的 Wilder 平滑，自第 `2 * period - 1` 根K线起有值
+///
+/// # 参数
+/// * `highs` - 最高价列表
+/// * `lows` - 最低价列表
+/// * `closes` - 收盘价列表
+/// * `period` - 周期 (默认14)
+///
+/// # 返回
+/// (adx, +DI, -DI)
+#[pyfunction]
+fn calculate_adx(
+    highs: Vec<f64>,
+    lows: Vec<f64>,
+    closes: Vec<f64>,
+    period: usize,
+) -> PyResult<(IndicatorSeries, IndicatorSeries, IndicatorSeries)> {
+    if highs.len() != lows.len() || highs.len() != closes.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Input arrays must have the same length"
+        ));
+    }
+    if period == 0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Period must be greater than 0"
+        ));
+    }
+
+    let n = highs.len();
+    let mut adx = vec![None; n];
+    let mut plus_di = vec![None; n];
+    let mut minus_di = vec![None; n];
+
+    if n <= period {
+        return Ok((adx, plus_di, minus_di));
+    }
+
+    let true_ranges = calculate_true_ranges(&highs, &lows, &closes);
+    let period_f = period as f64;
+
+    let mut smoothed_tr = 0.0;
+    let mut smoothed_plus_dm = 0.0;
+    let mut smoothed_minus_dm = 0.0;
+    let mut dx_sum = 0.0;
+    let mut prev_adx: Option<f64> = None;
+
+    for i in 1..n {
+        let up_move = highs[i] - highs[i - 1];
+        let down_move = lows[i - 1] - lows[i];
+        let plus_dm = if up_move > down_move && up_move > 0.0 { up_move } else { 0.0 };
+        let minus_dm = if down_move > up_move && down_move > 0.0 { down_move } else { 0.0 };
+
+        // Wilder 平滑：前 period 个值求和作为初值，之后 S = S - S/period + x
+        if i <= period {
+            smoothed_tr += true_ranges[i];
+            smoothed_plus_dm += plus_dm;
+            smoothed_minus_dm += minus_dm;
+        } else {
+            smoothed_tr = smoothed_tr - smoothed_tr / period_f + true_ranges[i];
+            smoothed_plus_dm = smoothed_plus_dm - smoothed_plus_dm / period_f + plus_dm;
+            smoothed_minus_dm = smoothed_minus_dm - smoothed_minus_dm / period_f + minus_dm;
+        }
+
+        if i < period {
+            continue;
+        }
+
+        let (pdi, mdi) = if smoothed_tr > 0.0 {
+            (100.0 * smoothed_plus_dm / smoothed_tr, 100.0 * smoothed_minus_dm / smoothed_tr)
+        } else {
+            (0.0, 0.0)
+        };
+        plus_di[i] = Some(pdi);
+        minus_di[i] = Some(mdi);
+
+        let di_sum = pdi + mdi;
+        let dx = if di_sum > 0.0 { 100.0 * (pdi - mdi).abs() / di_sum } else { 0.0 };
+
+        if i < 2 * period - 1 {
+            dx_sum += dx;
+        } else {
+            let value = match prev_adx {
+                None => (dx_sum + dx) / period_f,
+                Some(prev) => (prev * (period_f - 1.0) + dx) / period_f,
+            };
+            adx[i] = Some(value);
+            prev_adx = Some(value);
+        }
+    }
+
+    Ok((adx, plus_di, minus_di))
+}
+
+/// 辅助函数：计算真实波幅 (首根K线为最高价 - 最低价)
+fn calculate_true_ranges(highs: &[f64], lows: &[f64], closes: &[f64]) -> Vec<f64> {
+    let mut true_ranges = Vec::with_capacity(highs.len());
+
+    for i in 0..highs.len() {
+        if i == 0 {
+            true_ranges.push(highs[0] - lows[0]);
+        } else {
+            let tr = (highs[i] - lows[i])
+                .max((highs[i] - closes[i - 1]).abs())
+                .max((lows[i] - closes[i - 1]).abs());
+            true_ranges.push(tr);
+        }
+    }
+
+    true_ranges
+}
+
+/// 计算随机指标 (Stochastic Oscillator, %K/%D)
+///
+/// # 参数
+/// * `highs` - 最高价列表
+/// * `lows` - 最低价列表
+/// * `closes` - 收盘价列表
+/// * `k_period` - %K周期 (默认14)
+/// * `d_period` - %D周期 (默认3)
+/// * `smooth` - %K平滑周期 (慢速随机指标通常为3，0或1表示不平滑)
+///
+/// # 返回
+/// (%K, %D)，预热期及最高价等于最低价的窗口为 None
+#[pyfunction]
+fn calculate_stochastic(
+    highs: Vec<f64>,
+    lows: Vec<f64>,
+    closes: Vec<f64>,
+    k_period: usize,
+    d_period: usize,
+    smooth: usize,
+) -> PyResult<(IndicatorSeries, IndicatorSeries)> {
+    if highs.len() != lows.len() || highs.len() != closes.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Input arrays must have the same length"
+        ));
+    }
+    if k_period == 0 || d_period == 0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Periods must be greater than 0"
+        ));
+    }
+
+    let raw_k: Vec<Option<f64>> = rolling_high_low(&highs, &lows, k_period)
+        .iter()
+        .zip(closes.iter())
+        .map(|(extremes, &close)| {
+            extremes.and_then(|(highest, lowest)| {
+                if highest > lowest {
+                    Some(100.0 * (close - lowest) / (highest - lowest))
+                } else {
+                    None
+                }
+            })
+        })
+        .collect();
+
+    let percent_k = if smooth > 1 {
+        calculate_sma_from_values(&raw_k, smooth)
+    } else {
+        raw_k
+    };
+    let percent_d = calculate_sma_from_values(&percent_k, d_period);
+
+    Ok((percent_k, percent_d))
+}
+
+/// 检测价格与指标的常规背离
+///
+/// 枢轴点为左右各 `pivot_window` 根K线内的局部极值。相邻两个枢轴低点间距不超过
+/// `lookback` 时，价格创新低而指标抬高为底背离 ("bullish")；相邻枢轴高点上价格创新高
+/// 而指标走低为顶背离 ("bearish")。枢轴需等待右侧 `pivot_window` 根K线确认，
+/// 因此 `index` 为确认背离的K线位置，不含未来数据。
+///
+/// # 参数
+/// * `prices` - 价格列表
+/// * `indicator_values` - 指标值列表 (如 `calculate_rsi` 或 `calculate_macd` 的输出)
+/// * `lookback` - 两个枢轴点之间的最大间距
+/// * `pivot_window` - 枢轴判定窗口 (默认2)
+///
+/// # 返回
+/// 背离字典列表: index, type ("bullish"/"bearish"), pivots ([前枢轴, 后枢轴])
+#[pyfunction]
+#[pyo3(signature = (prices, indicator_values, lookback, pivot_window=2))]
+fn detect_divergence(
+    prices: Vec<f64>,
+    indicator_values: Vec<Option<f64>>,
+    lookback: usize,
+    pivot_window: usize,
+) -> PyResult<Vec<PyObject>> {
+    if prices.len() != indicator_values.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Input arrays must have the same length"
+        ));
+    }
+
+    let divergences = find_divergences(&prices, &indicator_values, lookback, pivot_window);
+
+    Python::with_gil(|py| {
+        divergences
+            .iter()
+            .map(|d| {
+                let dict = PyDict::new(py);
+                dict.set_item("index", d.index)?;
+                dict.set_item("type", d.kind)?;
+                dict.set_item("pivots", vec![d.first_pivot, d.second_pivot])?;
+                Ok(dict.into())
+            })
+            .collect()
+    })
+}
+
+/// 查找底背离与顶背离，按确认位置排序
+fn find_divergences(
+    prices: &[f64],
+    indicator: &[Option<f64>],
+    lookback: usize,
+    pivot_window: usize,
+) -> Vec<Divergence> {
+    let n = prices.len();
+    let is_pivot = |i: usize, low: bool| -> bool {
+        if i < pivot_window || i + pivot_window >= n || indicator[i].is_none() {
+            return false;
+        }
+        (i - pivot_window..=i + pivot_window).all(|j| {
+            if low { prices[i] <= prices[j] } else { prices[i] >= prices[j] }
+        })
+    };
+
+    let mut divergences = Vec::new();
+
+    for (low, kind) in [(true, "bullish"), (false, "bearish")] {
+        let pivots: Vec<usize> = (0..n).filter(|&i| is_pivot(i, low)).collect();
+
+        for pair in pivots.windows(2) {
+            let (first, second) = (pair[0], pair[1]);
+            if second - first > lookback {
+                continue;
+            }
+
+            let (p1, p2) = (prices[first], prices[second]);
+            let (v1, v2) = (indicator[first].unwrap(), indicator[second].unwrap());
+            let diverges = if low { p2 < p1 && v2 > v1 } else { p2 > p1 && v2 < v1 };
+
+            if diverges {
+                divergences.push(Divergence {
+                    index: second + pivot_window,
+                    kind,
+                    first_pivot: first,
+                    second_pivot: second,
+                });
+            }
+        }
+    }
+
+    divergences.sort_by_key(|d| d.index);
+    divergences
+}
+
+/// 并行计算多个技术指标
+///
+/// # 参数
+/// * `prices` - 价格列表
+/// * `rsi_period` - RSI周期
+/// * `macd_fast` - MACD快线
+/// * `macd_slow` - MACD慢线
+/// * `bb_period` - 布林带周期
+///
+/// # 返回
+/// 指标字典
+#[pyfunction]
+fn calculate_indicators(
+    prices: Vec<f64>,
+    rsi_period: usize,
+    macd_fast: usize,
+    macd_slow: usize,
+    bb_period: usize,
+) -> PyResult<PyObject> {
+    // 并行计算多个指标 (rayon::join 只接受2个闭包，使用嵌套)
+    let (rsi, (macd, bb)) = rayon::join(
+        || calculate_rsi(prices.clone(), rsi_period, "simple"),
+        || rayon::join(
+            || calculate_macd(prices.clone(), macd_fast, macd_slow, 9),
+            || calculate_bollinger_bands(prices.clone(), bb_period, 2.0),
+        ),
+    );
+
+    // 解包结果
+    let rsi = rsi?;
+    let (macd_line, signal_line, histogram) = macd?;
+    let (upper, middle, lower) = bb?;
+
+    Python::with_gil(|py| {
+        let dict = PyDict::new(py);
+
+        // RSI
+        let rsi_list = PyList::new(py, &rsi)?;
+        dict.set_item("rsi", rsi_list)?;
+
+        // MACD
+        let macd_list = PyList::new(py, &macd_line)?;
+        let signal_list = PyList::new(py, &signal_line)?;
+        let hist_list = PyList::new(py, &histogram)?;
+        dict.set_item("macd", macd_list)?;
+        dict.set_item("macd_signal", signal_list)?;
+        dict.set_item("macd_histogram", hist_list)?;
+
+        // Bollinger Bands
+        let upper_list = PyList::new(py, &upper)?;
+        let middle_list = PyList::new(py, &middle)?;
+        let lower_list = PyList::new(py, &lower)?;
+        dict.set_item("bb_upper", upper_list)?;
+        dict.set_item("bb_middle", middle_list)?;
+        dict.set_item("bb_lower", lower_list)?;
+
+        Ok(dict.into())
+    })
+}
+
+/// 识别K线形态
+///
+/// 以实体 `|close - open|`、振幅 `high - low` 及上下影线判断，不考虑前序趋势：
+/// * `doji` (neutral): 实体 <= 振幅 × `doji_body_ratio`
+/// * `hammer` (bullish): 下影线 >= 实体 × `shadow_body_ratio`，上影线 <= 振幅 × `opposite_shadow_ratio`
+/// * `shooting_star` (bearish): 上影线 >= 实体 × `shadow_body_ratio`，下影线 <= 振幅 × `opposite_shadow_ratio`
+/// * `bullish_engulfing` / `bearish_engulfing`: 当前实体与前一根方向相反且完全包住前一根实体
+///
+/// 十字星不再判定为锤子线或流星线；同一根K线可同时出现单根与双根形态
+///
+/// # 参数
+/// * `klines` - K线数据 (timestamp, open, high, low, close, volume)
+/// * `thresholds` - 可选阈值覆盖: doji_body_ratio, shadow_body_ratio, opposite_shadow_ratio
+///
+/// # 返回
+/// 形态字典列表: index, timestamp, pattern, direction
+#[pyfunction]
+#[pyo3(signature = (klines, thresholds=None))]
+fn candlestick_patterns(
+    klines: Vec<Kline>,
+    thresholds: Option<HashMap<String, f64>>,
+) -> PyResult<Vec<PyObject>> {
+    let mut config = PatternThresholds::default();
+    for (key, value) in thresholds.unwrap_or_default() {
+        match key.as_str() {
+            "doji_body_ratio" => config.doji_body_ratio = value,
+            "shadow_body_ratio" => config.shadow_body_ratio = value,
+            "opposite_shadow_ratio" => config.opposite_shadow_ratio = value,
+            _ => {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                    format!("Unknown pattern threshold: {}", key)
+                ));
+            }
+        }
+    }
+
+    let patterns = detect_candlestick_patterns(&klines, &config);
+
+    Python::with_gil(|py| {
+        patterns
+            .iter()
+            .map(|p| {
+                let dict = PyDict::new(py);
+                dict.set_item("index", p.index)?;
+                dict.set_item("timestamp", p.timestamp)?;
+                dict.set_item("pattern", p.pattern)?;
+                dict.set_item("direction", p.direction)?;
+                Ok(dict.into())
+            })
+            .collect()
+    })
+}
+
+/// 按K线顺序识别形态
+fn detect_candlestick_patterns(klines: &[Kline], config: &PatternThresholds) -> Vec<CandlePattern> {
+    let mut patterns = Vec::new();
+
+    for (i, &(timestamp, open, high, low, close, _)) in klines.iter().enumerate() {
+        let range = high - low;
+        let body = (close - open).abs();
+        let upper_shadow = high - open.max(close);
+        let lower_shadow = open.min(close) - low;
+        let mut push = |pattern, direction| {
+            patterns.push(CandlePattern { index: i, timestamp, pattern, direction });
+        };
+
+        if range > 0.0 {
+            if body <= range * config.doji_body_ratio {
+                push("doji", "neutral");
+            } else if lower_shadow >= body * config.shadow_body_ratio
+                && upper_shadow <= range * config.opposite_shadow_ratio
+            {
+                push("hammer", "bullish");
+            } else if upper_shadow >= body * config.shadow_body_ratio
+                && lower_shadow <= range * config.opposite_shadow_ratio
+            {
+                push("shooting_star", "bearish");
+            }
+        }
+
+        if i > 0 {
+            let (_, prev_open, _, _, prev_close, _) = klines[i - 1];
+            if prev_close < prev_open && close > open && open <= prev_close && close >= prev_open {
+                push("bullish_engulfing", "bullish");
+            } else if prev_close > prev_open && close < open && open >= prev_close && close <= prev_open {
+                push("bearish_engulfing", "bearish");
+            }
+        }
+    }
+
+    patterns
+}
+
+/// ATR 追踪止损离场信号
+///
+/// 假设自第一根K线起持仓，跟踪持仓以来的最高收盘价，收盘价跌破
+/// `最高收盘价 - multiplier * ATR` 时输出卖出信号；止损后从下一根K线重新开始跟踪。
+///
+/// # 参数
+/// * `highs` - 最高价列表
+/// * `lows` - 最低价列表
+/// * `closes` - 收盘价列表
+/// * `timestamps` - 时间戳列表
+/// * `atr_period` - ATR周期
+/// * `multiplier` - ATR倍数
+/// * `symbol` - 股票代码 (写入信号字典，默认空)
+///
+/// # 返回
+/// 信号列表 (与 `generate_signals` 相同结构)，`indicator_value` 为止损价
+#[pyfunction]
+#[pyo3(signature = (highs, lows, closes, timestamps, atr_period, multiplier, symbol=String::new()))]
+fn trailing_stop_signals(
+    highs: Vec<f64>,
+    lows: Vec<f64>,
+    closes: Vec<f64>,
+    timestamps: Vec<i64>,
+    atr_period: usize,
+    multiplier: f64,
+    symbol: String,
+) -> PyResult<Vec<PyObject>> {
+    if timestamps.len() != closes.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Input arrays must have the same length"
+        ));
+    }
+
+    let atr = calculate_atr(highs, lows, closes.clone(), atr_period)?;
+
+    let mut signals = Vec::new();
+    let mut highest: Option<f64> = None;
+    for (i, &close) in closes.iter().enumerate() {
+        let peak = highest.map_or(close, |h| h.max(close));
+        highest = Some(peak);
+
+        let atr_value = match atr[i] {
+            Some(v) => v,
+            None => continue,
+        };
+        let stop = peak - multiplier * atr_value;
+
+        if close < stop {
+            signals.push(create_signal(
+                &symbol,
+                i,
+                timestamps[i],
+                Signal::Sell,
+                SignalStrength::Strong,
+                close,
+                stop,
+                format!("Close {:.2} below trailing stop {:.2} ({}x ATR)", close, stop, multiplier),
+                // 跌破止损的深度 (以ATR为单位)
+                unit_clamp((stop - close) / atr_value)
+            ));
+            highest = None;
+        }
+    }
+
+    Python::with_gil(|py| signals.iter().map(|s| s.to_object(py)).collect())
+}
+
+/// 自适应阈值 RSI 信号
+///
+/// 以 RSI 自身在最近 `rank_window` 根K线内的百分位排名代替固定的 30/70 阈值：
+/// 排名低于 `lower_pct` 时买入，高于 `upper_pct` 时卖出。
+///
+/// # 参数
+/// * `prices` - 价格列表
+/// * `rsi_period` - RSI周期
+/// * `rank_window` - 百分位排名窗口 (RSI值个数，至少为2)
+/// * `lower_pct` - 买入排名阈值 (0-100)
+/// * `upper_pct` - 卖出排名阈值 (0-100)
+/// * `timestamps` - 时间戳列表 (可选，默认使用K线下标)
+/// * `symbol` - 股票代码 (写入信号字典，默认空)
+///
+/// # 返回
+/// 信号列表 (与 `generate_signals` 相同结构)，`indicator_value` 为 RSI 值
+#[pyfunction]
+#[pyo3(signature = (prices, rsi_period, rank_window, lower_pct, upper_pct, timestamps=None, symbol=String::new()))]
+#[allow(clippy::too_many_arguments)]
+fn adaptive_rsi_signals(
+    prices: Vec<f64>,
+    rsi_period: usize,
+    rank_window: usize,
+    lower_pct: f64,
+    upper_pct: f64,
+    timestamps: Option<Vec<i64>>,
+    symbol: String,
+) -> PyResult<Vec<PyObject>> {
+    if rank_window < 2 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "rank_window must be at least 2"
+        ));
+    }
+    if !(0.0..=100.0).contains(&lower_pct) || !(0.0..=100.0).contains(&upper_pct) || lower_pct > upper_pct {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Percent thresholds must satisfy 0 <= lower_pct <= upper_pct <= 100"
+        ));
+    }
+    let timestamps = timestamps.unwrap_or_else(|| (0..prices.len() as i64).collect());
+    if timestamps.len() != prices.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Prices and timestamps must have the same length"
+        ));
+    }
+
+    let rsi = calculate_rsi(prices.clone(), rsi_period, "simple")?;
+    let ranks = rolling_percent_rank(&rsi, rank_window);
+
+    let mut signals = Vec::new();
+    for (i, (rank, value)) in ranks.iter().zip(&rsi).enumerate() {
+        let (rank, value) = match (rank, value) {
+            (Some(rank), Some(value)) => (*rank, *value),
+            _ => continue,
+        };
+        let (signal, reason, confidence) = if rank < lower_pct {
+            (Signal::Buy, format!("RSI {:.1} ranks {:.0}% in its {}-bar range", value, rank, rank_window),
+             unit_clamp((lower_pct - rank) / lower_pct))
+        } else if rank > upper_pct {
+            (Signal::Sell, format!("RSI {:.1} ranks {:.0}% in its {}-bar range", value, rank, rank_window),
+             unit_clamp((rank - upper_pct) / (100.0 - upper_pct)))
+        } else {
+            continue;
+        };
+        signals.push(create_signal(
+            &symbol,
+            i,
+            timestamps[i],
+            signal,
+            SignalStrength::Moderate,
+            prices[i],
+            value,
+            reason,
+            confidence
+        ));
+    }
+
+    Python::with_gil(|py| signals.iter().map(|s| s.to_object(py)).collect())
+}
+
+/// 滚动百分位排名 (0-100)：窗口内严格小于当前值的其余值个数占 `window - 1` 的比例
+///
+/// 窗口内含 None 时为 None
+fn rolling_percent_rank(values: &[Option<f64>], window: usize) -> Vec<Option<f64>> {
+    (0..values.len())
+        .map(|i| {
+            if i + 1 < window {
+                return None;
+            }
+            let current = values[i]?;
+            let mut below = 0usize;
+            for value in &values[i + 1 - window..=i] {
+                if (*value)? < current {
+                    below += 1;
+                }
+            }
+            Some(below as f64 / (window - 1) as f64 * 100.0)
+        })
+        .collect()
+}
+
+/// 市场默认周期约定
+struct MarketDefaults {
+    /// ATR周期
+    atr_period: usize,
+    /// 波动率与日内振幅的统计窗口 (K线数)
+    volatility_window: usize,
+    /// 每年交易日数
+    trading_days: f64,
+}
+
+/// A股每年交易日数
+const A_SHARE_TRADING_DAYS: f64 = 242.0;
+/// 港股每年交易日数
+const HK_TRADING_DAYS: f64 = 247.0;
+/// 美股每年交易日数
+const US_TRADING_DAYS: f64 = 252.0;
+
+/// 按市场类型 ("A股", "港股", "美股") 取默认周期约定
+fn market_defaults(market_type: &str) -> PyResult<MarketDefaults> {
+    let trading_days = match market_type {
+        "A股" => A_SHARE_TRADING_DAYS,
+        "港股" => HK_TRADING_DAYS,
+        "美股" => US_TRADING_DAYS,
+        _ => {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                format!("Unsupported market type: {}", market_type)
+            ));
+        }
+    };
+
+    Ok(MarketDefaults { atr_period: 14, volatility_window: 20, trading_days })
+}
+
+/// 个股波动率概览
+///
+/// 按市场默认约定 (ATR周期14、统计窗口20根K线) 计算：
+/// * `atr` - 最新ATR
+/// * `atr_pct` - 最新ATR占最新收盘价的百分比
+/// * `annualized_volatility` - 最近窗口对数收益率样本标准差的年化值 (%)
+/// * `daily_range_pct` - 最近窗口 (最高价 - 最低价) / 收盘价 的平均值 (%)
+///
+/// # 参数
+/// * `klines` - K线数据 (timestamp, open, high, low, close, volume)
+/// * `market_type` - 市场类型 ("A股", "港股", "美股")
+/// * `periods_per_year` - 年化波动率使用的每年周期数，默认 None 即按市场每年交易日数
+///   (A股242、港股247、美股252)；周线、月线数据可传 52、12
+///
+/// # 返回
+/// 指标名到数值的字典；K线数不足 `统计窗口 + 1` 根、收盘价非正或 `periods_per_year` 非正时报错
+#[pyfunction]
+#[pyo3(signature = (klines, market_type, periods_per_year=None))]
+fn volatility_report(
+    klines: Vec<Kline>,
+    market_type: &str,
+    periods_per_year: Option<f64>,
+) -> PyResult<HashMap<String, f64>> {
+    let defaults = market_defaults(market_type)?;
+    let periods_per_year = periods_per_year.unwrap_or(defaults.trading_days);
+    if periods_per_year <= 0.0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "periods_per_year must be positive"
+        ));
+    }
+    let window = defaults.volatility_window;
+    if klines.len() < window.max(defaults.atr_period) + 1 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            format!("volatility_report requires at least {} klines", window.max(defaults.atr_period) + 1)
+        ));
+    }
+    if klines.iter().any(|k| k.4 <= 0.0) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Close prices must be positive"
+        ));
+    }
+
+    let highs: Vec<f64> = klines.iter().map(|k| k.2).collect();
+    let lows: Vec<f64> = klines.iter().map(|k| k.3).collect();
+    let closes: Vec<f64> = klines.iter().map(|k| k.4).collect();
+
+    let atr = calculate_atr(highs, lows, closes.clone(), defaults.atr_period)?
+        .last()
+        .copied()
+        .flatten()
+        .unwrap_or(0.0);
+    let last_close = closes[closes.len() - 1];
+
+    let recent = &klines[klines.len() - window..];
+    let log_returns: Vec<f64> = closes[closes.len() - window - 1..]
+        .windows(2)
+        .map(|w| (w[1] / w[0]).ln())
+        .collect();
+    let mean = log_returns.iter().sum::<f64>() / window as f64;
+    let variance = log_returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (window - 1) as f64;
+    let daily_range = recent.iter().map(|k| (k.2 - k.3) / k.4).sum::<f64>() / window as f64;
+
+    let mut report = HashMap::new();
+    report.insert("atr".to_string(), atr);
+    report.insert("atr_pct".to_string(), atr / last_close * 100.0);
+    report.insert(
+        "annualized_volatility".to_string(),
+        variance.sqrt() * periods_per_year.sqrt() * 100.0,
+    );
+    report.insert("daily_range_pct".to_string(), daily_range * 100.0);
+    Ok(report)
+}
+
+/// 生成交易信号
+///
+/// # 参数
+/// * `symbol` - 股票代码
+/// * `prices` - 价格列表
+/// * `strategy` - 策略类型 ("rsi", "macd", "bb", "combined", "mean_reversion")
+/// * `params` - 策略参数 (JSON字符串)
+/// * `include_hold` - 为 true 时在每个可评估的位置输出 Hold 信号及中性原因 (默认false)
+/// * `min_bars_between` - 同方向信号的最小间隔K线数 (默认0不过滤)。距上一个同方向信号
+///   (含被抑制的) 之间不足该数量的K线时抑制新信号，买卖方向分别计数，Hold 信号不受影响。
+///   `min_bars_between=1` 会把连续超卖的一段K线合并为一个入场信号
+///
+/// # 返回
+/// 信号列表
+#[pyfunction]
+#[pyo3(signature = (symbol, prices, timestamps, strategy, params, include_hold=false, min_bars_between=0))]
+fn generate_signals(
+    symbol: String,
+    prices: Vec<f64>,
+    timestamps: Vec<i64>,
+    strategy: &str,
+    params: &str,
+    include_hold: bool,
+    min_bars_between: usize,
+) -> PyResult<Vec<PyObject>> {
+    let params_map = parse_params(params)?;
+    let signals = compute_signals(&symbol, &prices, &timestamps, strategy, &params_map, include_hold)?;
+    let signals = space_out_signals(signals, min_bars_between);
+
+    Python::with_gil(|py| signals.iter().map(|s| s.to_object(py)).collect())
+}
+
+/// 批量生成多只股票的交易信号
+///
+/// 各股票的信号计算在释放 GIL 后由 rayon 并行完成，最后统一转换为 Python 字典
+///
+/// # 参数
+/// * `symbols` - 股票代码列表
+/// * `prices_list` - 与 `symbols` 一一对应的价格列表
+/// * `timestamps_list` - 与 `symbols` 一一对应的时间戳列表
+/// * `strategy` - 策略类型 (同 `generate_signals`)
+/// * `params` - 策略参数 (JSON字符串)，所有股票共用
+/// * `include_hold` - 是否输出 Hold 信号 (默认false)
+/// * `min_bars_between` - 同方向信号的最小间隔K线数 (同 `generate_signals`，默认0)
+///
+/// # 返回
+/// 股票代码 -> 信号列表
+#[pyfunction]
+#[pyo3(signature = (symbols, prices_list, timestamps_list, strategy, params, include_hold=false, min_bars_between=0))]
+#[allow(clippy::too_many_arguments)]
+fn generate_signals_batch(
+    py: Python<'_>,
+    symbols: Vec<String>,
+    prices_list: Vec<Vec<f64>>,
+    timestamps_list: Vec<Vec<i64>>,
+    strategy: &str,
+    params: &str,
+    include_hold: bool,
+    min_bars_between: usize,
+) -> PyResult<HashMap<String, Vec<PyObject>>> {
+    if symbols.len() != prices_list.len() || symbols.len() != timestamps_list.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            format!(
+                "symbols, prices_list and timestamps_list must have the same length (got {}, {}, {})",
+                symbols.len(), prices_list.len(), timestamps_list.len()
+            )
+        ));
+    }
+
+    let params_map = parse_params(params)?;
+
+    let computed: Vec<(String, Vec<SignalRecord>)> = py.allow_threads(|| {
+        symbols
+            .par_iter()
+            .zip(prices_list.par_iter())
+            .zip(timestamps_list.par_iter())
+            .map(|((symbol, prices), timestamps)| {
+                compute_signals(symbol, prices, timestamps, strategy, &params_map, include_hold)
+                    .map(|signals| (symbol.clone(), space_out_signals(signals, min_bars_between)))
+            })
+            .collect::<PyResult<_>>()
+    })?;
+
+    computed
+        .into_iter()
+        .map(|(symbol, signals)| {
+            let objects = signals.iter().map(|s| s.to_object(py)).collect::<PyResult<_>>()?;
+            Ok((symbol, objects))
+        })
+        .collect()
+}
+
+/// 信号命中率
+///
+/// 对每个买入/卖出信号，按时间戳定位价格序列中的位置，比较 `horizon_bars` 根之后的价格：
+/// 买入后上涨、卖出后下跌记为命中。Hold 信号、时间戳不在序列中或之后不足
+/// `horizon_bars` 根K线的信号不参与统计。
+///
+/// # 参数
+/// * `signals` - 信号字典列表 (需包含 timestamp 与 signal)
+/// * `prices` - 价格列表
+/// * `timestamps` - 与价格对应的时间戳列表
+/// * `horizon_bars` - 观察周期 (K线数)
+///
+/// # 返回
+/// 命中比例 (0..1)，没有可评估信号时为0
+#[pyfunction]
+fn signal_hit_rate(
+    py: Python<'_>,
+    signals: Vec<PyObject>,
+    prices: Vec<f64>,
+    timestamps: Vec<i64>,
+    horizon_bars: usize,
+) -> PyResult<f64> {
+    if prices.len() != timestamps.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Input arrays must have the same length"
+        ));
+    }
+
+    let index_by_timestamp: HashMap<i64, usize> = timestamps.iter()
+        .enumerate()
+        .map(|(i, &ts)| (ts, i))
+        .collect();
+
+    let mut events = Vec::with_capacity(signals.len());
+    for obj in &signals {
+        let dict = obj.downcast_bound::<PyDict>(py)?;
+        let timestamp: i64 = required_item(dict, "timestamp")?.extract()?;
+        let signal_str: String = required_item(dict, "signal")?.extract()?;
+        let signal = Signal::parse(&signal_str).ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                format!("Invalid signal: {}", signal_str)
+            )
+        })?;
+        if let Some(&index) = index_by_timestamp.get(&timestamp) {
+            events.push((index, signal));
+        }
+    }
+
+    Ok(hit_rate(&events, &prices, horizon_bars))
+}
+
+/// 合并相邻的同方向重复信号
+///
+/// 按 `index` 排序后，同方向且与簇内上一个信号相距不超过 `merge_window_bars` 根K线的信号
+/// 归为一簇，只保留簇内最强的一个 (先比强度，再比置信度，仍相同时保留最早的)。
+/// 反方向信号会结束当前簇；Hold 信号原样保留且不影响合并。
+///
+/// # 参数
+/// * `signals` - 信号字典列表 (需包含 index、signal 与 strength，confidence 可选)
+/// * `merge_window_bars` - 合并窗口 (K线数)
+///
+/// # 返回
+/// 去重后的信号字典，保持输入中的相对顺序
+#[pyfunction]
+fn dedupe_signals(
+    py: Python<'_>,
+    signals: Vec<PyObject>,
+    merge_window_bars: usize,
+) -> PyResult<Vec<PyObject>> {
+    let mut events = Vec::with_capacity(signals.len());
+    for obj in &signals {
+        let dict = obj.downcast_bound::<PyDict>(py)?;
+        let index: usize = required_item(dict, "index")?.extract()?;
+        let signal_str: String = required_item(dict, "signal")?.extract()?;
+        let signal = Signal::parse(&signal_str).ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                format!("Invalid signal: {}", signal_str)
+            )
+        })?;
+        let strength_str: String = required_item(dict, "strength")?.extract()?;
+        let strength = SignalStrength::parse(&strength_str).ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                format!("Invalid strength: {}", strength_str)
+            )
+        })?;
+        let confidence = match dict.get_item("confidence")? {
+            Some(value) if !value.is_none() => value.extract()?,
+            _ => 0.0,
+        };
+        events.push((index, signal, strength as u8, confidence));
+    }
+
+    let keep = dedupe_events(&events, merge_window_bars);
+    Ok(signals
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(obj, kept)| kept.then_some(obj))
+        .collect())
+}
+
+/// 对 (K线序号, 信号, 强度等级, 置信度) 列表做同方向合并，返回每个信号是否保留
+fn dedupe_events(events: &[(usize, Signal, u8, f64)], merge_window_bars: usize) -> Vec<bool> {
+    let mut order: Vec<usize> = (0..events.len()).collect();
+    order.sort_by_key(|&i| events[i].0);
+
+    let mut keep = vec![true; events.len()];
+    // 当前簇: (方向, 簇内最后一个信号的K线序号, 簇内最强信号的位置)
+    let mut cluster: Option<(Signal, usize, usize)> = None;
+    for i in order {
+        let (index, signal, strength, confidence) = events[i];
+        if signal == Signal::Hold {
+            continue;
+        }
+
+        match cluster {
+            Some((direction, last, best))
+                if direction == signal && index - last <= merge_window_bars =>
+            {
+                let (_, _, best_strength, best_confidence) = events[best];
+                let stronger = (strength, confidence) > (best_strength, best_confidence);
+                if stronger {
+                    keep[best] = false;
+                } else {
+                    keep[i] = false;
+                }
+                cluster = Some((direction, index, if stronger { i } else { best }));
+            }
+            _ => cluster = Some((signal, index, i)),
+        }
+    }
+
+    keep
+}
+
+/// 计算 (K线序号, 信号) 列表的命中率
+fn hit_rate(events: &[(usize, Signal)], prices: &[f64], horizon_bars: usize) -> f64 {
+    let mut evaluated = 0usize;
+    let mut hits = 0usize;
+    for &(index, signal) in events {
+        let future = match prices.get(index + horizon_bars) {
+            Some(&future) if horizon_bars > 0 => future,
+            _ => continue,
+        };
+        let hit = match signal {
+            Signal::Buy => future > prices[index],
+            Signal::Sell => future < prices[index],
+            Signal::Hold => continue,
+        };
+        evaluated += 1;
+        hits += hit as usize;
+    }
+
+    if evaluated == 0 {
+        0.0
+    } else {
+        hits as f64 / evaluated as f64
+    }
+}
+
+/// 抑制同方向的密集信号
+///
+/// 买、卖方向分别记录上一次出现的K线序号 (含被抑制的信号)，
+/// 两者之间的K线数少于 `min_bars_between` 时丢弃新信号；Hold 信号原样保留
+fn space_out_signals(signals: Vec<SignalRecord>, min_bars_between: usize) -> Vec<SignalRecord> {
+    if min_bars_between == 0 {
+        return signals;
+    }
+
+    let mut last_buy: Option<usize> = None;
+    let mut last_sell: Option<usize> = None;
+    signals
+        .into_iter()
+        .filter(|record| {
+            let last = match record.signal {
+                Signal::Buy => &mut last_buy,
+                Signal::Sell => &mut last_sell,
+                Signal::Hold => return true,
+            };
+            // 两个信号之间的K线数为 index - prev - 1
+            let keep = last.is_none_or(|prev| record.index - prev > min_bars_between);
+            *last = Some(record.index);
+            keep
+        })
+        .collect()
+}
+
+/// 计算单只股票的信号 (不持有 GIL)
+fn compute_signals(
+    symbol: &str,
+    prices: &[f64],
+    timestamps: &[i64],
+    strategy: &str,
+    params_map: &HashMap<String, f64>,
+    include_hold: bool,
+) -> PyResult<Vec<SignalRecord>> {
+    if prices.len() != timestamps.len() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            format!("Prices and timestamps for {} must have the same length", symbol)
+        ));
+    }
+
+    let signals = match strategy {
+        "rsi" => {
             let period = *params_map.get("period").unwrap_or(&14.0) as usize;
             let oversold = *params_map.get("oversold").unwrap_or(&30.0);
             let overbought = *params_map.get("overbought").unwrap_or(&70.0);
 
-            let rsi_values = calculate_rsi(prices.clone(), period)?;
+            let rsi_values = calculate_rsi(prices.to_vec(), period, "simple")?;
 
             rsi_values.iter().enumerate()
                 .filter_map(|(i, rsi)| {
                     rsi.and_then(|r| {
-                        let (signal, strength, reason) = if r < oversold {
-                            (Signal::Buy, SignalStrength::Strong, format!("RSI oversold ({:.1})", r))
+                        // 置信度：超出阈值的幅度占阈值到极值距离的比例；
+                        // Hold 为距最近阈值的距离占半个中性带宽的比例
+                        let (signal, strength, reason, confidence) = if r < oversold {
+                            (Signal::Buy, SignalStrength::Strong, format!("RSI oversold ({:.1})", r),
+                             unit_clamp((oversold - r) / oversold))
                         } else if r > overbought {
-                            (Signal::Sell, SignalStrength::Strong, format!("RSI overbought ({:.1})", r))
+                            (Signal::Sell, SignalStrength::Strong, format!("RSI overbought ({:.1})", r),
+                             unit_clamp((r - overbought) / (100.0 - overbought)))
+                        } else if include_hold {
+                            (Signal::Hold, SignalStrength::Weak, format!("RSI in neutral band ({:.1})", r),
+                             unit_clamp((r - oversold).min(overbought - r) / ((overbought - oversold) / 2.0)))
                         } else {
                             return None;
                         };
 
                         Some(create_signal(
-                            symbol.clone(),
+                            symbol,
+                            i,
                             timestamps[i],
                             signal,
                             strength,
                             prices[i],
                             r,
-                            reason
+                            reason,
+                            confidence
                         ))
                     })
                 })
@@ -347,8 +1916,9 @@ fn generate_signals(
         "macd" => {
             let fast = *params_map.get("fast").unwrap_or(&12.0) as usize;
             let slow = *params_map.get("slow").unwrap_or(&26.0) as usize;
+            let signal_period = *params_map.get("signal").unwrap_or(&9.0) as usize;
 
-            let (macd_line, signal_line, _) = calculate_macd(prices.clone(), fast, slow, 9)?;
+            let (macd_line, signal_line, _) = calculate_macd(prices.to_vec(), fast, slow, signal_period)?;
 
             macd_line.iter().enumerate()
                 .filter_map(|(i, macd_val)| {
@@ -362,29 +1932,34 @@ fn generate_signals(
                                 Some(v) => *v,
                                 None => return None,
                             };
-                            let prev_signal = signal_line.get(i - 1).and_then(|v| *v);
-                            let prev_macd = macd_line.get(i - 1).and_then(|v| *v);
+                            let prev_signal = i.checked_sub(1).and_then(|p| signal_line[p]);
+                            let prev_macd = i.checked_sub(1).and_then(|p| macd_line[p]);
 
                             if let (Some(prev_sig), Some(prev_mac)) = (prev_signal, prev_macd) {
                                 // prev_sig and prev_mac are both f64 after Some() pattern
                                 let prev_sig_val = prev_sig;
                                 let prev_mac_val = prev_mac;
-                                let (signal, strength) = if m_val > s_val && prev_mac_val <= prev_sig_val {
-                                    (Signal::Buy, SignalStrength::Moderate)
+                                let (signal, strength, reason) = if m_val > s_val && prev_mac_val <= prev_sig_val {
+                                    (Signal::Buy, SignalStrength::Moderate, "MACD crossover")
                                 } else if m_val < s_val && prev_mac_val >= prev_sig_val {
-                                    (Signal::Sell, SignalStrength::Moderate)
+                                    (Signal::Sell, SignalStrength::Moderate, "MACD crossover")
+                                } else if include_hold {
+                                    (Signal::Hold, SignalStrength::Weak, "MACD no crossover")
                                 } else {
                                     return None;
                                 };
 
                                 Some(create_signal(
-                                    symbol.clone(),
+                                    symbol,
+                                    i,
                                     timestamps[i],
                                     signal,
                                     strength,
                                     prices[i],
                                     m_val - s_val,
-                                    "MACD crossover".to_string()
+                                    reason.to_string(),
+                                    // 柱状值达到价格的1%视为满置信度
+                                    unit_clamp((m_val - s_val).abs() / (prices[i].abs() * 0.01))
                                 ))
                             } else {
                                 None
@@ -398,10 +1973,10 @@ fn generate_signals(
         "combined" => {
             // 综合多个指标生成信号
             let rsi_period = *params_map.get("rsi_period").unwrap_or(&14.0) as usize;
-            let rsi_values = calculate_rsi(prices.clone(), rsi_period)?;
+            let rsi_values = calculate_rsi(prices.to_vec(), rsi_period, "simple")?;
 
             let bb_period = *params_map.get("bb_period").unwrap_or(&20.0) as usize;
-            let (_, bb_middle, bb_lower) = calculate_bollinger_bands(prices.clone(), bb_period, 2.0)?;
+            let (bb_upper, _, bb_lower) = calculate_bollinger_bands(prices.to_vec(), bb_period, 2.0)?;
 
             rsi_values.iter().enumerate()
                 .filter_map(|(i, rsi)| {
@@ -412,9 +1987,9 @@ fn generate_signals(
                     };
 
                     // RSI超卖且价格触及下轨 -> 强买入
-                    if let (Some(middle_opt), Some(lower_opt)) = (bb_middle.get(i), bb_lower.get(i)) {
+                    if let (Some(upper_opt), Some(lower_opt)) = (bb_upper.get(i), bb_lower.get(i)) {
                         // Extract f64 values from &Option<f64>
-                        let middle = match middle_opt {
+                        let upper = match upper_opt {
                             Some(v) => *v,
                             None => return None,
                         };
@@ -424,28 +1999,51 @@ fn generate_signals(
                         };
 
                         let price = prices[i];
+                        let band_width = upper - lower;
 
                         if rsi_val < 30.0 && price <= lower {
                             return Some(create_signal(
-                                symbol.clone(),
+                                symbol,
+                                i,
                                 timestamps[i],
                                 Signal::Buy,
                                 SignalStrength::Strong,
                                 price,
                                 rsi_val,
-                                format!("RSI oversold ({:.1}) & price at BB lower", rsi_val)
+                                format!("RSI oversold ({:.1}) & price at BB lower", rsi_val),
+                                // RSI 超出程度与价格穿出布林带幅度 (以带宽为单位) 的均值
+                                (unit_clamp((30.0 - rsi_val) / 30.0)
+                                    + unit_clamp((lower - price) / band_width)) / 2.0
                             ));
                         }
 
-                        if rsi_val > 70.0 && price >= middle + (middle - lower) {
+                        // RSI超买且价格触及上轨 -> 强卖出
+                        if rsi_val > 70.0 && price >= upper {
                             return Some(create_signal(
-                                symbol.clone(),
+                                symbol,
+                                i,
                                 timestamps[i],
                                 Signal::Sell,
                                 SignalStrength::Strong,
                                 price,
                                 rsi_val,
-                                format!("RSI overbought ({:.1}) & price at BB upper", rsi_val)
+                                format!("RSI overbought ({:.1}) & price at BB upper", rsi_val),
+                                (unit_clamp((rsi_val - 70.0) / 30.0)
+                                    + unit_clamp((price - upper) / band_width)) / 2.0
+                            ));
+                        }
+
+                        if include_hold {
+                            return Some(create_signal(
+                                symbol,
+                                i,
+                                timestamps[i],
+                                Signal::Hold,
+                                SignalStrength::Weak,
+                                price,
+                                rsi_val,
+                                format!("RSI ({:.1}) and price inside BB neutral", rsi_val),
+                                0.0
                             ));
                         }
                     }
@@ -454,6 +2052,54 @@ fn generate_signals(
                 })
                 .collect()
         }
+        "mean_reversion" => {
+            // 均值回归：价格低于滚动均值 z 个标准差时买入，回到均值上方时卖出
+            let period = *params_map.get("period").unwrap_or(&20.0) as usize;
+            let z_threshold = *params_map.get("z").unwrap_or(&2.0);
+
+            let stats = rolling_mean_std(prices, period);
+            let mut holding = false;
+            let mut signals = Vec::new();
+
+            for (i, stat) in stats.iter().enumerate() {
+                let (avg, std) = match stat {
+                    Some((avg, std)) if *std > 0.0 => (*avg, *std),
+                    _ => continue,
+                };
+                let z = (prices[i] - avg) / std;
+
+                let (signal, strength, reason, confidence) = if !holding && z < -z_threshold {
+                    holding = true;
+                    (Signal::Buy, SignalStrength::Moderate,
+                     format!("Price below rolling mean (z={:.2})", z),
+                     unit_clamp((-z - z_threshold) / z_threshold))
+                } else if holding && z >= 0.0 {
+                    holding = false;
+                    (Signal::Sell, SignalStrength::Moderate,
+                     format!("Price reverted above rolling mean (z={:.2})", z),
+                     unit_clamp(z / z_threshold))
+                } else if include_hold {
+                    (Signal::Hold, SignalStrength::Weak,
+                     format!("Mean reversion neutral (z={:.2})", z), 0.0)
+                } else {
+                    continue;
+                };
+
+                signals.push(create_signal(
+                    symbol,
+                    i,
+                    timestamps[i],
+                    signal,
+                    strength,
+                    prices[i],
+                    z,
+                    reason,
+                    confidence
+                ));
+            }
+
+            signals
+        }
         _ => {
             return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
                 format!("Unknown strategy: {}", strategy)
@@ -461,12 +2107,36 @@ fn generate_signals(
         }
     };
 
-    // signals is already Vec<PyObject>, return directly
     Ok(signals)
 }
 
-/// 创建信号对象
-fn create_signal(
+/// 解析策略参数 JSON，空字符串视为使用默认参数
+fn parse_params(params: &str) -> PyResult<HashMap<String, f64>> {
+    if params.trim().is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    serde_json::from_str(params).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            format!("Invalid params JSON: {}", e)
+        )
+    })
+}
+
+/// 将置信度限制在 0..1，非有限值视为0
+fn unit_clamp(value: f64) -> f64 {
+    if value.is_finite() {
+        value.clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+/// 信号记录 (纯 Rust 数据，输出前再转换为 Python 字典)
+#[derive(Debug, Clone)]
+struct SignalRecord {
+    /// K线序号
+    index: usize,
     symbol: String,
     timestamp: i64,
     signal: Signal,
@@ -474,31 +2144,51 @@ fn create_signal(
     price: f64,
     indicator_value: f64,
     reason: String,
-) -> PyObject {
-    Python::with_gil(|py| {
+    confidence: f64,
+}
+
+impl SignalRecord {
+    fn to_object(&self, py: Python<'_>) -> PyResult<PyObject> {
         let dict = PyDict::new(py);
-        dict.set_item("symbol", symbol).unwrap();
-        dict.set_item("timestamp", timestamp).unwrap();
+        dict.set_item("symbol", &self.symbol)?;
+        dict.set_item("index", self.index)?;
+        dict.set_item("timestamp", self.timestamp)?;
 
-        let signal_str = match signal {
-            Signal::Buy => "buy",
-            Signal::Sell => "sell",
-            Signal::Hold => "hold",
-        };
-        dict.set_item("signal", signal_str).unwrap();
+        dict.set_item("signal", self.signal.as_str())?;
+        dict.set_item("strength", self.strength.as_str())?;
+        dict.set_item("price", self.price)?;
+        dict.set_item("indicator_value", self.indicator_value)?;
+        dict.set_item("reason", &self.reason)?;
+        dict.set_item("confidence", self.confidence)?;
 
-        let strength_str = match strength {
-            SignalStrength::Weak => "weak",
-            SignalStrength::Moderate => "moderate",
-            SignalStrength::Strong => "strong",
-        };
-        dict.set_item("strength", strength_str).unwrap();
-        dict.set_item("price", price).unwrap();
-        dict.set_item("indicator_value", indicator_value).unwrap();
-        dict.set_item("reason", reason).unwrap();
+        Ok(dict.into())
+    }
+}
 
-        dict.into()
-    })
+/// 创建信号记录
+#[allow(clippy::too_many_arguments)]
+fn create_signal(
+    symbol: &str,
+    index: usize,
+    timestamp: i64,
+    signal: Signal,
+    strength: SignalStrength,
+    price: f64,
+    indicator_value: f64,
+    reason: String,
+    confidence: f64,
+) -> SignalRecord {
+    SignalRecord {
+        index,
+        symbol: symbol.to_string(),
+        timestamp,
+        signal,
+        strength,
+        price,
+        indicator_value,
+        reason,
+        confidence,
+    }
 }
 
 /// 辅助函数：计算EMA
@@ -520,20 +2210,64 @@ fn calculate_ema(prices: &[f64], period: usize) -> Vec<Option<f64>> {
     result
 }
 
+/// 辅助函数：滚动窗口内的 (最高价, 最低价)，前 `period - 1` 个值为 None
+fn rolling_high_low(highs: &[f64], lows: &[f64], period: usize) -> Vec<Option<(f64, f64)>> {
+    (0..highs.len())
+        .map(|i| {
+            if period == 0 || i + 1 < period {
+                return None;
+            }
+            let start = i + 1 - period;
+            let highest = highs[start..=i].iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            let lowest = lows[start..=i].iter().cloned().fold(f64::INFINITY, f64::min);
+            Some((highest, lowest))
+        })
+        .collect()
+}
+
+/// 辅助函数：从Option值计算SMA，窗口内存在 None 时结果为 None
+fn calculate_sma_from_values(values: &[Option<f64>], period: usize) -> Vec<Option<f64>> {
+    (0..values.len())
+        .map(|i| {
+            if period == 0 || i + 1 < period {
+                return None;
+            }
+            values[i + 1 - period..=i]
+                .iter()
+                .try_fold(0.0, |sum, v| v.map(|x| sum + x))
+                .map(|sum| sum / period as f64)
+        })
+        .collect()
+}
+
 /// 辅助函数：从Option值计算EMA
+///
+/// 输入连续 `period` 个有效值后才开始输出，以这 `period` 个值的简单均值作为初始EMA；
+/// 此前及遇到 None 时输出 None，遇到 None 后重新累计
 fn calculate_ema_from_values(values: &[Option<f64>], period: usize) -> Vec<Option<f64>> {
     let multiplier = 2.0 / (period as f64 + 1.0);
     let mut result = Vec::with_capacity(values.len());
-
-    for i in 0..values.len() {
-        if i == 0 {
-            result.push(values[0]);
-        } else if let (Some(curr), Some(prev_ema)) = (values[i], result[i - 1]) {
-            let ema = (curr - prev_ema) * multiplier + prev_ema;
-            result.push(Some(ema));
-        } else {
-            result.push(None);
-        }
+    let mut run: Vec<f64> = Vec::with_capacity(period);
+    let mut prev_ema: Option<f64> = None;
+
+    for value in values {
+        let ema = match (value, prev_ema) {
+            (Some(curr), Some(prev)) => Some((curr - prev) * multiplier + prev),
+            (Some(curr), None) => {
+                run.push(*curr);
+                if period > 0 && run.len() == period {
+                    Some(run.iter().sum::<f64>() / period as f64)
+                } else {
+                    None
+                }
+            }
+            (None, _) => {
+                run.clear();
+                None
+            }
+        };
+        prev_ema = ema;
+        result.push(ema);
     }
 
     result
@@ -546,7 +2280,711 @@ fn tacn_strategy(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(calculate_macd, m)?)?;
     m.add_function(wrap_pyfunction!(calculate_bollinger_bands, m)?)?;
     m.add_function(wrap_pyfunction!(calculate_atr, m)?)?;
+    m.add_function(wrap_pyfunction!(calculate_donchian, m)?)?;
+    m.add_function(wrap_pyfunction!(calculate_keltner, m)?)?;
+    m.add_function(wrap_pyfunction!(calculate_stochastic, m)?)?;
+    m.add_function(wrap_pyfunction!(calculate_adx, m)?)?;
+    m.add_function(wrap_pyfunction!(calculate_williams_r, m)?)?;
+    m.add_function(wrap_pyfunction!(calculate_obv, m)?)?;
+    m.add_function(wrap_pyfunction!(calculate_cci, m)?)?;
+    m.add_function(wrap_pyfunction!(calculate_psar, m)?)?;
+    m.add_function(wrap_pyfunction!(calculate_roc, m)?)?;
+    m.add_function(wrap_pyfunction!(calculate_momentum, m)?)?;
+    m.add_function(wrap_pyfunction!(efficiency_ratio, m)?)?;
+    m.add_function(wrap_pyfunction!(kama, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_divergence, m)?)?;
+    m.add_function(wrap_pyfunction!(candlestick_patterns, m)?)?;
     m.add_function(wrap_pyfunction!(calculate_indicators, m)?)?;
     m.add_function(wrap_pyfunction!(generate_signals, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_signals_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(trailing_stop_signals, m)?)?;
+    m.add_function(wrap_pyfunction!(adaptive_rsi_signals, m)?)?;
+    m.add_function(wrap_pyfunction!(signal_hit_rate, m)?)?;
+    m.add_function(wrap_pyfunction!(dedupe_signals, m)?)?;
+    m.add_function(wrap_pyfunction!(volatility_report, m)?)?;
+    m.add_class::<Signal>()?;
+    m.add_class::<SignalStrength>()?;
+    m.add_class::<StrategySignal>()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strategy_signal_dict_round_trip() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let mut indicators = HashMap::new();
+            indicators.insert("rsi".to_string(), 25.5);
+            let original = StrategySignal {
+                symbol: "600519".to_string(),
+                timestamp: 1_700_000_000_000,
+                signal: Signal::Buy,
+                strength: SignalStrength::Strong,
+                price: 1688.0,
+                indicators,
+                reason: "RSI oversold (25.5)".to_string(),
+                confidence: 0.15,
+            };
+
+            let dict = original.to_dict(py).unwrap();
+            let restored = StrategySignal::from_dict(&dict).unwrap();
+
+            assert_eq!(restored.symbol, original.symbol);
+            assert_eq!(restored.timestamp, original.timestamp);
+            assert_eq!(restored.signal, original.signal);
+            assert_eq!(restored.strength, original.strength);
+            assert_eq!(restored.price, original.price);
+            assert_eq!(restored.indicators, original.indicators);
+            assert_eq!(restored.reason, original.reason);
+            assert_eq!(restored.confidence, original.confidence);
+
+            dict.set_item("signal", "long").unwrap();
+            assert!(StrategySignal::from_dict(&dict).is_err());
+
+            dict.set_item("signal", "sell").unwrap();
+            dict.del_item("price").unwrap();
+            assert!(StrategySignal::from_dict(&dict).is_err());
+        });
+    }
+
+    #[test]
+    fn test_calculate_rsi_wilder_vs_simple() {
+        // 价格变化: +1, -1, +2, -1
+        let prices = vec![1.0, 2.0, 1.0, 3.0, 2.0];
+
+        let wilder = calculate_rsi(prices.clone(), 2, "wilder").unwrap();
+        assert_eq!(wilder.len(), 5);
+        assert_eq!(&wilder[..2], &[None, None]);
+        assert!((wilder[2].unwrap() - 50.0).abs() < 1e-9);
+        assert!((wilder[3].unwrap() - 250.0 / 3.0).abs() < 1e-9);
+        assert!((wilder[4].unwrap() - 50.0).abs() < 1e-9);
+
+        let simple = calculate_rsi(prices.clone(), 2, "simple").unwrap();
+        assert!((simple[4].unwrap() - 200.0 / 3.0).abs() < 1e-9);
+
+        assert!(calculate_rsi(prices.clone(), 2, "ema").is_err());
+        assert!(calculate_rsi(prices, 0, "wilder").is_err());
+        assert!(calculate_rsi(vec![], 0, "wilder").is_err());
+    }
+
+    #[test]
+    fn test_calculate_stochastic() {
+        let highs = vec![10.0, 11.0, 12.0, 13.0, 14.0, 15.0];
+        let lows = vec![8.0, 9.0, 10.0, 11.0, 12.0, 13.0];
+        let closes = vec![9.0, 10.0, 11.0, 12.0, 13.0, 15.0];
+
+        let (k, d) = calculate_stochastic(highs.clone(), lows.clone(), closes.clone(), 3, 2, 1).unwrap();
+        assert_eq!(&k[..2], &[None, None]);
+        // 窗口 [8, 12]，收盘 11 -> 75
+        assert!((k[2].unwrap() - 75.0).abs() < 1e-9);
+        // 窗口 [11, 15]，收盘 15 -> 100
+        assert!((k[5].unwrap() - 100.0).abs() < 1e-9);
+        assert_eq!(d[2], None);
+        assert!((d[3].unwrap() - 75.0).abs() < 1e-9);
+        assert!((d[5].unwrap() - 87.5).abs() < 1e-9);
+
+        let (slow_k, _) = calculate_stochastic(highs.clone(), lows.clone(), closes.clone(), 3, 2, 3).unwrap();
+        assert_eq!(slow_k[3], None);
+        assert!((slow_k[5].unwrap() - (75.0 + 75.0 + 100.0) / 3.0).abs() < 1e-9);
+
+        assert!(calculate_stochastic(highs, lows, vec![1.0], 3, 2, 1).is_err());
+    }
+
+    #[test]
+    fn test_calculate_williams_r() {
+        let highs = vec![10.0, 12.0, 11.0, 13.0];
+        let lows = vec![8.0, 9.0, 9.0, 10.0];
+        let closes = vec![9.0, 11.0, 9.0, 13.0];
+
+        let wr = calculate_williams_r(highs.clone(), lows.clone(), closes, 3).unwrap();
+        assert_eq!(&wr[..2], &[None, None]);
+        // 窗口 [8, 12]，收盘 9 -> -75
+        assert!((wr[2].unwrap() + 75.0).abs() < 1e-9);
+        // 收盘位于窗口最高价 -> 0
+        assert!(wr[3].unwrap().abs() < 1e-9);
+
+        assert!(calculate_williams_r(highs, lows, vec![1.0], 3).is_err());
+    }
+
+    #[test]
+    fn test_calculate_cci() {
+        // 典型价为 1, 2, 3, 4
+        let closes = vec![1.0, 2.0, 3.0, 4.0];
+        let highs: Vec<f64> = closes.iter().map(|c| c + 1.0).collect();
+        let lows: Vec<f64> = closes.iter().map(|c| c - 1.0).collect();
+
+        let cci = calculate_cci(highs, lows, closes, 3).unwrap();
+        assert_eq!(cci[0], None);
+        assert_eq!(cci[1], None);
+        // 窗口 [1,2,3]: sma=2, 平均偏差=2/3, CCI=(3-2)/(0.015*2/3)=100
+        assert!((cci[2].unwrap() - 100.0).abs() < 1e-9);
+        assert!((cci[3].unwrap() - 100.0).abs() < 1e-9);
+
+        let flat = vec![5.0; 4];
+        assert!(calculate_cci(flat.clone(), flat.clone(), flat, 3).unwrap().iter().all(|v| v.is_none()));
+        assert!(calculate_cci(vec![1.0], vec![1.0, 2.0], vec![1.0], 3).is_err());
+    }
+
+    #[test]
+    fn test_calculate_psar() {
+        let highs = vec![10.0, 11.0, 12.0, 13.0, 12.5];
+        let lows = vec![9.0, 10.0, 11.0, 12.0, 8.5];
+        let sar = calculate_psar(highs, lows, 0.02, 0.02, 0.2).unwrap();
+
+        assert_eq!(sar[0], None);
+        assert_eq!(sar[1], Some(9.0));
+        // 9 + 0.02 * (11 - 9) = 9.04 被前两根最低价 9 限制
+        assert_eq!(sar[2], Some(9.0));
+        assert!((sar[3].unwrap() - 9.12).abs() < 1e-12);
+        // 跌破 SAR 反转为下降趋势，SAR 取原极值点 13
+        assert_eq!(sar[4], Some(13.0));
+
+        assert!(calculate_psar(vec![1.0, 2.0], vec![1.0], 0.02, 0.02, 0.2).is_err());
+    }
+
+    #[test]
+    fn test_calculate_donchian_and_keltner() {
+        let highs = vec![10.0, 12.0, 11.0, 13.0, 12.0];
+        let lows = vec![8.0, 9.0, 7.0, 10.0, 11.0];
+        let closes = vec![9.0, 11.0, 10.0, 12.0, 11.5];
+
+        let (upper, middle, lower) = calculate_donchian(highs.clone(), lows.clone(), 3).unwrap();
+        assert_eq!(upper[..2], [None, None]);
+        assert_eq!(upper[2..], [Some(12.0), Some(13.0), Some(13.0)]);
+        assert_eq!(lower[2..], [Some(7.0), Some(7.0), Some(7.0)]);
+        assert_eq!(middle[3], Some(10.0));
+
+        let (k_upper, k_middle, k_lower) =
+            calculate_keltner(highs.clone(), lows.clone(), closes.clone(), 3, 2, 2.0).unwrap();
+        let ema = calculate_ema(&closes, 3);
+        let atr = calculate_atr(highs.clone(), lows.clone(), closes.clone(), 2).unwrap();
+        assert_eq!(k_middle[..2], [None, None]);
+        for i in 2..closes.len() {
+            assert_eq!(k_middle[i], ema[i]);
+            assert!((k_upper[i].unwrap() - (ema[i].unwrap() + 2.0 * atr[i].unwrap())).abs() < 1e-12);
+            assert!((k_lower[i].unwrap() - (ema[i].unwrap() - 2.0 * atr[i].unwrap())).abs() < 1e-12);
+        }
+
+        assert!(calculate_donchian(highs.clone(), vec![1.0], 3).is_err());
+        assert!(calculate_keltner(highs, lows, vec![1.0], 3, 2, 2.0).is_err());
+    }
+
+    #[test]
+    fn test_calculate_roc_and_momentum() {
+        let prices = vec![10.0, 0.0, 11.0, 12.0, 9.0];
+
+        let roc = calculate_roc(prices.clone(), 2).unwrap();
+        assert_eq!(roc[..2], [None, None]);
+        assert!((roc[2].unwrap() - 10.0).abs() < 1e-12);
+        // 基期价格为0
+        assert_eq!(roc[3], None);
+        assert!((roc[4].unwrap() - (-100.0 * 2.0 / 11.0)).abs() < 1e-12);
+
+        let momentum = calculate_momentum(prices, 2).unwrap();
+        assert_eq!(momentum, vec![None, None, Some(1.0), Some(12.0), Some(-2.0)]);
+    }
+
+    #[test]
+    fn test_efficiency_ratio_and_kama() {
+        // 单边直线上涨：ER = 1
+        let trend: Vec<f64> = (0..20).map(|i| 10.0 + i as f64 * 0.5).collect();
+        let er = efficiency_ratio(trend.clone(), 10).unwrap();
+        assert!(er[..10].iter().all(|v| v.is_none()));
+        assert!(er[10..].iter().all(|v| (v.unwrap() - 1.0).abs() < 1e-12));
+
+        // 来回震荡：ER 接近0
+        let whipsaw: Vec<f64> = (0..21).map(|i| if i % 2 == 0 { 10.0 } else { 11.0 }).collect();
+        let er = efficiency_ratio(whipsaw.clone(), 10).unwrap();
+        assert!(er[10..].iter().all(|v| v.unwrap() < 0.15));
+
+        // 价格无变动时为 None
+        assert_eq!(efficiency_ratio(vec![5.0; 4], 2).unwrap(), vec![None; 4]);
+
+        // ER = 1 时 KAMA 以快速系数跟随趋势
+        let values = kama(trend.clone(), 10, 2, 30).unwrap();
+        assert!(values[..9].iter().all(|v| v.is_none()));
+        assert_eq!(values[9], Some(trend[9]));
+        let expected = trend[9] + (2.0f64 / 3.0).powi(2) * (trend[10] - trend[9]);
+        assert!((values[10].unwrap() - expected).abs() < 1e-12);
+
+        // 震荡时 KAMA 几乎不动
+        let values = kama(whipsaw, 10, 2, 30).unwrap();
+        assert!((values[20].unwrap() - values[9].unwrap()).abs() < 0.1);
+
+        assert!(kama(trend, 0, 2, 30).is_err());
+    }
+
+    #[test]
+    fn test_calculate_obv() {
+        let closes = vec![10.0, 11.0, 10.5, 10.5, 12.0];
+        let volumes = vec![100.0, 200.0, 150.0, 300.0, 50.0];
+        let obv = calculate_obv(closes, volumes).unwrap();
+        assert_eq!(obv, vec![0.0, 200.0, 50.0, 50.0, 100.0]);
+
+        assert!(calculate_obv(vec![1.0, 2.0], vec![100.0]).is_err());
+    }
+
+    #[test]
+    fn test_calculate_adx_uptrend() {
+        // 单边上涨: +DM 恒为1，-DM 恒为0 -> -DI = 0，DX = ADX = 100
+        let highs: Vec<f64> = (0..20).map(|i| 11.0 + i as f64).collect();
+        let lows: Vec<f64> = (0..20).map(|i| 10.0 + i as f64).collect();
+        let closes: Vec<f64> = (0..20).map(|i| 10.5 + i as f64).collect();
+
+        let (adx, plus_di, minus_di) = calculate_adx(highs, lows, closes, 5).unwrap();
+        assert_eq!(plus_di[4], None);
+        assert!(plus_di[5].unwrap() > 50.0);
+        assert_eq!(minus_di[5], Some(0.0));
+        assert_eq!(adx[8], None);
+        assert!((adx[9].unwrap() - 100.0).abs() < 1e-9);
+        assert!((adx[19].unwrap() - 100.0).abs() < 1e-9);
+
+        assert!(calculate_adx(vec![1.0], vec![], vec![1.0], 5).is_err());
+    }
+
+    #[test]
+    fn test_combined_sells_at_bb_upper() {
+        pyo3::prepare_freethreaded_python();
+
+        // 30 根小幅震荡后连续上涨，最后一根大幅拉升至布林上轨之上
+        let mut prices: Vec<f64> = (0..30).map(|i| 100.0 + if i % 2 == 0 { 0.1 } else { -0.1 }).collect();
+        prices.extend([101.0, 102.0, 103.0, 104.0, 110.0]);
+        let timestamps: Vec<i64> = (0..prices.len() as i64).collect();
+        let last = prices.len() - 1;
+
+        let (upper, _, _) = calculate_bollinger_bands(prices.clone(), 20, 2.0).unwrap();
+        assert!(prices[last] >= upper[last].unwrap());
+
+        let signals = generate_signals("TEST".to_string(), prices, timestamps, "combined", "{}", false, 0).unwrap();
+        assert!(signal_pairs(&signals).contains(&(last as i64, "sell".to_string())));
+    }
+
+    #[test]
+    fn test_macd_signal_line_warmup() {
+        let prices: Vec<f64> = (0..40).map(|i| 100.0 + (i as f64 / 3.0).sin() * 5.0).collect();
+        let (macd_line, signal_line, histogram) = calculate_macd(prices, 12, 26, 9).unwrap();
+
+        // MACD 线自首根K线起有效，信号线第一个值出现在第 9 根 (索引8)
+        let first_macd = macd_line.iter().position(|v| v.is_some()).unwrap();
+        let first_signal = signal_line.iter().position(|v| v.is_some()).unwrap();
+        assert_eq!(first_signal, first_macd + 9 - 1);
+        assert!(histogram[..first_signal].iter().all(|v| v.is_none()));
+
+        let seed = macd_line[first_macd..=first_signal].iter().flatten().sum::<f64>() / 9.0;
+        assert!((signal_line[first_signal].unwrap() - seed).abs() < 1e-12);
+
+        // 前导 None 不参与初值
+        let values = vec![None, None, Some(1.0), Some(2.0), Some(3.0), Some(4.0), None, Some(5.0)];
+        let ema = calculate_ema_from_values(&values, 3);
+        assert_eq!(ema, vec![None, None, None, None, Some(2.0), Some(3.0), None, None]);
+    }
+
+    #[test]
+    fn test_macd_signal_period_param() {
+        pyo3::prepare_freethreaded_python();
+
+        let prices: Vec<f64> = (0..120).map(|i| 100.0 + 10.0 * (i as f64 / 7.0).sin() + i as f64 * 0.05).collect();
+        let timestamps: Vec<i64> = (0..prices.len() as i64).collect();
+
+        let default = generate_signals("TEST".to_string(), prices.clone(), timestamps.clone(), "macd", "{}", false, 0).unwrap();
+        let explicit = generate_signals(
+            "TEST".to_string(), prices.clone(), timestamps.clone(), "macd", r#"{"fast":12,"slow":26,"signal":9}"#, false, 0,
+        ).unwrap();
+        let fast_signal = generate_signals(
+            "TEST".to_string(), prices, timestamps, "macd", r#"{"fast":12,"slow":26,"signal":5}"#, false, 0,
+        ).unwrap();
+
+        assert_eq!(signal_pairs(&default), signal_pairs(&explicit));
+        assert!(!signal_pairs(&fast_signal).is_empty());
+        assert_ne!(signal_pairs(&default), signal_pairs(&fast_signal));
+    }
+
+    #[test]
+    fn test_generate_signals_include_hold() {
+        pyo3::prepare_freethreaded_python();
+
+        let prices: Vec<f64> = (0..60).map(|i| 100.0 + 5.0 * (i as f64 / 4.0).sin()).collect();
+        let timestamps: Vec<i64> = (0..prices.len() as i64).collect();
+
+        let transitions = generate_signals(
+            "TEST".to_string(), prices.clone(), timestamps.clone(), "rsi", "{}", false, 0,
+        ).unwrap();
+        let timeline = generate_signals(
+            "TEST".to_string(), prices.clone(), timestamps, "rsi", "{}", true, 0,
+        ).unwrap();
+
+        // 每个有RSI值的位置都输出一条信号
+        let evaluated = calculate_rsi(prices, 14, "simple").unwrap().iter().flatten().count();
+        assert_eq!(timeline.len(), evaluated);
+
+        let pairs = signal_pairs(&timeline);
+        let holds: Vec<_> = pairs.iter().filter(|(_, s)| s == "hold").collect();
+        assert_eq!(holds.len(), timeline.len() - transitions.len());
+        assert!(!holds.is_empty());
+        for pair in signal_pairs(&transitions) {
+            assert!(pairs.contains(&pair));
+        }
+    }
+
+    #[test]
+    fn test_rsi_signal_confidence() {
+        pyo3::prepare_freethreaded_python();
+
+        let prices: Vec<f64> = (0..80).map(|i| 100.0 + 8.0 * (i as f64 / 6.0).sin()).collect();
+        let timestamps: Vec<i64> = (0..prices.len() as i64).collect();
+        let signals = generate_signals("TEST".to_string(), prices, timestamps, "rsi", "{}", false, 0).unwrap();
+        assert!(!signals.is_empty());
+
+        Python::with_gil(|py| {
+            for obj in &signals {
+                let dict = obj.downcast_bound::<PyDict>(py).unwrap();
+                let rsi: f64 = dict.get_item("indicator_value").unwrap().unwrap().extract().unwrap();
+                let confidence: f64 = dict.get_item("confidence").unwrap().unwrap().extract().unwrap();
+                let signal: String = dict.get_item("signal").unwrap().unwrap().extract().unwrap();
+
+                let expected = if signal == "buy" { (30.0 - rsi) / 30.0 } else { (rsi - 70.0) / 30.0 };
+                assert!((confidence - expected).abs() < 1e-12);
+                assert!((0.0..=1.0).contains(&confidence));
+            }
+        });
+    }
+
+    #[test]
+    fn test_generate_signals_batch_matches_single() {
+        pyo3::prepare_freethreaded_python();
+
+        let symbols = vec!["600519".to_string(), "000001".to_string()];
+        let prices_list: Vec<Vec<f64>> = vec![
+            (0..80).map(|i| 100.0 + 8.0 * (i as f64 / 6.0).sin()).collect(),
+            (0..60).map(|i| 20.0 + 2.0 * (i as f64 / 3.0).cos()).collect(),
+        ];
+        let timestamps_list: Vec<Vec<i64>> = prices_list.iter()
+            .map(|p| (0..p.len() as i64).collect())
+            .collect();
+
+        let batch = Python::with_gil(|py| {
+            generate_signals_batch(
+                py, symbols.clone(), prices_list.clone(), timestamps_list.clone(), "rsi", "{}", false, 0,
+            )
+        }).unwrap();
+
+        assert_eq!(batch.len(), 2);
+        for (i, symbol) in symbols.iter().enumerate() {
+            let single = generate_signals(
+                symbol.clone(), prices_list[i].clone(), timestamps_list[i].clone(), "rsi", "{}", false, 0,
+            ).unwrap();
+            assert_eq!(signal_pairs(&batch[symbol]), signal_pairs(&single));
+        }
+
+        let err = Python::with_gil(|py| {
+            generate_signals_batch(py, symbols, prices_list, vec![], "rsi", "{}", false, 0)
+        }).unwrap_err();
+        assert!(err.to_string().contains("same length"));
+    }
+
+    #[test]
+    fn test_min_bars_between_collapses_runs() {
+        let record = |index: usize, signal: Signal| {
+            create_signal("TEST", index, index as i64, signal, SignalStrength::Strong, 1.0, 0.0, String::new(), 0.0)
+        };
+        let signals = vec![
+            record(10, Signal::Buy),
+            record(11, Signal::Buy),
+            record(12, Signal::Hold),
+            record(12, Signal::Buy),
+            record(13, Signal::Sell),
+            record(15, Signal::Buy),
+            record(16, Signal::Sell),
+        ];
+        let indices = |signals: Vec<SignalRecord>| signals.iter().map(|r| r.index).collect::<Vec<_>>();
+
+        assert_eq!(indices(space_out_signals(signals.clone(), 0)), vec![10, 11, 12, 12, 13, 15, 16]);
+        // 10..12 的连续买入合并为一个 (Hold 保留)；15、16 前各有2根K线没有同向信号
+        assert_eq!(indices(space_out_signals(signals.clone(), 1)), vec![10, 12, 13, 15, 16]);
+        assert_eq!(indices(space_out_signals(signals, 3)), vec![10, 12, 13]);
+    }
+
+    #[test]
+    fn test_signal_hit_rate() {
+        pyo3::prepare_freethreaded_python();
+
+        // 先涨后跌再涨
+        let prices = vec![10.0, 11.0, 12.0, 13.0, 12.0, 11.0, 10.0, 11.0, 12.0];
+        let timestamps: Vec<i64> = (0..prices.len() as i64).map(|i| 1_000 + i * 60_000).collect();
+
+        let record = |index: usize, signal: Signal| {
+            create_signal("TEST", index, timestamps[index], signal, SignalStrength::Strong, prices[index], 0.0, String::new(), 0.0)
+        };
+        let records = [
+            record(0, Signal::Buy),   // 10 -> 12 命中
+            record(3, Signal::Sell),  // 13 -> 11 命中
+            record(4, Signal::Hold),  // 不统计
+            record(5, Signal::Buy),   // 11 -> 11 未命中
+            record(6, Signal::Buy),   // 10 -> 12 命中
+            record(8, Signal::Sell),  // 之后不足2根，不统计
+        ];
+
+        Python::with_gil(|py| {
+            let signals: Vec<PyObject> = records.iter().map(|r| r.to_object(py).unwrap()).collect();
+            let rate = signal_hit_rate(py, signals, prices.clone(), timestamps.clone(), 2).unwrap();
+            assert!((rate - 0.75).abs() < 1e-12);
+
+            let bad = PyDict::new(py);
+            bad.set_item("timestamp", timestamps[0]).unwrap();
+            assert!(signal_hit_rate(py, vec![bad.into()], prices.clone(), timestamps.clone(), 2).is_err());
+        });
+    }
+
+    #[test]
+    fn test_mean_reversion_signals() {
+        pyo3::prepare_freethreaded_python();
+
+        // 平稳震荡后急跌，再回升至均值上方
+        let mut prices: Vec<f64> = (0..20).map(|i| if i % 2 == 0 { 100.0 } else { 101.0 }).collect();
+        prices.extend([92.0, 95.0, 99.0, 103.0, 104.0]);
+        let timestamps: Vec<i64> = (0..prices.len() as i64).collect();
+
+        let signals = generate_signals(
+            "TEST".to_string(), prices, timestamps, "mean_reversion", r#"{"period": 20, "z": 2.0}"#, false, 0,
+        ).unwrap();
+        assert_eq!(signal_pairs(&signals), vec![(20, "buy".to_string()), (23, "sell".to_string())]);
+
+        Python::with_gil(|py| {
+            let dict = signals[0].downcast_bound::<PyDict>(py).unwrap();
+            let reason: String = dict.get_item("reason").unwrap().unwrap().extract().unwrap();
+            let z: f64 = dict.get_item("indicator_value").unwrap().unwrap().extract().unwrap();
+            assert!(z < -2.0);
+            assert!(reason.contains(&format!("z={:.2}", z)));
+        });
+    }
+
+    #[test]
+    fn test_trailing_stop_signals() {
+        pyo3::prepare_freethreaded_python();
+
+        // 每根K线振幅为2，上涨至110后回落
+        let closes = vec![100.0, 104.0, 108.0, 110.0, 109.0, 106.0, 103.0, 104.0];
+        let highs: Vec<f64> = closes.iter().map(|c| c + 1.0).collect();
+        let lows: Vec<f64> = closes.iter().map(|c| c - 1.0).collect();
+        let timestamps: Vec<i64> = (0..closes.len() as i64).collect();
+
+        let atr = calculate_atr(highs.clone(), lows.clone(), closes.clone(), 3).unwrap();
+        let signals = trailing_stop_signals(highs, lows, closes.clone(), timestamps, 3, 1.5, "TEST".to_string()).unwrap();
+        let pairs = signal_pairs(&signals);
+
+        // 第一次跌破 110 - 1.5 * ATR 的位置
+        let first_stop = (0..closes.len())
+            .find(|&i| i > 3 && closes[i] < 110.0 - 1.5 * atr[i].unwrap())
+            .unwrap();
+        assert_eq!(pairs[0], (first_stop as i64, "sell".to_string()));
+
+        assert!(trailing_stop_signals(vec![1.0], vec![1.0], vec![1.0], vec![], 3, 2.0, String::new()).is_err());
+    }
+
+    #[test]
+    fn test_generate_signals_rejects_malformed_params() {
+        pyo3::prepare_freethreaded_python();
+
+        let prices: Vec<f64> = (0..30).map(|i| 100.0 + i as f64).collect();
+        let timestamps: Vec<i64> = (0..30).collect();
+
+        assert!(generate_signals("TEST".to_string(), prices.clone(), timestamps.clone(), "rsi", "", false, 0).is_ok());
+        assert!(generate_signals("TEST".to_string(), prices.clone(), timestamps.clone(), "rsi", "{}", false, 0).is_ok());
+        let err = generate_signals("TEST".to_string(), prices, timestamps, "rsi", r#"{"period": "14"}"#, false, 0).unwrap_err();
+        assert!(err.to_string().contains("Invalid params JSON"));
+    }
+
+    /// 提取信号字典中的 (timestamp, signal)
+    fn signal_pairs(signals: &[PyObject]) -> Vec<(i64, String)> {
+        Python::with_gil(|py| {
+            signals.iter()
+                .map(|obj| {
+                    let signal = obj.downcast_bound::<PyDict>(py).unwrap();
+                    let timestamp: i64 = signal.get_item("timestamp").unwrap().unwrap().extract().unwrap();
+                    let side: String = signal.get_item("signal").unwrap().unwrap().extract().unwrap();
+                    (timestamp, side)
+                })
+                .collect()
+        })
+    }
+
+    #[test]
+    fn test_find_divergences() {
+        // 价格两次探底 (10 -> 9)，指标低点抬高 (20 -> 30) -> 底背离
+        let prices = vec![12.0, 11.0, 10.0, 11.0, 12.0, 11.0, 9.0, 11.0, 12.0];
+        let indicator: Vec<Option<f64>> = vec![40.0, 30.0, 20.0, 35.0, 45.0, 40.0, 30.0, 40.0, 50.0]
+            .into_iter()
+            .map(Some)
+            .collect();
+
+        let found = find_divergences(&prices, &indicator, 10, 2);
+        assert_eq!(found, vec![Divergence { index: 8, kind: "bullish", first_pivot: 2, second_pivot: 6 }]);
+
+        // 间距超过 lookback 时不视为背离
+        assert!(find_divergences(&prices, &indicator, 3, 2).is_empty());
+
+        // 镜像: 价格创新高、指标走低 -> 顶背离
+        let inverted: Vec<f64> = prices.iter().map(|p| 24.0 - p).collect();
+        let inverted_ind: Vec<Option<f64>> = indicator.iter().map(|v| v.map(|x| 100.0 - x)).collect();
+        let found = find_divergences(&inverted, &inverted_ind, 10, 2);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, "bearish");
+    }
+
+    /// 形态名称列表
+    fn pattern_names(klines: &[Kline], config: &PatternThresholds) -> Vec<(usize, &'static str)> {
+        detect_candlestick_patterns(klines, config)
+            .iter()
+            .map(|p| (p.index, p.pattern))
+            .collect()
+    }
+
+    #[test]
+    fn test_candlestick_patterns() {
+        let config = PatternThresholds::default();
+
+        // 十字星：开收几乎相同，上下影线均长
+        let doji = [(0, 10.0, 11.0, 9.0, 10.05, 0.0)];
+        assert_eq!(pattern_names(&doji, &config), vec![(0, "doji")]);
+
+        // 锤子线：小实体在顶部，长下影线
+        let hammer = [(0, 10.0, 10.6, 8.0, 10.5, 0.0)];
+        assert_eq!(pattern_names(&hammer, &config), vec![(0, "hammer")]);
+
+        // 流星线：小实体在底部，长上影线
+        let star = [(0, 10.5, 13.0, 9.95, 10.0, 0.0)];
+        assert_eq!(pattern_names(&star, &config), vec![(0, "shooting_star")]);
+
+        // 看涨吞没：阴线后阳线实体包住前一根
+        let bullish = [(0, 10.5, 10.6, 9.9, 10.0, 0.0), (1, 9.9, 11.0, 9.8, 10.8, 0.0)];
+        assert_eq!(pattern_names(&bullish, &config), vec![(1, "bullish_engulfing")]);
+
+        // 看跌吞没：阳线后阴线实体包住前一根
+        let bearish = [(0, 10.0, 10.6, 9.9, 10.5, 0.0), (1, 10.6, 10.7, 9.6, 9.8, 0.0)];
+        assert_eq!(pattern_names(&bearish, &config), vec![(1, "bearish_engulfing")]);
+
+        // 放宽十字星阈值后普通阳线也被识别为十字星
+        let loose = PatternThresholds { doji_body_ratio: 0.9, ..PatternThresholds::default() };
+        assert_eq!(pattern_names(&bullish[1..], &loose), vec![(0, "doji")]);
+    }
+
+    #[test]
+    fn test_candlestick_patterns_rejects_unknown_threshold() {
+        let mut thresholds = HashMap::new();
+        thresholds.insert("body".to_string(), 0.2);
+        assert!(candlestick_patterns(vec![], Some(thresholds)).is_err());
+    }
+
+    #[test]
+    fn test_volatility_report_matches_implied_range() {
+        // 线性同余伪随机游走，每根K线由16步组成，开盘价等于前收盘价
+        let mut seed: u64 = 42;
+        let mut next_step = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            if (seed >> 33) & 1 == 0 { 0.0025 } else { -0.0025 }
+        };
+        let mut klines = Vec::new();
+        let mut close = 100.0f64;
+        for day in 0..60 {
+            let open = close;
+            let (mut high, mut low) = (open, open);
+            for _ in 0..16 {
+                close *= next_step() + 1.0;
+                high = high.max(close);
+                low = low.min(close);
+            }
+            klines.push((day as i64 * 86_400_000, open, high, low, close, 1_000.0));
+        }
+
+        let report = volatility_report(klines.clone(), "美股", None).unwrap();
+        for key in ["atr", "atr_pct", "annualized_volatility", "daily_range_pct"] {
+            assert!(report[key] > 0.0, "{} = {}", key, report[key]);
+        }
+
+        // 布朗运动日内振幅期望约为 sqrt(8/π) 倍日波动率
+        let daily_vol = report["annualized_volatility"] / US_TRADING_DAYS.sqrt();
+        let implied_range = (8.0 / std::f64::consts::PI).sqrt() * daily_vol;
+        let ratio = report["atr_pct"] / implied_range;
+        assert!((0.6..1.5).contains(&ratio), "atr_pct / implied range = {}", ratio);
+
+        let a_share = volatility_report(klines.clone(), "A股", None).unwrap();
+        assert!(a_share["annualized_volatility"] < report["annualized_volatility"]);
+        assert!(volatility_report(klines.clone(), "期货", None).is_err());
+        assert!(volatility_report(klines[..20].to_vec(), "美股", None).is_err());
+
+        // 显式传入每年周期数时覆盖市场默认值
+        let weekly = volatility_report(klines.clone(), "美股", Some(52.0)).unwrap();
+        let expected = report["annualized_volatility"] * (52.0 / US_TRADING_DAYS).sqrt();
+        assert!((weekly["annualized_volatility"] - expected).abs() < 1e-9);
+        assert_eq!(weekly["atr"], report["atr"]);
+        let overridden = volatility_report(klines.clone(), "A股", Some(US_TRADING_DAYS)).unwrap();
+        assert!((overridden["annualized_volatility"] - report["annualized_volatility"]).abs() < 1e-9);
+        assert!(volatility_report(klines.clone(), "美股", Some(0.0)).is_err());
+    }
+
+    #[test]
+    fn test_dedupe_signals_collapses_cluster() {
+        pyo3::prepare_freethreaded_python();
+        let make = |index: usize, signal, strength, confidence| {
+            create_signal(
+                "600519", index, index as i64, signal, strength, 10.0, 0.0, String::new(), confidence,
+            )
+        };
+        let records = [
+            make(10, Signal::Buy, SignalStrength::Weak, 0.2),
+            make(11, Signal::Buy, SignalStrength::Strong, 0.4),
+            make(12, Signal::Hold, SignalStrength::Weak, 0.0),
+            make(13, Signal::Buy, SignalStrength::Strong, 0.3),
+            make(30, Signal::Sell, SignalStrength::Moderate, 0.5),
+            make(40, Signal::Buy, SignalStrength::Weak, 0.1),
+        ];
+
+        Python::with_gil(|py| {
+            let objects: Vec<PyObject> = records.iter().map(|r| r.to_object(py).unwrap()).collect();
+            let deduped = dedupe_signals(py, objects, 3).unwrap();
+            let indices: Vec<usize> = deduped.iter()
+                .map(|obj| obj.bind(py).get_item("index").unwrap().extract().unwrap())
+                .collect();
+            // 三个聚集的买入只保留最强的一个，Hold 与远处的信号保留
+            assert_eq!(indices, vec![11, 12, 30, 40]);
+        });
+
+        // 反方向信号结束当前簇
+        let events = [
+            (0, Signal::Buy, 0, 0.0),
+            (1, Signal::Sell, 0, 0.0),
+            (2, Signal::Buy, 2, 0.0),
+        ];
+        assert_eq!(dedupe_events(&events, 5), vec![true, true, true]);
+    }
+
+    #[test]
+    fn test_adaptive_rsi_signals_differ_from_fixed() {
+        pyo3::prepare_freethreaded_python();
+
+        // 涨跌交替、幅度缓慢变化：RSI 始终在 30-70 之间，固定阈值不产生信号
+        let mut prices = vec![100.0];
+        for i in 1..120 {
+            let step = if i % 2 == 0 { 1.0 } else { -1.0 } + 0.3 * (i as f64 * 0.2).sin();
+            prices.push(prices[i - 1] + step);
+        }
+        let timestamps: Vec<i64> = (0..prices.len() as i64).collect();
+
+        let fixed = generate_signals("TEST".to_string(), prices.clone(), timestamps.clone(), "rsi", "", false, 0).unwrap();
+        assert!(fixed.is_empty());
+
+        let adaptive = adaptive_rsi_signals(prices.clone(), 14, 20, 10.0, 90.0, Some(timestamps), "TEST".to_string()).unwrap();
+        let pairs = signal_pairs(&adaptive);
+        assert!(pairs.iter().any(|(_, s)| s == "buy"));
+        assert!(pairs.iter().any(|(_, s)| s == "sell"));
+
+        // 排名定义：窗口内低于当前值的个数 / (窗口 - 1)
+        let ranks = rolling_percent_rank(&[Some(1.0), Some(3.0), Some(2.0), None, Some(5.0)], 3);
+        assert_eq!(ranks, [None, None, Some(50.0), None, None]);
+
+        assert!(adaptive_rsi_signals(prices, 14, 1, 10.0, 90.0, None, String::new()).is_err());
+    }
+}
@@ -48,6 +48,17 @@ fn calculate_wordcloud(texts: Vec<String>) -> PyResult<PyObject> {
     })
 }
 
+/// 高级分词：按空白与中文标点切分，保留长度不小于 `min_len` 的词
+fn tokenize_advanced(text: &str, min_len: usize) -> Vec<String> {
+    let separators = ['，', '。', '！', '？', '、', '；', '：', '"', '\'', '（', '）', '【', '】', '《', '》'];
+
+    // 支持多种分隔符
+    text.split(|c: char| c.is_whitespace() || separators.contains(&c))
+        .map(|word| word.chars().filter(|c| !c.is_whitespace()).collect::<String>())
+        .filter(|word| word.len() >= min_len)
+        .collect()
+}
+
 /// 高级词云统计（支持自定义分隔符）
 ///
 /// # 参数
@@ -60,19 +71,10 @@ fn calculate_wordcloud(texts: Vec<String>) -> PyResult<PyObject> {
 fn calculate_wordcloud_advanced(texts: Vec<String>, min_length: Option<usize>) -> PyResult<PyObject> {
     let min_len = min_length.unwrap_or(1);
     let mut word_count: HashMap<String, usize> = HashMap::new();
-    let separators = ['，', '。', '！', '？', '、', '；', '：', '"', '\'', '（', '）', '【', '】', '《', '》'];
 
     for text in texts {
-        // 支持多种分隔符
-        for word in text.split(|c: char| c.is_whitespace() || separators.contains(&c)) {
-            let clean_word = word
-                .chars()
-                .filter(|c| !c.is_whitespace())
-                .collect::<String>();
-
-            if clean_word.len() >= min_len {
-                *word_count.entry(clean_word).or_insert(0) += 1;
-            }
+        for word in tokenize_advanced(&text, min_len) {
+            *word_count.entry(word).or_insert(0) += 1;
         }
     }
 
@@ -85,11 +87,50 @@ fn calculate_wordcloud_advanced(texts: Vec<String>, min_length: Option<usize>) -
     })
 }
 
+/// 基于极性词典的文本情感得分
+///
+/// 使用高级分词切分每条文本，累加命中词典的词的极性分值，未命中的词计 0
+///
+/// # 参数
+/// * `texts` - 文本字符串列表
+/// * `lexicon` - 极性词典，键为词，值为极性分值（正为利好，负为利空）
+/// * `min_length` - 最小词长度（默认为1）
+/// * `normalize` - 是否按词数归一化（默认否）
+///
+/// # 返回
+/// 每条文本的情感得分列表；归一化时无词文本得分为 0
+#[pyfunction(signature = (texts, lexicon, min_length=1, normalize=false))]
+fn lexicon_sentiment(
+    texts: Vec<String>,
+    lexicon: HashMap<String, f64>,
+    min_length: usize,
+    normalize: bool,
+) -> PyResult<Vec<f64>> {
+    Ok(texts
+        .iter()
+        .map(|text| {
+            let tokens = tokenize_advanced(text, min_length);
+            let score: f64 = tokens
+                .iter()
+                .map(|token| lexicon.get(token).copied().unwrap_or(0.0))
+                .sum();
+            if !normalize {
+                score
+            } else if tokens.is_empty() {
+                0.0
+            } else {
+                score / tokens.len() as f64
+            }
+        })
+        .collect())
+}
+
 /// Rust 模块定义
 #[pymodule]
 fn tacn_wordcloud(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(calculate_wordcloud, m)?)?;
     m.add_function(wrap_pyfunction!(calculate_wordcloud_advanced, m)?)?;
+    m.add_function(wrap_pyfunction!(lexicon_sentiment, m)?)?;
     Ok(())
 }
 
@@ -121,4 +162,25 @@ mod tests {
         assert_eq!(word_count.get("AI"), Some(&2));
         assert_eq!(word_count.get("股票"), Some(&2));
     }
+
+    #[test]
+    fn test_lexicon_sentiment() {
+        let lexicon: HashMap<String, f64> = [
+            ("利好".to_string(), 1.0),
+            ("增长".to_string(), 0.5),
+            ("风险".to_string(), -0.8),
+        ]
+        .into_iter()
+        .collect();
+        let texts = vec!["业绩 增长，重大 利好；注意 风险".to_string(), String::new()];
+
+        let scores = lexicon_sentiment(texts.clone(), lexicon.clone(), 1, false).unwrap();
+        assert!((scores[0] - 0.7).abs() < 1e-9);
+        assert_eq!(scores[1], 0.0);
+
+        // 6 个词归一化
+        let scores = lexicon_sentiment(texts, lexicon, 1, true).unwrap();
+        assert!((scores[0] - 0.7 / 6.0).abs() < 1e-9);
+        assert_eq!(scores[1], 0.0);
+    }
 }